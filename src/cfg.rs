@@ -0,0 +1,533 @@
+//! Basic blocks of a function and their layout as boxes joined by arrows.
+
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+use crate::disasm::{Flow, Step};
+
+/// Arrows that don't fit in this many columns left of the boxes are only
+/// named in the box footer.
+const MAX_LANES: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Into the next block: the last instruction doesn't branch, or is a
+    /// conditional branch that is not taken.
+    FallThrough,
+    /// A taken conditional branch.
+    Conditional,
+    Unconditional,
+    /// Through a register or memory, the target is not known.
+    Indirect,
+}
+
+impl EdgeKind {
+    fn color(self) -> Color {
+        match self {
+            EdgeKind::FallThrough => Color::Red,
+            EdgeKind::Conditional => Color::Green,
+            EdgeKind::Unconditional => Color::Blue,
+            EdgeKind::Indirect => Color::Yellow,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Edge {
+    pub kind: EdgeKind,
+    /// The block it leads to; `None` when it leaves the function or is indirect.
+    pub to: Option<usize>,
+    pub target: Option<u64>,
+}
+
+pub struct BasicBlock {
+    pub start: u64,
+    /// Lines of the listing holding its instructions.
+    pub lines: Range<usize>,
+    pub edges: Vec<Edge>,
+}
+
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl Cfg {
+    /// Split the instructions of a listing into basic blocks. `instructions`
+    /// has one entry per listing line; labels and blank lines also end a block.
+    pub fn build(instructions: &[Option<Step>]) -> Cfg {
+        let steps: Vec<(usize, Step)> = instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(line, step)| step.map(|step| (line, step)))
+            .collect();
+        let index: HashMap<u64, usize> = steps
+            .iter()
+            .enumerate()
+            .map(|(i, (_, step))| (step.address, i))
+            .collect();
+
+        // 块的起点: 第一条指令, 跳转目标, 以及跳转之后的指令
+        let mut leaders = BTreeSet::from([0]);
+        for (i, &(line, step)) in steps.iter().enumerate() {
+            if let Flow::Branch(target) | Flow::Jump(Some(target)) = step.flow {
+                if let Some(&leader) = index.get(&target) {
+                    leaders.insert(leader);
+                }
+            }
+            let gap = steps.get(i + 1).is_some_and(|&(next, _)| next != line + 1);
+            if step.flow.ends_run() || gap {
+                leaders.insert(i + 1);
+            }
+        }
+        let starts: Vec<usize> = leaders.into_iter().filter(|&i| i < steps.len()).collect();
+        let block_of: HashMap<usize, usize> = starts
+            .iter()
+            .enumerate()
+            .map(|(block, &first)| (first, block))
+            .collect();
+        let to_block = |target: u64| index.get(&target).and_then(|i| block_of.get(i)).copied();
+
+        let blocks = starts
+            .iter()
+            .enumerate()
+            .map(|(block, &first)| {
+                let last = starts.get(block + 1).map_or(steps.len(), |&next| next) - 1;
+                let step = steps[last].1;
+                let fall_through = starts.get(block + 1).map(|&next| Edge {
+                    kind: EdgeKind::FallThrough,
+                    to: Some(block + 1),
+                    target: Some(steps[next].1.address),
+                });
+                let jump = |kind, target: Option<u64>| Edge {
+                    kind,
+                    to: target.and_then(to_block),
+                    target,
+                };
+                let edges = match step.flow {
+                    Flow::Next | Flow::Call(_) => fall_through.into_iter().collect(),
+                    Flow::Branch(target) => {
+                        std::iter::once(jump(EdgeKind::Conditional, Some(target)))
+                            .chain(fall_through)
+                            .collect()
+                    }
+                    Flow::Jump(Some(target)) => vec![jump(EdgeKind::Unconditional, Some(target))],
+                    Flow::Jump(None) => vec![jump(EdgeKind::Indirect, None)],
+                    Flow::Stop => vec![],
+                };
+                BasicBlock {
+                    start: steps[first].1.address,
+                    lines: steps[first].0..steps[last].0 + 1,
+                    edges,
+                }
+            })
+            .collect();
+        Cfg { blocks }
+    }
+
+    /// The block showing listing line `line`.
+    pub fn block_of_line(&self, line: usize) -> Option<usize> {
+        self.blocks
+            .iter()
+            .position(|block| block.lines.contains(&line))
+    }
+}
+
+/// One row of the drawing.
+#[derive(Debug, Clone, Copy)]
+enum Row {
+    Top(usize),
+    /// A block and the listing line it shows.
+    Code(usize, usize),
+    Bottom(usize),
+    /// Below a block, where the fall-through arrow goes.
+    Gap(usize),
+}
+
+const UP: u8 = 1;
+const DOWN: u8 = 2;
+const LEFT: u8 = 4;
+const RIGHT: u8 = 8;
+
+/// The blocks of a function stacked in address order, with the jumps between
+/// them drawn as arrows in the columns on their left.
+pub struct Graph {
+    pub cfg: Cfg,
+    rows: Vec<Row>,
+    /// Line-drawing directions and color of each cell left of the boxes.
+    gutter: Vec<Vec<(u8, Color)>>,
+    /// Rows where an arrow enters a box.
+    arrows: Vec<Option<Color>>,
+    /// Width inside the box borders.
+    width: usize,
+    titles: Vec<String>,
+    footers: Vec<String>,
+    pub scroll: usize,
+}
+
+impl Graph {
+    /// Lay out `cfg`, whose instructions are shown by `lines`. The first block
+    /// is titled `name`; targets outside of the function are named by `name_of`.
+    pub fn layout(cfg: Cfg, lines: &[Line], name: &str, name_of: impl Fn(u64) -> String) -> Graph {
+        let titles: Vec<String> = cfg
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| match i {
+                0 => name.to_string(),
+                _ => format!("loc_{:X}", block.start),
+            })
+            .collect();
+        let footers: Vec<String> = cfg
+            .blocks
+            .iter()
+            .map(|block| {
+                let conditional = block.edges.iter().any(|e| e.kind == EdgeKind::Conditional);
+                let target = |edge: &Edge| match (edge.to, edge.target) {
+                    (Some(to), _) => titles[to].clone(),
+                    (None, Some(target)) => name_of(target),
+                    (None, None) => String::from("?"),
+                };
+                block
+                    .edges
+                    .iter()
+                    .filter_map(|edge| match edge.kind {
+                        EdgeKind::Conditional => Some(format!("taken → {}", target(edge))),
+                        EdgeKind::FallThrough if conditional => {
+                            Some(format!("else → {}", target(edge)))
+                        }
+                        EdgeKind::FallThrough => None,
+                        EdgeKind::Unconditional => Some(format!("→ {}", target(edge))),
+                        EdgeKind::Indirect => Some(String::from("→ indirect")),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("  ")
+            })
+            .collect();
+
+        let mut rows = vec![];
+        // 每个块的标题行和最后一条指令所在的行, 箭头的两端
+        let mut tops = vec![];
+        let mut ends = vec![];
+        let mut width = 0;
+        for (i, block) in cfg.blocks.iter().enumerate() {
+            tops.push(rows.len());
+            rows.push(Row::Top(i));
+            for line in block.lines.clone() {
+                rows.push(Row::Code(i, line));
+                width = width.max(lines.get(line).map_or(0, |line| code(line).width()));
+            }
+            ends.push(rows.len() - 1);
+            rows.push(Row::Bottom(i));
+            rows.push(Row::Gap(i));
+            width = width
+                .max(titles[i].chars().count() + 4)
+                .max(footers[i].chars().count() + 4);
+        }
+        let width = width + 2;
+
+        // 每条跳转占一列, 短的离方框近; 放不下的只在方框底部写出目标
+        let mut jumps: Vec<(usize, usize, Color)> = cfg
+            .blocks
+            .iter()
+            .enumerate()
+            .flat_map(|(i, block)| {
+                block
+                    .edges
+                    .iter()
+                    .filter(move |edge| edge.kind != EdgeKind::FallThrough)
+                    .filter_map(move |edge| edge.to.map(|to| (i, to, edge.kind.color())))
+            })
+            .map(|(from, to, color)| (ends[from], tops[to], color))
+            .collect();
+        jumps.sort_by_key(|&(from, to, _)| from.abs_diff(to));
+        let mut lanes: Vec<Vec<Range<usize>>> = vec![];
+        let mut placed = vec![];
+        for (from, to, color) in jumps {
+            let span = from.min(to)..from.max(to) + 1;
+            let free = lanes.iter().position(|lane| {
+                lane.iter()
+                    .all(|used| used.end <= span.start || span.end <= used.start)
+            });
+            let lane = match free {
+                Some(lane) => lane,
+                None if lanes.len() < MAX_LANES => {
+                    lanes.push(vec![]);
+                    lanes.len() - 1
+                }
+                None => continue,
+            };
+            lanes[lane].push(span);
+            placed.push((lane, from, to, color));
+        }
+
+        let columns = match lanes.len() {
+            0 => 0,
+            n => n * 2 + 1,
+        };
+        let mut gutter = vec![vec![(0u8, Color::Reset); columns]; rows.len()];
+        let mut arrows = vec![None; rows.len()];
+        for (lane, from, to, color) in placed {
+            let column = columns - 2 - lane * 2;
+            let mut mark = |row: usize, col: usize, bits: u8| {
+                let cell: &mut (u8, Color) = &mut gutter[row][col];
+                cell.0 |= bits;
+                cell.1 = color;
+            };
+            // 从跳转指令向左, 沿本列上下走到目标方框的标题行, 再向右进入方框
+            for col in column + 1..columns {
+                mark(from, col, LEFT | RIGHT);
+                mark(to, col, LEFT | RIGHT);
+            }
+            let (down, up) = if to > from { (DOWN, UP) } else { (UP, DOWN) };
+            mark(from, column, RIGHT | down);
+            mark(to, column, RIGHT | up);
+            for row in from.min(to) + 1..from.max(to) {
+                mark(row, column, UP | DOWN);
+            }
+            arrows[to] = Some(color);
+        }
+
+        Graph {
+            cfg,
+            rows,
+            gutter,
+            arrows,
+            width,
+            titles,
+            footers,
+            scroll: 0,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// The row showing listing line `line`.
+    pub fn row_of_line(&self, line: usize) -> Option<usize> {
+        self.rows
+            .iter()
+            .position(|row| matches!(row, Row::Code(_, l) if *l == line))
+    }
+
+    /// Draw `rows`, taking the instruction text from `lines`. The box of the
+    /// block holding listing line `cursor` is highlighted, and so is that line
+    /// when `show_cursor` is set.
+    pub fn lines<'a>(
+        &self,
+        lines: &[Line<'a>],
+        rows: Range<usize>,
+        cursor: usize,
+        show_cursor: bool,
+    ) -> Vec<Line<'a>> {
+        let selected = self.cfg.block_of_line(cursor);
+        let width = self.width;
+        rows.filter_map(|r| {
+            let row = *self.rows.get(r)?;
+            let block = row_block(row);
+            let border = if Some(block) == selected {
+                Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::new()
+            };
+            let mut line = gutter_line(&self.gutter[r]);
+            if let Some(&(bits, color)) = self.gutter[r].last() {
+                line.push_span(match self.arrows[r] {
+                    Some(color) => Span::styled("►", Style::new().fg(color)),
+                    None if bits & RIGHT != 0 => Span::styled("─", Style::new().fg(color)),
+                    None => Span::raw(" "),
+                });
+            }
+            match row {
+                Row::Top(_) => {
+                    let title = &self.titles[block];
+                    let fill = width.saturating_sub(title.chars().count() + 3);
+                    line.push_span(Span::styled(
+                        format!("┌─ {} {}┐", title, "─".repeat(fill)),
+                        border,
+                    ));
+                }
+                Row::Bottom(_) => {
+                    let footer = &self.footers[block];
+                    let text = if footer.is_empty() {
+                        "─".repeat(width)
+                    } else {
+                        let fill = width.saturating_sub(footer.chars().count() + 3);
+                        format!("─ {} {}", footer, "─".repeat(fill))
+                    };
+                    line.push_span(Span::styled(format!("└{}┘", text), border));
+                }
+                Row::Code(_, l) => {
+                    let mut code = lines.get(l).map(code).unwrap_or_default();
+                    let pad = width.saturating_sub(code.width() + 2);
+                    if show_cursor && l == cursor {
+                        code = code.patch_style(Modifier::REVERSED);
+                    }
+                    line.push_span(Span::styled("│ ", border));
+                    line.spans.extend(code.spans);
+                    line.push_span(Span::raw(" ".repeat(pad)));
+                    line.push_span(Span::styled(" │", border));
+                }
+                Row::Gap(_) => {
+                    let next = self.cfg.blocks[block]
+                        .edges
+                        .iter()
+                        .find(|edge| edge.kind == EdgeKind::FallThrough);
+                    if let Some(edge) = next {
+                        // 只有条件跳转不成立的那条边才着色
+                        let conditional = self.cfg.blocks[block]
+                            .edges
+                            .iter()
+                            .any(|e| e.kind == EdgeKind::Conditional);
+                        let color = if conditional {
+                            edge.kind.color()
+                        } else {
+                            Color::Reset
+                        };
+                        line.push_span(Span::styled("  ▼", Style::new().fg(color)));
+                    }
+                }
+            }
+            Some(line)
+        })
+        .collect()
+    }
+}
+
+fn row_block(row: Row) -> usize {
+    match row {
+        Row::Top(block) | Row::Code(block, _) | Row::Bottom(block) | Row::Gap(block) => block,
+    }
+}
+
+/// An instruction line without the indentation of the flat listing.
+fn code<'a>(line: &Line<'a>) -> Line<'a> {
+    let mut spans = line.spans.clone();
+    if let Some(first) = spans.first_mut() {
+        first.content = first.content.trim_start().to_string().into();
+    }
+    Line::from(spans)
+}
+
+fn gutter_line<'a>(cells: &[(u8, Color)]) -> Line<'a> {
+    let mut line = Line::default();
+    for &(bits, color) in cells {
+        let c = match bits {
+            0 => ' ',
+            b if b == LEFT | RIGHT || b == LEFT || b == RIGHT => '─',
+            b if b == UP | DOWN || b == UP || b == DOWN => '│',
+            b if b == RIGHT | DOWN => '┌',
+            b if b == RIGHT | UP => '└',
+            b if b == LEFT | DOWN => '┐',
+            b if b == LEFT | UP => '┘',
+            b if b == UP | DOWN | RIGHT => '├',
+            b if b == UP | DOWN | LEFT => '┤',
+            b if b == LEFT | RIGHT | DOWN => '┬',
+            b if b == LEFT | RIGHT | UP => '┴',
+            _ => '┼',
+        };
+        let style = Style::new().fg(color);
+        match line.spans.last_mut() {
+            Some(last) if last.style == style => last.content.to_mut().push(c),
+            _ => line.push_span(Span::styled(c.to_string(), style)),
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(address: u64, flow: Flow) -> Option<Step> {
+        Some(Step {
+            address,
+            len: 4,
+            flow,
+            data: None,
+        })
+    }
+
+    /// Kind, block and target of each edge.
+    type Edges = Vec<(EdgeKind, Option<usize>, Option<u64>)>;
+
+    /// Each block's lines and its edges.
+    fn shape(cfg: &Cfg) -> Vec<(Range<usize>, Edges)> {
+        cfg.blocks
+            .iter()
+            .map(|block| {
+                let edges = block
+                    .edges
+                    .iter()
+                    .map(|edge| (edge.kind, edge.to, edge.target))
+                    .collect();
+                (block.lines.clone(), edges)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splits_at_branches_and_their_targets() {
+        use EdgeKind::*;
+        let cfg = Cfg::build(&[
+            step(0x100, Flow::Next),
+            step(0x104, Flow::Branch(0x110)),
+            step(0x108, Flow::Next),
+            step(0x10c, Flow::Jump(Some(0x118))),
+            step(0x110, Flow::Next),
+            // 调用之后继续执行, 不结束基本块
+            step(0x114, Flow::Call(Some(0x500))),
+            step(0x118, Flow::Branch(0x900)),
+            step(0x11c, Flow::Stop),
+        ]);
+        assert_eq!(
+            shape(&cfg),
+            [
+                (
+                    0..2,
+                    vec![
+                        (Conditional, Some(2), Some(0x110)),
+                        (FallThrough, Some(1), Some(0x108)),
+                    ]
+                ),
+                (2..4, vec![(Unconditional, Some(3), Some(0x118))]),
+                (4..6, vec![(FallThrough, Some(3), Some(0x118))]),
+                (
+                    6..7,
+                    vec![
+                        (Conditional, None, Some(0x900)),
+                        (FallThrough, Some(4), Some(0x11c)),
+                    ]
+                ),
+                (7..8, vec![]),
+            ]
+        );
+        let starts: Vec<u64> = cfg.blocks.iter().map(|block| block.start).collect();
+        assert_eq!(starts, [0x100, 0x108, 0x110, 0x118, 0x11c]);
+        assert_eq!(cfg.block_of_line(5), Some(2));
+    }
+
+    #[test]
+    fn labels_end_blocks_and_indirect_jumps_have_no_target() {
+        use EdgeKind::*;
+        let cfg = Cfg::build(&[
+            step(0x100, Flow::Next),
+            // 标签行
+            None,
+            step(0x104, Flow::Jump(None)),
+            step(0x108, Flow::Next),
+        ]);
+        assert_eq!(
+            shape(&cfg),
+            [
+                (0..1, vec![(FallThrough, Some(1), Some(0x104))]),
+                (2..3, vec![(Indirect, None, None)]),
+                (3..4, vec![]),
+            ]
+        );
+        assert_eq!(cfg.block_of_line(1), None);
+    }
+}
@@ -0,0 +1,110 @@
+//! Readable names for Itanium C++ and Rust (legacy and v0) symbols.
+
+use std::borrow::Cow;
+
+use cpp_demangle::{DemangleOptions, Symbol};
+
+use crate::disasm::FormatOptions;
+
+/// `name` as it is shown with `options`. Names that aren't mangled, or that
+/// the demanglers reject, are returned as they are. A version or `@plt`
+/// suffix is kept after the demangled name.
+pub fn demangle(name: &str, options: FormatOptions) -> Cow<'_, str> {
+    if options.mangled {
+        return Cow::Borrowed(name);
+    }
+    // foo@GLIBC_2.2.5, foo@@VERS_1, foo@plt
+    let (symbol, suffix) = match name.find('@') {
+        Some(at) => name.split_at(at),
+        None => (name, ""),
+    };
+    // 旧式 Rust 名字也是合法的 C++ 名字, 所以先试 Rust
+    if let Ok(demangled) = rustc_demangle::try_demangle(symbol) {
+        let demangled = if options.hide_hash {
+            format!("{:#}", demangled)
+        } else {
+            demangled.to_string()
+        };
+        return Cow::Owned(demangled + suffix);
+    }
+    if !symbol.starts_with("_Z") {
+        return Cow::Borrowed(name);
+    }
+    match Symbol::new(symbol).map(|parsed| parsed.demangle(&DemangleOptions::default())) {
+        Ok(Ok(demangled)) => Cow::Owned(demangled + suffix),
+        _ => Cow::Borrowed(name),
+    }
+}
+
+/// How `names` are shown with `options`.
+pub fn demangle_all(names: &[String], options: FormatOptions) -> Vec<String> {
+    names
+        .iter()
+        .map(|name| demangle(name, options).into_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shown(name: &str) -> String {
+        demangle(name, FormatOptions::default()).into_owned()
+    }
+
+    fn without_hash(name: &str) -> String {
+        let options = FormatOptions {
+            hide_hash: true,
+            ..FormatOptions::default()
+        };
+        demangle(name, options).into_owned()
+    }
+
+    #[test]
+    fn itanium() {
+        assert_eq!(shown("_ZN3foo3barEv"), "foo::bar()");
+        assert_eq!(
+            shown("_ZNSt6vectorIiSaIiEE9push_backERKi"),
+            "std::vector<int, std::allocator<int> >::push_back(int const&)"
+        );
+        assert_eq!(shown("_Z3addii@plt"), "add(int, int)@plt");
+        // 不是 Rust 名字, 不受 hide_hash 影响
+        assert_eq!(without_hash("_Z3addii"), "add(int, int)");
+    }
+
+    #[test]
+    fn rust_legacy() {
+        let name = "_ZN4core3fmt5write17h0123456789abcdefE";
+        assert_eq!(shown(name), "core::fmt::write::h0123456789abcdef");
+        assert_eq!(without_hash(name), "core::fmt::write");
+        assert_eq!(
+            without_hash("_ZN3std2io5stdio6_print17h1122334455667788E@GLIBC_2.2.5"),
+            "std::io::stdio::_print@GLIBC_2.2.5"
+        );
+    }
+
+    #[test]
+    fn rust_v0() {
+        let name = "_RNvCs1234_7mycrate3foo";
+        // 消歧义值是 62 进制的
+        assert_eq!(shown(name), "mycrate[3c1c0]::foo");
+        assert_eq!(without_hash(name), "mycrate::foo");
+    }
+
+    #[test]
+    fn left_alone() {
+        assert_eq!(shown("main"), "main");
+        assert_eq!(shown("puts@GLIBC_2.2.5"), "puts@GLIBC_2.2.5");
+        // 以 _Z 开头但不是合法的名字
+        assert_eq!(shown("_Zfoo"), "_Zfoo");
+        let mangled = FormatOptions {
+            mangled: true,
+            ..FormatOptions::default()
+        };
+        assert_eq!(demangle("_ZN3foo3barEv", mangled), "_ZN3foo3barEv");
+        assert!(matches!(
+            demangle("main", FormatOptions::default()),
+            Cow::Borrowed("main")
+        ));
+    }
+}
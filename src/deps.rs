@@ -1,22 +1,25 @@
 use elf::{
-    dynamic::Dyn, endian::AnyEndian, parse::ParsingTable, string_table::StringTable, abi
+    dynamic::Dyn, endian::AnyEndian, parse::ParsingTable, string_table::StringTable, abi, ElfBytes
 };
 use ratatui::{
     buffer::Buffer,
+    crossterm::event::KeyCode,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, List, ListDirection, ListState, Paragraph, StatefulWidget, Widget},
+    widgets::{Block, Paragraph, Widget},
 };
 use std::collections::HashMap;
 use std::process::Command;
 use std::env::consts::{ARCH, OS};
 
+use crate::empty::Page;
+use crate::filter::FilteredList;
+
 pub struct DependenciesPage<'a> {
     pub rpath: Option<String>,
     pub needed: Vec<DependencyEntry<'a>>,
-    pub list: List<'a>,
-    pub state: ListState,
+    pub list: FilteredList,
 }
 
 pub struct DependencyEntry<'a> {
@@ -36,10 +39,7 @@ impl<'a> DependenciesPage<'a> {
         }
 
         // 检查架构是否匹配
-        let current_arch = match ARCH {
-            "x86_64" => true,
-            _ => false,
-        };
+        let current_arch = matches!(ARCH, "x86_64");
 
         if !current_arch {
             return library_paths;
@@ -121,7 +121,7 @@ impl<'a> DependenciesPage<'a> {
                             needed.push(DependencyEntry {
                                 name,
                                 is_critical,
-                                search_path: Self::get_search_path(name, rpath.as_deref()),
+                                search_path: Self::get_search_path(rpath.as_deref()),
                                 actual_path,
                             });
                         }
@@ -138,18 +138,10 @@ impl<'a> DependenciesPage<'a> {
             }
         }).collect::<Vec<_>>();
 
-        let list = List::new(list_items)
-            .block(Block::bordered().title("Dependencies"))
-            .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
-            .highlight_symbol(">> ")
-            .repeat_highlight_symbol(true)
-            .direction(ListDirection::TopToBottom);
-
         DependenciesPage {
             rpath,
             needed,
-            list,
-            state: ListState::default(),
+            list: FilteredList::new("Dependencies", ">> ", list_items),
         }
     }
 
@@ -163,7 +155,7 @@ impl<'a> DependenciesPage<'a> {
         critical_libs.iter().any(|lib| name.starts_with(lib))
     }
 
-    fn get_search_path(name: &str, rpath: Option<&str>) -> String {
+    fn get_search_path(rpath: Option<&str>) -> String {
         let mut paths = Vec::new();
         
         // 1. RPATH/RUNPATH
@@ -195,10 +187,9 @@ impl Widget for &mut DependenciesPage<'_> {
             .constraints(vec![Constraint::Min(40), Constraint::Percentage(100)])
             .split(area);
 
-        StatefulWidget::render(&self.list, layout[0], buf, &mut self.state);
+        self.list.render(layout[0], buf);
 
-        let details = if let Some(selected) = self.state.selected() {
-            let entry = &self.needed[selected];
+        let details = if let Some(entry) = self.list.selected().and_then(|idx| self.needed.get(idx)) {
             let mut lines = vec![
                 Line::from(vec![
                     Span::raw("Library: "),
@@ -237,12 +228,11 @@ impl Widget for &mut DependenciesPage<'_> {
 
             lines
         } else {
-            let mut lines = vec![
+            vec![
                 Line::from("Select a library to view details"),
                 Line::from(""),
                 Line::from("* Critical system libraries are marked with an asterisk"),
-            ];
-            lines
+            ]
         };
 
         Paragraph::new(details)
@@ -250,3 +240,25 @@ impl Widget for &mut DependenciesPage<'_> {
             .render(layout[1], buf);
     }
 }
+
+impl<'a> Page<'a> for DependenciesPage<'a> {
+    fn page_render(&mut self, area: Rect, buf: &mut Buffer) {
+        self.render(area, buf);
+    }
+
+    fn select_next(&mut self, _elf: &ElfBytes<'a, AnyEndian>) {
+        self.list.select_next();
+    }
+
+    fn select_previous(&mut self, _elf: &ElfBytes<'a, AnyEndian>) {
+        self.list.select_previous();
+    }
+
+    fn select_left(&mut self) {}
+
+    fn select_right(&mut self) {}
+
+    fn handle_key(&mut self, key: KeyCode, _elf: &ElfBytes<'a, AnyEndian>) -> bool {
+        self.list.handle_key(key)
+    }
+}
@@ -0,0 +1,81 @@
+use elf::{endian::AnyEndian, ElfBytes};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListDirection, ListState, Paragraph, StatefulWidget, Widget},
+};
+
+use crate::empty::Page;
+use crate::error::Diagnostics;
+
+pub struct DiagnosticsPage {
+    diagnostics: Diagnostics,
+    pub state: ListState,
+}
+
+impl DiagnosticsPage {
+    pub fn new(diagnostics: Diagnostics) -> DiagnosticsPage {
+        DiagnosticsPage {
+            diagnostics,
+            state: ListState::default(),
+        }
+    }
+}
+
+impl Widget for &mut DiagnosticsPage {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let entries = self.diagnostics.entries();
+        let title = format!("Diagnostics ({})", entries.len());
+
+        // 问题会在解码过程中不断增加, 所以每次渲染时重新生成列表
+        if entries.is_empty() {
+            Paragraph::new("No problems were found while reading this file")
+                .block(Block::bordered().title(title))
+                .render(area, buf);
+            return;
+        }
+
+        let items: Vec<Line> = entries
+            .iter()
+            .map(|error| {
+                let offset = match error.offset() {
+                    Some(offset) => format!("{:016X}", offset),
+                    None => format!("{:>16}", "-"),
+                };
+                Line::from(vec![
+                    Span::raw(offset).cyan(),
+                    Span::raw("    "),
+                    Span::raw(error.to_string()),
+                ])
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
+            .highlight_symbol(">> ")
+            .repeat_highlight_symbol(true)
+            .direction(ListDirection::TopToBottom);
+        StatefulWidget::render(list, area, buf, &mut self.state);
+    }
+}
+
+impl<'a> Page<'a> for DiagnosticsPage {
+    fn page_render(&mut self, area: Rect, buf: &mut Buffer) {
+        self.render(area, buf);
+    }
+
+    fn select_next(&mut self, _elf: &ElfBytes<'a, AnyEndian>) {
+        self.state.select_next();
+    }
+
+    fn select_previous(&mut self, _elf: &ElfBytes<'a, AnyEndian>) {
+        self.state.select_previous();
+    }
+
+    fn select_left(&mut self) {}
+
+    fn select_right(&mut self) {}
+}
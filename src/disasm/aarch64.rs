@@ -0,0 +1,2645 @@
+//! AArch64 (A64) instruction decoder.
+//!
+//! Covers the base integer ISA, scalar floating point and the common Advanced
+//! SIMD forms; anything else is printed as `.inst 0x...`. Output follows the
+//! GNU objdump syntax, including its preferred aliases (`mov`, `cmp`, `lsl`, ...).
+
+use std::rc::Rc;
+
+use super::{DataUse, DecodedInstruction, Disassembler, Flow, Step, SymbolMap, TokenKind, Tokens};
+
+pub struct Aarch64Disassembler {
+    symbols: Rc<SymbolMap>,
+}
+
+impl Aarch64Disassembler {
+    pub fn new(symbols: Rc<SymbolMap>) -> Aarch64Disassembler {
+        Aarch64Disassembler { symbols }
+    }
+}
+
+impl Disassembler for Aarch64Disassembler {
+    fn disassemble(&mut self, code: &[u8], address: u64) -> Vec<DecodedInstruction> {
+        // 记录 adrp 加载到寄存器中的页地址, 用于解析 PLT 桩和全局变量的访问
+        let mut pages: [Option<u64>; 31] = [None; 31];
+        let mut buffer = vec![];
+        for (i, chunk) in code.chunks(4).enumerate() {
+            let pc = address + i as u64 * 4;
+            let mut out = Tokens::default();
+            let (step, data_use) = match <[u8; 4]>::try_from(chunk) {
+                Ok(bytes) => {
+                    let insn = decode(u32::from_le_bytes(bytes), pc);
+                    format(&insn, &mut out, &self.symbols);
+                    let (step, paged) = step(&insn, pc, &mut pages);
+                    if let Some(address) = paged {
+                        out.comment(address, &self.symbols);
+                    }
+                    (step, insn.data_use)
+                }
+                Err(_) => {
+                    out.push(".byte   ", TokenKind::Directive);
+                    let bytes: Vec<String> = chunk.iter().map(|b| format!("0x{:02x}", b)).collect();
+                    out.number(bytes.join(", "));
+                    let step = Step {
+                        address: pc,
+                        len: chunk.len(),
+                        flow: Flow::Stop,
+                        data: None,
+                    };
+                    (step, DataUse::Address)
+                }
+            };
+            buffer.push(DecodedInstruction {
+                step,
+                tokens: out.vec,
+                data_use,
+            });
+        }
+        buffer
+    }
+
+    fn steps(&mut self, code: &[u8], address: u64, run_only: bool) -> Vec<Step> {
+        let mut pages: [Option<u64>; 31] = [None; 31];
+        let mut steps = vec![];
+        for (i, chunk) in code.chunks_exact(4).enumerate() {
+            let pc = address + i as u64 * 4;
+            let insn = decode(
+                u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+                pc,
+            );
+            let (step, _) = step(&insn, pc, &mut pages);
+            steps.push(step);
+            if run_only && step.flow.ends_run() {
+                break;
+            }
+        }
+        steps
+    }
+}
+
+/// The instruction's step, and the address completed by an `adrp` pair.
+fn step(insn: &Insn, pc: u64, pages: &mut [Option<u64>; 31]) -> (Step, Option<u64>) {
+    let paged = track_pages(insn, pages);
+    // adrp 只给出页地址, 要等后面的 add/ldr 补上页内偏移
+    let literal = match insn.page {
+        None if !insn.branch => target(insn),
+        _ => None,
+    };
+    let step = Step {
+        address: pc,
+        len: 4,
+        flow: flow(insn),
+        data: paged.or(literal),
+    };
+    (step, paged)
+}
+
+fn target(insn: &Insn) -> Option<u64> {
+    insn.operands.iter().find_map(|operand| match operand {
+        Target(address) => Some(*address),
+        _ => None,
+    })
+}
+
+fn flow(insn: &Insn) -> Flow {
+    let target = target(insn);
+    if !insn.branch {
+        return match insn.mnemonic.as_str() {
+            "udf" | "brk" | "hlt" | ".inst" => Flow::Stop,
+            _ => Flow::Next,
+        };
+    }
+    match insn.mnemonic.as_str() {
+        "b" => Flow::Jump(target),
+        "bl" => Flow::Call(target),
+        "br" | "braaz" | "brabz" | "braa" | "brab" => Flow::Jump(None),
+        "blr" | "blraaz" | "blrabz" | "blraa" | "blrab" => Flow::Call(None),
+        "ret" | "retaa" | "retab" | "eret" | "eretaa" | "eretab" | "drps" => Flow::Stop,
+        // cbz/tbz/b.cond 等条件分支
+        _ => target.map_or(Flow::Stop, Flow::Branch),
+    }
+}
+
+/// Operand of a decoded instruction, before it is turned into tokens.
+enum Operand {
+    Reg(String),
+    /// Immediate, already formatted with its `#`.
+    Imm(String),
+    /// PC-relative address: branch target, literal pool entry, `adr`/`adrp` result.
+    Target(u64),
+    /// `[base, offset...]`, with `!` for pre-indexed writeback.
+    Mem {
+        base: String,
+        offset: Vec<Operand>,
+        writeback: bool,
+    },
+    Shift(&'static str, u32),
+    Extend(&'static str, Option<u32>),
+    /// Condition codes, barrier options, system register names, ...
+    Name(String),
+    RegList(Vec<String>),
+}
+
+use Operand::*;
+
+struct Insn {
+    mnemonic: String,
+    operands: Vec<Operand>,
+    /// General-purpose registers written by the instruction, as a bit mask.
+    clobbers: u32,
+    /// Control flow leaves the straight line, forget what registers hold.
+    branch: bool,
+    /// `adrp` destination and the page it loads.
+    page: Option<(u32, u64)>,
+    /// Base register and offset of an `add`/load/store that may complete an `adrp`.
+    page_ref: Option<(u32, u64)>,
+    /// What a load or store does with the bytes at its address.
+    data_use: DataUse,
+}
+
+impl Insn {
+    fn new(mnemonic: impl Into<String>) -> Insn {
+        Insn {
+            mnemonic: mnemonic.into(),
+            operands: vec![],
+            clobbers: 0,
+            branch: false,
+            page: None,
+            page_ref: None,
+            data_use: DataUse::Address,
+        }
+    }
+
+    fn op(mut self, operand: Operand) -> Insn {
+        self.operands.push(operand);
+        self
+    }
+
+    fn reg(self, name: String) -> Insn {
+        self.op(Reg(name))
+    }
+
+    fn imm(self, text: String) -> Insn {
+        self.op(Imm(text))
+    }
+
+    fn writes(mut self, reg: u32) -> Insn {
+        if reg < 31 {
+            self.clobbers |= 1 << reg;
+        }
+        self
+    }
+
+    fn branch(mut self) -> Insn {
+        self.branch = true;
+        self
+    }
+}
+
+fn format(insn: &Insn, out: &mut Tokens, symbols: &SymbolMap) {
+    if insn.mnemonic.starts_with('.') {
+        out.push(format!("{:<7} ", insn.mnemonic), TokenKind::Directive);
+    } else {
+        out.mnemonic(&insn.mnemonic);
+    }
+    for (i, operand) in insn.operands.iter().enumerate() {
+        if i > 0 {
+            out.text(", ");
+        }
+        format_operand(operand, out, symbols);
+    }
+}
+
+fn format_operand(operand: &Operand, out: &mut Tokens, symbols: &SymbolMap) {
+    match operand {
+        Reg(name) => out.register(name.clone()),
+        Imm(text) => out.number(text.clone()),
+        Target(address) => out.target(*address, symbols),
+        Mem {
+            base,
+            offset,
+            writeback,
+        } => {
+            out.text("[");
+            out.register(base.clone());
+            for operand in offset {
+                out.text(", ");
+                format_operand(operand, out, symbols);
+            }
+            out.text(if *writeback { "]!" } else { "]" });
+        }
+        Shift(name, amount) => {
+            out.keyword(*name);
+            out.number(format!(" #{}", amount));
+        }
+        Extend(name, amount) => {
+            out.keyword(*name);
+            if let Some(amount) = amount {
+                out.number(format!(" #{}", amount));
+            }
+        }
+        Name(name) => out.keyword(name.clone()),
+        RegList(regs) => {
+            out.text("{");
+            for (i, reg) in regs.iter().enumerate() {
+                if i > 0 {
+                    out.text(", ");
+                }
+                out.register(reg.clone());
+            }
+            out.text("}");
+        }
+    }
+}
+
+/// Follow `adrp` + `add`/`ldr` pairs and return the address they form,
+/// which is how PLT stubs and position-independent code reach the GOT.
+fn track_pages(insn: &Insn, pages: &mut [Option<u64>; 31]) -> Option<u64> {
+    let address = insn.page_ref.and_then(|(base, offset)| {
+        let page = pages.get(base as usize).copied().flatten()?;
+        Some(page.wrapping_add(offset))
+    });
+    if insn.branch {
+        *pages = [None; 31];
+    }
+    for (reg, page) in pages.iter_mut().enumerate() {
+        if insn.clobbers & (1 << reg) != 0 {
+            *page = None;
+        }
+    }
+    if let Some((reg, page)) = insn.page {
+        pages[reg as usize] = Some(page);
+    }
+    address
+}
+
+fn bits(w: u32, hi: u32, lo: u32) -> u32 {
+    (w >> lo) & ((1u32 << (hi - lo + 1)) - 1)
+}
+
+fn bit(w: u32, n: u32) -> bool {
+    (w >> n) & 1 == 1
+}
+
+fn sext(value: u32, width: u32) -> i64 {
+    let shift = 64 - width;
+    ((value as i64) << shift) >> shift
+}
+
+/// General-purpose register, where 31 is the zero register.
+fn reg(n: u32, sf: bool) -> String {
+    match (n, sf) {
+        (31, true) => "xzr".to_string(),
+        (31, false) => "wzr".to_string(),
+        (n, true) => format!("x{}", n),
+        (n, false) => format!("w{}", n),
+    }
+}
+
+/// General-purpose register, where 31 is the stack pointer.
+fn reg_sp(n: u32, sf: bool) -> String {
+    match (n, sf) {
+        (31, true) => "sp".to_string(),
+        (31, false) => "wsp".to_string(),
+        _ => reg(n, sf),
+    }
+}
+
+fn x(n: u32) -> String {
+    reg(n, true)
+}
+
+fn xsp(n: u32) -> String {
+    reg_sp(n, true)
+}
+
+fn hex(value: u64) -> String {
+    format!("#0x{:x}", value)
+}
+
+fn dec(value: i64) -> String {
+    format!("#{}", value)
+}
+
+const CONDITIONS: [&str; 16] = [
+    "eq", "ne", "hs", "lo", "mi", "pl", "vs", "vc", "hi", "ls", "ge", "lt", "gt", "le", "al", "nv",
+];
+
+fn cond(c: u32) -> Operand {
+    Name(CONDITIONS[c as usize & 15].to_string())
+}
+
+fn mem(base: u32, offset: i64) -> Operand {
+    Mem {
+        base: xsp(base),
+        offset: if offset == 0 {
+            vec![]
+        } else {
+            vec![Imm(dec(offset))]
+        },
+        writeback: false,
+    }
+}
+
+fn mem_base(base: u32) -> Operand {
+    mem(base, 0)
+}
+
+fn decode(w: u32, pc: u64) -> Insn {
+    let insn = match bits(w, 28, 25) {
+        0b0000 if bits(w, 31, 16) == 0 => Some(Insn::new("udf").imm(dec(bits(w, 15, 0) as i64))),
+        0b1000 | 0b1001 => dp_imm(w, pc),
+        0b1010 | 0b1011 => branch_system(w, pc),
+        0b0100 | 0b0110 | 0b1100 | 0b1110 => load_store(w, pc),
+        0b0101 | 0b1101 => dp_reg(w),
+        0b0111 | 0b1111 => simd_fp(w),
+        _ => None,
+    };
+    insn.unwrap_or_else(|| Insn::new(".inst").imm(format!("0x{:08x}", w)))
+}
+
+// ---------------------------------------------------------------------------
+// Data processing -- immediate
+
+fn dp_imm(w: u32, pc: u64) -> Option<Insn> {
+    let sf = bit(w, 31);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    match bits(w, 25, 23) {
+        0b000 | 0b001 => {
+            let imm = sext((bits(w, 23, 5) << 2) | bits(w, 30, 29), 21);
+            if bit(w, 31) {
+                let page = (pc & !0xfff).wrapping_add((imm << 12) as u64);
+                let mut insn = Insn::new("adrp").reg(x(rd)).op(Target(page)).writes(rd);
+                if rd < 31 {
+                    insn.page = Some((rd, page));
+                }
+                Some(insn)
+            } else {
+                Some(
+                    Insn::new("adr")
+                        .reg(x(rd))
+                        .op(Target(pc.wrapping_add(imm as u64)))
+                        .writes(rd),
+                )
+            }
+        }
+        0b010 => {
+            let op = bit(w, 30);
+            let s = bit(w, 29);
+            let shifted = bit(w, 22);
+            let imm12 = bits(w, 21, 10) as u64;
+            let shift = if shifted {
+                Some(Shift("lsl", 12))
+            } else {
+                None
+            };
+            if !op && !s && !shifted && imm12 == 0 && (rd == 31 || rn == 31) {
+                return Some(
+                    Insn::new("mov")
+                        .reg(reg_sp(rd, sf))
+                        .reg(reg_sp(rn, sf))
+                        .writes(rd),
+                );
+            }
+            let mut insn = if s && rd == 31 {
+                Insn::new(if op { "cmp" } else { "cmn" })
+            } else {
+                let name = match (op, s) {
+                    (false, false) => "add",
+                    (false, true) => "adds",
+                    (true, false) => "sub",
+                    (true, true) => "subs",
+                };
+                let dest = if s { reg(rd, sf) } else { reg_sp(rd, sf) };
+                Insn::new(name).reg(dest).writes(rd)
+            };
+            insn = insn.reg(reg_sp(rn, sf)).imm(hex(imm12));
+            if let Some(shift) = shift {
+                insn = insn.op(shift);
+            } else if !op && !s && sf {
+                insn.page_ref = Some((rn, imm12));
+            }
+            Some(insn)
+        }
+        0b100 => {
+            let n = bit(w, 22);
+            if !sf && n {
+                return None;
+            }
+            let imm = decode_bit_masks(n, bits(w, 15, 10), bits(w, 21, 16), sf)?;
+            let insn = match bits(w, 30, 29) {
+                0b00 => Insn::new("and").reg(reg_sp(rd, sf)).reg(reg(rn, sf)),
+                0b01 if rn == 31 => Insn::new("mov").reg(reg_sp(rd, sf)),
+                0b01 => Insn::new("orr").reg(reg_sp(rd, sf)).reg(reg(rn, sf)),
+                0b10 => Insn::new("eor").reg(reg_sp(rd, sf)).reg(reg(rn, sf)),
+                _ if rd == 31 => Insn::new("tst").reg(reg(rn, sf)),
+                _ => Insn::new("ands").reg(reg(rd, sf)).reg(reg(rn, sf)),
+            };
+            Some(insn.imm(hex(imm)).writes(rd))
+        }
+        0b101 => {
+            let opc = bits(w, 30, 29);
+            let hw = bits(w, 22, 21);
+            let imm16 = bits(w, 20, 5) as u64;
+            if opc == 0b01 || (!sf && hw >= 2) {
+                return None;
+            }
+            let shift = hw * 16;
+            let mask = if sf { u64::MAX } else { 0xffff_ffff };
+            let shifted_op = |insn: Insn| {
+                let insn = insn.reg(reg(rd, sf)).imm(hex(imm16)).writes(rd);
+                if shift > 0 {
+                    insn.op(Shift("lsl", shift))
+                } else {
+                    insn
+                }
+            };
+            let insn = match opc {
+                0b00 if !(imm16 == 0 && hw != 0) && (sf || imm16 != 0xffff) => Insn::new("mov")
+                    .reg(reg(rd, sf))
+                    .imm(hex(!(imm16 << shift) & mask))
+                    .writes(rd),
+                0b00 => shifted_op(Insn::new("movn")),
+                0b10 if !(imm16 == 0 && hw != 0) => Insn::new("mov")
+                    .reg(reg(rd, sf))
+                    .imm(hex(imm16 << shift))
+                    .writes(rd),
+                0b10 => shifted_op(Insn::new("movz")),
+                _ => shifted_op(Insn::new("movk")),
+            };
+            Some(insn)
+        }
+        0b110 => {
+            let opc = bits(w, 30, 29);
+            let immr = bits(w, 21, 16);
+            let imms = bits(w, 15, 10);
+            let size = if sf { 64 } else { 32 };
+            if opc == 0b11 || bit(w, 22) != sf || immr >= size || imms >= size {
+                return None;
+            }
+            let d = reg(rd, sf);
+            let n = reg(rn, sf);
+            let bitfield = |name: &str, lsb: u32, width: u32| {
+                Insn::new(name)
+                    .reg(d.clone())
+                    .reg(n.clone())
+                    .imm(dec(lsb as i64))
+                    .imm(dec(width as i64))
+            };
+            let insn = match opc {
+                0b00 => {
+                    if imms == size - 1 {
+                        Insn::new("asr")
+                            .reg(d.clone())
+                            .reg(n.clone())
+                            .imm(dec(immr as i64))
+                    } else if immr == 0 && imms == 7 {
+                        Insn::new("sxtb").reg(d.clone()).reg(reg(rn, false))
+                    } else if immr == 0 && imms == 15 {
+                        Insn::new("sxth").reg(d.clone()).reg(reg(rn, false))
+                    } else if immr == 0 && imms == 31 {
+                        Insn::new("sxtw").reg(d.clone()).reg(reg(rn, false))
+                    } else if imms < immr {
+                        bitfield("sbfiz", size - immr, imms + 1)
+                    } else {
+                        bitfield("sbfx", immr, imms - immr + 1)
+                    }
+                }
+                0b01 => {
+                    if imms < immr {
+                        if rn == 31 {
+                            Insn::new("bfc")
+                                .reg(d.clone())
+                                .imm(dec((size - immr) as i64))
+                                .imm(dec(imms as i64 + 1))
+                        } else {
+                            bitfield("bfi", size - immr, imms + 1)
+                        }
+                    } else {
+                        bitfield("bfxil", immr, imms - immr + 1)
+                    }
+                }
+                _ => {
+                    if imms == size - 1 {
+                        Insn::new("lsr")
+                            .reg(d.clone())
+                            .reg(n.clone())
+                            .imm(dec(immr as i64))
+                    } else if imms + 1 == immr {
+                        Insn::new("lsl")
+                            .reg(d.clone())
+                            .reg(n.clone())
+                            .imm(dec((size - 1 - imms) as i64))
+                    } else if !sf && immr == 0 && imms == 7 {
+                        Insn::new("uxtb").reg(d.clone()).reg(n.clone())
+                    } else if !sf && immr == 0 && imms == 15 {
+                        Insn::new("uxth").reg(d.clone()).reg(n.clone())
+                    } else if imms < immr {
+                        bitfield("ubfiz", size - immr, imms + 1)
+                    } else {
+                        bitfield("ubfx", immr, imms - immr + 1)
+                    }
+                }
+            };
+            Some(insn.writes(rd))
+        }
+        0b111 => {
+            let rm = bits(w, 20, 16);
+            let imms = bits(w, 15, 10);
+            if bits(w, 30, 29) != 0 || bit(w, 21) || bit(w, 22) != sf || (!sf && imms >= 32) {
+                return None;
+            }
+            let insn = if rn == rm {
+                Insn::new("ror").reg(reg(rd, sf)).reg(reg(rn, sf))
+            } else {
+                Insn::new("extr")
+                    .reg(reg(rd, sf))
+                    .reg(reg(rn, sf))
+                    .reg(reg(rm, sf))
+            };
+            Some(insn.imm(dec(imms as i64)).writes(rd))
+        }
+        _ => None,
+    }
+}
+
+/// Expand the N:immr:imms encoding of a logical immediate (DecodeBitMasks).
+fn decode_bit_masks(n: bool, imms: u32, immr: u32, sf: bool) -> Option<u64> {
+    let combined = ((n as u32) << 6) | (!imms & 0x3f);
+    if combined == 0 {
+        return None;
+    }
+    let len = 31 - combined.leading_zeros();
+    if len < 1 {
+        return None;
+    }
+    let esize = 1u32 << len;
+    let levels = esize - 1;
+    let s = imms & levels;
+    let r = immr & levels;
+    if s == levels {
+        return None;
+    }
+    let welem: u64 = (1u64 << (s + 1)) - 1;
+    let mask = if esize == 64 {
+        u64::MAX
+    } else {
+        (1u64 << esize) - 1
+    };
+    let elem = if r == 0 {
+        welem
+    } else {
+        ((welem >> r) | (welem << (esize - r))) & mask
+    };
+    let mut result = 0u64;
+    let mut i = 0;
+    while i < 64 {
+        result |= elem << i;
+        i += esize;
+    }
+    Some(if sf { result } else { result & 0xffff_ffff })
+}
+
+// ---------------------------------------------------------------------------
+// Branches, exception generation and system instructions
+
+fn branch_system(w: u32, pc: u64) -> Option<Insn> {
+    let rt = bits(w, 4, 0);
+    if bits(w, 30, 26) == 0b00101 {
+        let target = pc.wrapping_add((sext(bits(w, 25, 0), 26) * 4) as u64);
+        let name = if bit(w, 31) { "bl" } else { "b" };
+        return Some(Insn::new(name).op(Target(target)).branch());
+    }
+    if bits(w, 30, 25) == 0b011010 {
+        let target = pc.wrapping_add((sext(bits(w, 23, 5), 19) * 4) as u64);
+        let name = if bit(w, 24) { "cbnz" } else { "cbz" };
+        return Some(
+            Insn::new(name)
+                .reg(reg(rt, bit(w, 31)))
+                .op(Target(target))
+                .branch(),
+        );
+    }
+    if bits(w, 30, 25) == 0b011011 {
+        let target = pc.wrapping_add((sext(bits(w, 18, 5), 14) * 4) as u64);
+        let bit_pos = (bits(w, 31, 31) << 5) | bits(w, 23, 19);
+        let name = if bit(w, 24) { "tbnz" } else { "tbz" };
+        return Some(
+            Insn::new(name)
+                .reg(reg(rt, bit(w, 31)))
+                .imm(dec(bit_pos as i64))
+                .op(Target(target))
+                .branch(),
+        );
+    }
+    if bits(w, 31, 24) == 0b0101_0100 {
+        let target = pc.wrapping_add((sext(bits(w, 23, 5), 19) * 4) as u64);
+        let prefix = if bit(w, 4) { "bc" } else { "b" };
+        let name = format!("{}.{}", prefix, CONDITIONS[bits(w, 3, 0) as usize]);
+        return Some(Insn::new(name).op(Target(target)).branch());
+    }
+    if bits(w, 31, 24) == 0b1101_0100 {
+        let imm16 = bits(w, 20, 5) as u64;
+        if bits(w, 4, 2) != 0 {
+            return None;
+        }
+        let name = match (bits(w, 23, 21), bits(w, 1, 0)) {
+            (0b000, 0b01) => "svc",
+            (0b000, 0b10) => "hvc",
+            (0b000, 0b11) => "smc",
+            (0b001, 0b00) => "brk",
+            (0b010, 0b00) => "hlt",
+            (0b101, 0b01) => "dcps1",
+            (0b101, 0b10) => "dcps2",
+            (0b101, 0b11) => "dcps3",
+            _ => return None,
+        };
+        return Some(Insn::new(name).imm(hex(imm16)));
+    }
+    if bits(w, 31, 22) == 0b11_0101_0100 {
+        return system(w);
+    }
+    if bits(w, 31, 25) == 0b110_1011 {
+        return branch_register(w);
+    }
+    None
+}
+
+fn branch_register(w: u32) -> Option<Insn> {
+    let rn = bits(w, 9, 5);
+    let op4 = bits(w, 4, 0);
+    if bits(w, 20, 16) != 0b11111 {
+        return None;
+    }
+    let insn = match (bits(w, 24, 21), bits(w, 15, 10), op4) {
+        (0b0000, 0, 0) => Insn::new("br").reg(x(rn)),
+        (0b0001, 0, 0) => Insn::new("blr").reg(x(rn)),
+        (0b0010, 0, 0) if rn == 30 => Insn::new("ret"),
+        (0b0010, 0, 0) => Insn::new("ret").reg(x(rn)),
+        (0b0100, 0, 0) if rn == 31 => Insn::new("eret"),
+        (0b0101, 0, 0) if rn == 31 => Insn::new("drps"),
+        (0b0000, 2, 31) => Insn::new("braaz").reg(x(rn)),
+        (0b0000, 3, 31) => Insn::new("brabz").reg(x(rn)),
+        (0b0001, 2, 31) => Insn::new("blraaz").reg(x(rn)),
+        (0b0001, 3, 31) => Insn::new("blrabz").reg(x(rn)),
+        (0b0010, 2, 31) if rn == 31 => Insn::new("retaa"),
+        (0b0010, 3, 31) if rn == 31 => Insn::new("retab"),
+        (0b0100, 2, 31) if rn == 31 => Insn::new("eretaa"),
+        (0b0100, 3, 31) if rn == 31 => Insn::new("eretab"),
+        (0b1000, 2, _) => Insn::new("braa").reg(x(rn)).reg(xsp(op4)),
+        (0b1000, 3, _) => Insn::new("brab").reg(x(rn)).reg(xsp(op4)),
+        (0b1001, 2, _) => Insn::new("blraa").reg(x(rn)).reg(xsp(op4)),
+        (0b1001, 3, _) => Insn::new("blrab").reg(x(rn)).reg(xsp(op4)),
+        _ => return None,
+    };
+    Some(insn.branch())
+}
+
+fn system(w: u32) -> Option<Insn> {
+    let l = bit(w, 21);
+    let op0 = bits(w, 20, 19);
+    let op1 = bits(w, 18, 16);
+    let crn = bits(w, 15, 12);
+    let crm = bits(w, 11, 8);
+    let op2 = bits(w, 7, 5);
+    let rt = bits(w, 4, 0);
+
+    if !l && op0 == 0 && crn == 2 && op1 == 3 && rt == 31 {
+        return Some(hint((crm << 3) | op2));
+    }
+    if !l && op0 == 0 && crn == 3 && op1 == 3 && rt == 31 {
+        let insn = match op2 {
+            0b010 if crm == 15 => Insn::new("clrex"),
+            0b010 => Insn::new("clrex").imm(dec(crm as i64)),
+            0b100 if crm == 0 => Insn::new("ssbb"),
+            0b100 if crm == 4 => Insn::new("pssbb"),
+            0b100 => Insn::new("dsb").op(barrier_option(crm)),
+            0b101 => Insn::new("dmb").op(barrier_option(crm)),
+            0b110 if crm == 15 => Insn::new("isb"),
+            0b110 => Insn::new("isb").imm(dec(crm as i64)),
+            0b111 if crm == 0 => Insn::new("sb"),
+            _ => return None,
+        };
+        return Some(insn);
+    }
+    if !l && op0 == 0 && crn == 4 && rt == 31 {
+        let field = match (op1, op2) {
+            (0, 3) => "uao",
+            (0, 4) => "pan",
+            (0, 5) => "spsel",
+            (3, 1) => "ssbs",
+            (3, 2) => "dit",
+            (3, 4) => "tco",
+            (3, 6) => "daifset",
+            (3, 7) => "daifclr",
+            _ => return None,
+        };
+        return Some(
+            Insn::new("msr")
+                .op(Name(field.to_string()))
+                .imm(hex(crm as u64)),
+        );
+    }
+    if op0 == 1 {
+        let known = match (op1, crn, crm, op2) {
+            (3, 7, 4, 1) => Some(("dc", "zva")),
+            (3, 7, 10, 1) => Some(("dc", "cvac")),
+            (3, 7, 11, 1) => Some(("dc", "cvau")),
+            (3, 7, 12, 1) => Some(("dc", "cvap")),
+            (3, 7, 14, 1) => Some(("dc", "civac")),
+            (0, 7, 6, 1) => Some(("dc", "ivac")),
+            (3, 7, 5, 1) => Some(("ic", "ivau")),
+            (0, 7, 5, 0) => Some(("ic", "iallu")),
+            (0, 7, 1, 0) => Some(("ic", "ialluis")),
+            _ => None,
+        };
+        if let (false, Some((name, op))) = (l, known) {
+            let insn = Insn::new(name).op(Name(op.to_string()));
+            return Some(if rt == 31 { insn } else { insn.reg(x(rt)) });
+        }
+        let operands = |insn: Insn| {
+            insn.imm(dec(op1 as i64))
+                .op(Name(format!("c{}", crn)))
+                .op(Name(format!("c{}", crm)))
+                .imm(dec(op2 as i64))
+        };
+        return Some(if l {
+            operands(Insn::new("sysl").reg(x(rt))).writes(rt)
+        } else if rt == 31 {
+            operands(Insn::new("sys"))
+        } else {
+            operands(Insn::new("sys")).reg(x(rt))
+        });
+    }
+    if op0 >= 2 {
+        let name = Name(system_register(op0, op1, crn, crm, op2));
+        return Some(if l {
+            Insn::new("mrs").reg(x(rt)).op(name).writes(rt)
+        } else {
+            Insn::new("msr").op(name).reg(x(rt))
+        });
+    }
+    None
+}
+
+fn hint(op: u32) -> Insn {
+    let name = match op {
+        0x00 => "nop",
+        0x01 => "yield",
+        0x02 => "wfe",
+        0x03 => "wfi",
+        0x04 => "sev",
+        0x05 => "sevl",
+        0x07 => "xpaclri",
+        0x08 => "pacia1716",
+        0x0a => "pacib1716",
+        0x0c => "autia1716",
+        0x0e => "autib1716",
+        0x10 => "esb",
+        0x14 => "csdb",
+        0x18 => "paciaz",
+        0x19 => "paciasp",
+        0x1a => "pacibz",
+        0x1b => "pacibsp",
+        0x1c => "autiaz",
+        0x1d => "autiasp",
+        0x1e => "autibz",
+        0x1f => "autibsp",
+        0x20 => "bti",
+        0x22 => return Insn::new("bti").op(Name("c".to_string())),
+        0x24 => return Insn::new("bti").op(Name("j".to_string())),
+        0x26 => return Insn::new("bti").op(Name("jc".to_string())),
+        _ => return Insn::new("hint").imm(hex(op as u64)),
+    };
+    Insn::new(name)
+}
+
+fn barrier_option(crm: u32) -> Operand {
+    let name = match crm {
+        1 => "oshld",
+        2 => "oshst",
+        3 => "osh",
+        5 => "nshld",
+        6 => "nshst",
+        7 => "nsh",
+        9 => "ishld",
+        10 => "ishst",
+        11 => "ish",
+        13 => "ld",
+        14 => "st",
+        15 => "sy",
+        _ => return Imm(hex(crm as u64)),
+    };
+    Name(name.to_string())
+}
+
+fn system_register(op0: u32, op1: u32, crn: u32, crm: u32, op2: u32) -> String {
+    let name = match (op0, op1, crn, crm, op2) {
+        (3, 3, 13, 0, 2) => "tpidr_el0",
+        (3, 3, 13, 0, 3) => "tpidrro_el0",
+        (3, 0, 13, 0, 4) => "tpidr_el1",
+        (3, 3, 4, 2, 0) => "nzcv",
+        (3, 3, 4, 2, 1) => "daif",
+        (3, 3, 4, 4, 0) => "fpcr",
+        (3, 3, 4, 4, 1) => "fpsr",
+        (3, 3, 0, 0, 1) => "ctr_el0",
+        (3, 3, 0, 0, 7) => "dczid_el0",
+        (3, 3, 14, 0, 0) => "cntfrq_el0",
+        (3, 3, 14, 0, 1) => "cntpct_el0",
+        (3, 3, 14, 0, 2) => "cntvct_el0",
+        (3, 0, 0, 0, 0) => "midr_el1",
+        (3, 0, 0, 0, 5) => "mpidr_el1",
+        (3, 0, 4, 2, 2) => "currentel",
+        (3, 0, 4, 0, 0) => "spsr_el1",
+        (3, 0, 4, 0, 1) => "elr_el1",
+        (3, 0, 4, 1, 0) => "sp_el0",
+        (3, 0, 1, 0, 0) => "sctlr_el1",
+        (3, 0, 12, 0, 0) => "vbar_el1",
+        (3, 0, 5, 2, 0) => "esr_el1",
+        (3, 0, 6, 0, 0) => "far_el1",
+        (3, 0, 2, 0, 0) => "ttbr0_el1",
+        (3, 0, 2, 0, 1) => "ttbr1_el1",
+        (3, 0, 2, 0, 2) => "tcr_el1",
+        (3, 0, 10, 2, 0) => "mair_el1",
+        _ => return format!("s{}_{}_c{}_c{}_{}", op0, op1, crn, crm, op2),
+    };
+    name.to_string()
+}
+
+// ---------------------------------------------------------------------------
+// Loads and stores
+
+fn load_store(w: u32, pc: u64) -> Option<Insn> {
+    if bits(w, 29, 24) == 0b001000 && !bit(w, 26) {
+        return load_store_exclusive(w);
+    }
+    if !bit(w, 31) && (bits(w, 29, 23) == 0b0011000 || bits(w, 29, 23) == 0b0011001) {
+        return load_store_structures(w);
+    }
+    if bits(w, 29, 27) == 0b011 && bits(w, 25, 24) == 0 {
+        return load_literal(w, pc);
+    }
+    if bits(w, 29, 27) == 0b101 {
+        return load_store_pair(w);
+    }
+    if bits(w, 29, 27) == 0b111 {
+        return load_store_register(w);
+    }
+    None
+}
+
+fn load_store_exclusive(w: u32) -> Option<Insn> {
+    let size = bits(w, 31, 30);
+    let o2 = bit(w, 23);
+    let l = bit(w, 22);
+    let o1 = bit(w, 21);
+    let o0 = bit(w, 15);
+    let rs = bits(w, 20, 16);
+    let rt2 = bits(w, 14, 10);
+    let rn = bits(w, 9, 5);
+    let rt = bits(w, 4, 0);
+    let suffix = match size {
+        0 => "b",
+        1 => "h",
+        _ => "",
+    };
+    let is64 = size == 3;
+    let insn = match (o2, o1) {
+        (false, false) => {
+            let name = match (l, o0) {
+                (false, false) => "stxr",
+                (false, true) => "stlxr",
+                (true, false) => "ldxr",
+                (true, true) => "ldaxr",
+            };
+            let insn = Insn::new(format!("{}{}", name, suffix));
+            if l {
+                insn.reg(reg(rt, is64)).writes(rt)
+            } else {
+                insn.reg(reg(rs, false)).reg(reg(rt, is64)).writes(rs)
+            }
+        }
+        (false, true) if size < 2 => {
+            if rt2 != 31 || rs % 2 == 1 || rt % 2 == 1 {
+                return None;
+            }
+            let name = format!(
+                "casp{}{}",
+                if l { "a" } else { "" },
+                if o0 { "l" } else { "" }
+            );
+            let wide = size == 1;
+            Insn::new(name)
+                .reg(reg(rs, wide))
+                .reg(reg(rs + 1, wide))
+                .reg(reg(rt, wide))
+                .reg(reg(rt + 1, wide))
+                .writes(rs)
+                .writes(rs + 1)
+        }
+        (false, true) => {
+            let name = match (l, o0) {
+                (false, false) => "stxp",
+                (false, true) => "stlxp",
+                (true, false) => "ldxp",
+                (true, true) => "ldaxp",
+            };
+            let insn = Insn::new(name);
+            if l {
+                insn.reg(reg(rt, is64))
+                    .reg(reg(rt2, is64))
+                    .writes(rt)
+                    .writes(rt2)
+            } else {
+                insn.reg(reg(rs, false))
+                    .reg(reg(rt, is64))
+                    .reg(reg(rt2, is64))
+                    .writes(rs)
+            }
+        }
+        (true, false) => {
+            let name = match (l, o0) {
+                (false, true) => "stlr",
+                (true, true) => "ldar",
+                (false, false) => "stllr",
+                (true, false) => "ldlar",
+            };
+            let insn = Insn::new(format!("{}{}", name, suffix)).reg(reg(rt, is64));
+            if l {
+                insn.writes(rt)
+            } else {
+                insn
+            }
+        }
+        (true, true) => {
+            if rt2 != 31 {
+                return None;
+            }
+            let name = format!(
+                "cas{}{}{}",
+                if l { "a" } else { "" },
+                if o0 { "l" } else { "" },
+                suffix
+            );
+            Insn::new(name)
+                .reg(reg(rs, is64))
+                .reg(reg(rt, is64))
+                .writes(rs)
+        }
+    };
+    Some(insn.op(mem_base(rn)))
+}
+
+fn load_store_structures(w: u32) -> Option<Insn> {
+    let q = bit(w, 30);
+    let l = bit(w, 22);
+    let post = bit(w, 23);
+    let rm = bits(w, 20, 16);
+    let size = bits(w, 11, 10);
+    let rn = bits(w, 9, 5);
+    let rt = bits(w, 4, 0);
+    if bit(w, 21) || (!post && rm != 0) {
+        return None;
+    }
+    let (n, regs) = match bits(w, 15, 12) {
+        0b0000 => (4, 4),
+        0b0010 => (1, 4),
+        0b0100 => (3, 3),
+        0b0110 => (1, 3),
+        0b0111 => (1, 1),
+        0b1000 => (2, 2),
+        0b1010 => (1, 2),
+        _ => return None,
+    };
+    if size == 3 && !q && n != 1 {
+        return None;
+    }
+    let arrangement = vector_arrangement(size, q)?;
+    let list = (0..regs)
+        .map(|i| format!("v{}.{}", (rt + i) % 32, arrangement))
+        .collect();
+    let name = format!("{}{}", if l { "ld" } else { "st" }, n);
+    let insn = Insn::new(name).op(RegList(list)).op(mem_base(rn));
+    Some(if !post {
+        insn
+    } else if rm == 31 {
+        insn.imm(dec(regs as i64 * if q { 16 } else { 8 }))
+    } else {
+        insn.reg(x(rm))
+    })
+}
+
+fn load_literal(w: u32, pc: u64) -> Option<Insn> {
+    let rt = bits(w, 4, 0);
+    let target = pc.wrapping_add((sext(bits(w, 23, 5), 19) * 4) as u64);
+    use RegClass::*;
+    let (name, class, scale) = match (bit(w, 26), bits(w, 31, 30)) {
+        (false, 0) => ("ldr", W, 2),
+        (false, 1) => ("ldr", X, 3),
+        (false, 2) => ("ldrsw", X, 2),
+        (false, _) => ("prfm", Prefetch, 3),
+        (true, 0) => ("ldr", S, 2),
+        (true, 1) => ("ldr", D, 3),
+        (true, 2) => ("ldr", Q, 4),
+        _ => return None,
+    };
+    let mut insn = Insn::new(name).op(class.name(rt)).op(Target(target));
+    if class == W || class == X {
+        insn = insn.writes(rt);
+    }
+    insn.data_use = class.data_use(scale);
+    Some(insn)
+}
+
+fn load_store_pair(w: u32) -> Option<Insn> {
+    let opc = bits(w, 31, 30);
+    let v = bit(w, 26);
+    let index = bits(w, 25, 23);
+    let l = bit(w, 22);
+    let rt2 = bits(w, 14, 10);
+    let rn = bits(w, 9, 5);
+    let rt = bits(w, 4, 0);
+    let (name, scale, register): (&str, i64, fn(u32) -> String) = match (v, opc) {
+        (false, 0) => (if l { "ldp" } else { "stp" }, 4, |n| reg(n, false)),
+        (false, 1) if l && index != 0 => ("ldpsw", 4, x),
+        (false, 2) => (if l { "ldp" } else { "stp" }, 8, x),
+        (true, 0) => (if l { "ldp" } else { "stp" }, 4, |n| format!("s{}", n)),
+        (true, 1) => (if l { "ldp" } else { "stp" }, 8, |n| format!("d{}", n)),
+        (true, 2) => (if l { "ldp" } else { "stp" }, 16, |n| format!("q{}", n)),
+        _ => return None,
+    };
+    let offset = sext(bits(w, 21, 15), 7) * scale;
+    let name = match index {
+        0b000 => name.replace('p', "np"),
+        0b001..=0b011 => name.to_string(),
+        _ => return None,
+    };
+    let mut insn = Insn::new(name).reg(register(rt)).reg(register(rt2));
+    if l && !v {
+        insn = insn.writes(rt).writes(rt2);
+    }
+    Some(match index {
+        0b001 => insn.op(mem_base(rn)).imm(dec(offset)).writes(rn),
+        0b011 => insn
+            .op(Mem {
+                base: xsp(rn),
+                offset: vec![Imm(dec(offset))],
+                writeback: true,
+            })
+            .writes(rn),
+        _ => insn.op(mem(rn, offset)),
+    })
+}
+
+fn prefetch_op(rt: u32) -> Operand {
+    let kind = match rt >> 3 {
+        0 => "pld",
+        1 => "pli",
+        2 => "pst",
+        _ => return Imm(hex(rt as u64)),
+    };
+    let target = match (rt >> 1) & 3 {
+        0 => "l1",
+        1 => "l2",
+        2 => "l3",
+        _ => return Imm(hex(rt as u64)),
+    };
+    let policy = if rt & 1 == 0 { "keep" } else { "strm" };
+    Name(format!("{}{}{}", kind, target, policy))
+}
+
+/// Register class of a single-register load or store.
+#[derive(Clone, Copy, PartialEq)]
+enum RegClass {
+    W,
+    X,
+    B,
+    H,
+    S,
+    D,
+    Q,
+    Prefetch,
+}
+
+impl RegClass {
+    fn name(self, n: u32) -> Operand {
+        match self {
+            RegClass::W => Reg(reg(n, false)),
+            RegClass::X => Reg(x(n)),
+            RegClass::B => Reg(format!("b{}", n)),
+            RegClass::H => Reg(format!("h{}", n)),
+            RegClass::S => Reg(format!("s{}", n)),
+            RegClass::D => Reg(format!("d{}", n)),
+            RegClass::Q => Reg(format!("q{}", n)),
+            RegClass::Prefetch => prefetch_op(n),
+        }
+    }
+
+    /// How a load or store of `1 << scale` bytes into the register uses them.
+    fn data_use(self, scale: u32) -> DataUse {
+        match self {
+            RegClass::S => DataUse::Float32,
+            RegClass::D => DataUse::Float64,
+            RegClass::Prefetch => DataUse::Address,
+            // 64 位的整数可能是指针
+            _ => DataUse::Integer(1 << scale),
+        }
+    }
+}
+
+fn load_store_register(w: u32) -> Option<Insn> {
+    let size = bits(w, 31, 30);
+    let v = bit(w, 26);
+    let opc = bits(w, 23, 22);
+    let rn = bits(w, 9, 5);
+    let rt = bits(w, 4, 0);
+    if !v && !bit(w, 24) && bit(w, 21) && bits(w, 11, 10) == 0 {
+        return atomic(w);
+    }
+
+    use RegClass::*;
+    let (name, class, scale) = match (v, size, opc) {
+        (false, 0, 0) => ("strb", W, 0),
+        (false, 0, 1) => ("ldrb", W, 0),
+        (false, 0, 2) => ("ldrsb", X, 0),
+        (false, 0, 3) => ("ldrsb", W, 0),
+        (false, 1, 0) => ("strh", W, 1),
+        (false, 1, 1) => ("ldrh", W, 1),
+        (false, 1, 2) => ("ldrsh", X, 1),
+        (false, 1, 3) => ("ldrsh", W, 1),
+        (false, 2, 0) => ("str", W, 2),
+        (false, 2, 1) => ("ldr", W, 2),
+        (false, 2, 2) => ("ldrsw", X, 2),
+        (false, 3, 0) => ("str", X, 3),
+        (false, 3, 1) => ("ldr", X, 3),
+        (false, 3, 2) => ("prfm", Prefetch, 3),
+        (true, 0, 0) => ("str", B, 0),
+        (true, 0, 1) => ("ldr", B, 0),
+        (true, 0, 2) => ("str", Q, 4),
+        (true, 0, 3) => ("ldr", Q, 4),
+        (true, 1, 0) => ("str", H, 1),
+        (true, 1, 1) => ("ldr", H, 1),
+        (true, 2, 0) => ("str", S, 2),
+        (true, 2, 1) => ("ldr", S, 2),
+        (true, 3, 0) => ("str", D, 3),
+        (true, 3, 1) => ("ldr", D, 3),
+        _ => return None,
+    };
+    let is_load = name.starts_with("ld");
+    let gpr_load = is_load && (class == W || class == X);
+    let finish = |name: String| {
+        let insn = Insn::new(name).op(class.name(rt));
+        if gpr_load {
+            insn.writes(rt)
+        } else {
+            insn
+        }
+    };
+
+    if bit(w, 24) {
+        let offset = (bits(w, 21, 10) as u64) << scale;
+        let mut insn = finish(name.to_string()).op(mem(rn, offset as i64));
+        insn.page_ref = Some((rn, offset));
+        insn.data_use = class.data_use(scale);
+        return Some(insn);
+    }
+
+    if !bit(w, 21) {
+        let imm9 = sext(bits(w, 20, 12), 9);
+        let renamed = |infix: &str| {
+            if name == "prfm" {
+                "prfum".to_string()
+            } else {
+                format!("{}{}{}", &name[..2], infix, &name[3..])
+            }
+        };
+        return match bits(w, 11, 10) {
+            0b00 => Some(finish(renamed("ur")).op(mem(rn, imm9))),
+            0b01 if class != Prefetch => Some(
+                finish(name.to_string())
+                    .op(mem_base(rn))
+                    .imm(dec(imm9))
+                    .writes(rn),
+            ),
+            0b10 if !v && class != Prefetch => Some(finish(renamed("tr")).op(mem(rn, imm9))),
+            0b11 if class != Prefetch => Some(
+                finish(name.to_string())
+                    .op(Mem {
+                        base: xsp(rn),
+                        offset: vec![Imm(dec(imm9))],
+                        writeback: true,
+                    })
+                    .writes(rn),
+            ),
+            _ => None,
+        };
+    }
+
+    match bits(w, 11, 10) {
+        0b10 => {
+            let option = bits(w, 15, 13);
+            let s = bit(w, 12);
+            let rm = bits(w, 20, 16);
+            let index = if option & 1 == 1 {
+                x(rm)
+            } else {
+                reg(rm, false)
+            };
+            let amount = if s { scale } else { 0 };
+            let extend = match option {
+                0b010 => Some(Extend("uxtw", s.then_some(amount))),
+                0b011 if s => Some(Shift("lsl", amount)),
+                0b011 => None,
+                0b110 => Some(Extend("sxtw", s.then_some(amount))),
+                0b111 => Some(Extend("sxtx", s.then_some(amount))),
+                _ => return None,
+            };
+            let mut offset = vec![Reg(index)];
+            offset.extend(extend);
+            Some(finish(name.to_string()).op(Mem {
+                base: xsp(rn),
+                offset,
+                writeback: false,
+            }))
+        }
+        _ => None,
+    }
+}
+
+fn atomic(w: u32) -> Option<Insn> {
+    let size = bits(w, 31, 30);
+    let a = bit(w, 23);
+    let r = bit(w, 22);
+    let rs = bits(w, 20, 16);
+    let rn = bits(w, 9, 5);
+    let rt = bits(w, 4, 0);
+    let size_suffix = match size {
+        0 => "b",
+        1 => "h",
+        _ => "",
+    };
+    let is64 = size == 3;
+    let op = match (bit(w, 15), bits(w, 14, 12)) {
+        (false, 0) => "ldadd",
+        (false, 1) => "ldclr",
+        (false, 2) => "ldeor",
+        (false, 3) => "ldset",
+        (false, 4) => "ldsmax",
+        (false, 5) => "ldsmin",
+        (false, 6) => "ldumax",
+        (false, 7) => "ldumin",
+        (true, 0) => "swp",
+        (true, 4) if a && !r && rs == 31 => {
+            return Some(
+                Insn::new(format!("ldapr{}", size_suffix))
+                    .reg(reg(rt, is64))
+                    .op(mem_base(rn))
+                    .writes(rt),
+            );
+        }
+        _ => return None,
+    };
+    let order = format!("{}{}", if a { "a" } else { "" }, if r { "l" } else { "" });
+    if op.starts_with("ld") && !a && rt == 31 {
+        let name = format!("st{}{}{}", &op[2..], order, size_suffix);
+        return Some(Insn::new(name).reg(reg(rs, is64)).op(mem_base(rn)));
+    }
+    let name = format!("{}{}{}", op, order, size_suffix);
+    Some(
+        Insn::new(name)
+            .reg(reg(rs, is64))
+            .reg(reg(rt, is64))
+            .op(mem_base(rn))
+            .writes(rt),
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Data processing -- register
+
+fn dp_reg(w: u32) -> Option<Insn> {
+    let sf = bit(w, 31);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    let rm = bits(w, 20, 16);
+    if !bit(w, 28) {
+        return if !bit(w, 24) {
+            logical_shifted(w)
+        } else if !bit(w, 21) {
+            add_sub_shifted(w)
+        } else {
+            add_sub_extended(w)
+        };
+    }
+    match bits(w, 24, 21) {
+        0b0000 if bits(w, 15, 10) == 0 => {
+            let name = match (bit(w, 30), bit(w, 29)) {
+                (false, false) => "adc",
+                (false, true) => "adcs",
+                (true, false) if rn == 31 => "ngc",
+                (true, false) => "sbc",
+                (true, true) if rn == 31 => "ngcs",
+                (true, true) => "sbcs",
+            };
+            let insn = Insn::new(name).reg(reg(rd, sf));
+            let insn = if name.starts_with("ng") {
+                insn
+            } else {
+                insn.reg(reg(rn, sf))
+            };
+            Some(insn.reg(reg(rm, sf)).writes(rd))
+        }
+        0b0010 if bit(w, 29) && !bit(w, 10) && !bit(w, 4) => {
+            let name = if bit(w, 30) { "ccmp" } else { "ccmn" };
+            let insn = Insn::new(name).reg(reg(rn, sf));
+            let insn = if bit(w, 11) {
+                insn.imm(dec(rm as i64))
+            } else {
+                insn.reg(reg(rm, sf))
+            };
+            Some(
+                insn.imm(hex(bits(w, 3, 0) as u64))
+                    .op(cond(bits(w, 15, 12))),
+            )
+        }
+        0b0100 if !bit(w, 29) && !bit(w, 11) => {
+            let c = bits(w, 15, 12);
+            let op = (bit(w, 30), bit(w, 10));
+            let invertible = c < 14;
+            let insn = match op {
+                (false, true) if invertible && rn == 31 && rm == 31 => {
+                    Insn::new("cset").reg(reg(rd, sf)).op(cond(c ^ 1))
+                }
+                (true, false) if invertible && rn == 31 && rm == 31 => {
+                    Insn::new("csetm").reg(reg(rd, sf)).op(cond(c ^ 1))
+                }
+                (false, true) if invertible && rn == rm => Insn::new("cinc")
+                    .reg(reg(rd, sf))
+                    .reg(reg(rn, sf))
+                    .op(cond(c ^ 1)),
+                (true, false) if invertible && rn == rm => Insn::new("cinv")
+                    .reg(reg(rd, sf))
+                    .reg(reg(rn, sf))
+                    .op(cond(c ^ 1)),
+                (true, true) if invertible && rn == rm => Insn::new("cneg")
+                    .reg(reg(rd, sf))
+                    .reg(reg(rn, sf))
+                    .op(cond(c ^ 1)),
+                _ => {
+                    let name = match op {
+                        (false, false) => "csel",
+                        (false, true) => "csinc",
+                        (true, false) => "csinv",
+                        (true, true) => "csneg",
+                    };
+                    Insn::new(name)
+                        .reg(reg(rd, sf))
+                        .reg(reg(rn, sf))
+                        .reg(reg(rm, sf))
+                        .op(cond(c))
+                }
+            };
+            Some(insn.writes(rd))
+        }
+        0b0110 if !bit(w, 29) => {
+            let opcode = bits(w, 15, 10);
+            if bit(w, 30) {
+                if rm != 0 {
+                    return None;
+                }
+                let name = match (opcode, sf) {
+                    (0b000000, _) => "rbit",
+                    (0b000001, _) => "rev16",
+                    (0b000010, true) => "rev32",
+                    (0b000010, false) | (0b000011, true) => "rev",
+                    (0b000100, _) => "clz",
+                    (0b000101, _) => "cls",
+                    _ => return None,
+                };
+                return Some(Insn::new(name).reg(reg(rd, sf)).reg(reg(rn, sf)).writes(rd));
+            }
+            let (name, crc_wide) = match opcode {
+                0b000010 => ("udiv", None),
+                0b000011 => ("sdiv", None),
+                0b001000 => ("lsl", None),
+                0b001001 => ("lsr", None),
+                0b001010 => ("asr", None),
+                0b001011 => ("ror", None),
+                0b010000 => ("crc32b", Some(false)),
+                0b010001 => ("crc32h", Some(false)),
+                0b010010 => ("crc32w", Some(false)),
+                0b010011 => ("crc32x", Some(true)),
+                0b010100 => ("crc32cb", Some(false)),
+                0b010101 => ("crc32ch", Some(false)),
+                0b010110 => ("crc32cw", Some(false)),
+                0b010111 => ("crc32cx", Some(true)),
+                _ => return None,
+            };
+            let insn = match crc_wide {
+                Some(wide) if wide != sf => return None,
+                Some(wide) => Insn::new(name)
+                    .reg(reg(rd, false))
+                    .reg(reg(rn, false))
+                    .reg(reg(rm, wide)),
+                None => Insn::new(name)
+                    .reg(reg(rd, sf))
+                    .reg(reg(rn, sf))
+                    .reg(reg(rm, sf)),
+            };
+            Some(insn.writes(rd))
+        }
+        0b1000..=0b1111 if bits(w, 30, 29) == 0 => {
+            let ra = bits(w, 14, 10);
+            let o0 = bit(w, 15);
+            let long = |name: &str, short: &str| {
+                if ra == 31 {
+                    Insn::new(short)
+                        .reg(x(rd))
+                        .reg(reg(rn, false))
+                        .reg(reg(rm, false))
+                } else {
+                    Insn::new(name)
+                        .reg(x(rd))
+                        .reg(reg(rn, false))
+                        .reg(reg(rm, false))
+                        .reg(x(ra))
+                }
+            };
+            let insn = match (bits(w, 23, 21), o0) {
+                (0b000, _) => {
+                    let (name, short) = if o0 {
+                        ("msub", "mneg")
+                    } else {
+                        ("madd", "mul")
+                    };
+                    let insn = Insn::new(if ra == 31 { short } else { name })
+                        .reg(reg(rd, sf))
+                        .reg(reg(rn, sf))
+                        .reg(reg(rm, sf));
+                    if ra == 31 {
+                        insn
+                    } else {
+                        insn.reg(reg(ra, sf))
+                    }
+                }
+                (0b001, false) if sf => long("smaddl", "smull"),
+                (0b001, true) if sf => long("smsubl", "smnegl"),
+                (0b101, false) if sf => long("umaddl", "umull"),
+                (0b101, true) if sf => long("umsubl", "umnegl"),
+                (0b010, false) if sf => Insn::new("smulh").reg(x(rd)).reg(x(rn)).reg(x(rm)),
+                (0b110, false) if sf => Insn::new("umulh").reg(x(rd)).reg(x(rn)).reg(x(rm)),
+                _ => return None,
+            };
+            Some(insn.writes(rd))
+        }
+        _ => None,
+    }
+}
+
+const SHIFTS: [&str; 4] = ["lsl", "lsr", "asr", "ror"];
+
+fn logical_shifted(w: u32) -> Option<Insn> {
+    let sf = bit(w, 31);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    let rm = bits(w, 20, 16);
+    let shift = bits(w, 23, 22);
+    let amount = bits(w, 15, 10);
+    if !sf && amount >= 32 {
+        return None;
+    }
+    let shift_op = (shift != 0 || amount != 0).then(|| Shift(SHIFTS[shift as usize], amount));
+    let opc = bits(w, 30, 29);
+    let n = bit(w, 21);
+    let insn = match (opc, n) {
+        (0b01, false) if rn == 31 && shift_op.is_none() => {
+            return Some(
+                Insn::new("mov")
+                    .reg(reg(rd, sf))
+                    .reg(reg(rm, sf))
+                    .writes(rd),
+            );
+        }
+        (0b01, true) if rn == 31 => Insn::new("mvn")
+            .reg(reg(rd, sf))
+            .reg(reg(rm, sf))
+            .writes(rd),
+        (0b11, false) if rd == 31 => Insn::new("tst").reg(reg(rn, sf)).reg(reg(rm, sf)),
+        _ => {
+            let name = match (opc, n) {
+                (0b00, false) => "and",
+                (0b00, true) => "bic",
+                (0b01, false) => "orr",
+                (0b01, true) => "orn",
+                (0b10, false) => "eor",
+                (0b10, true) => "eon",
+                (_, false) => "ands",
+                (_, true) => "bics",
+            };
+            Insn::new(name)
+                .reg(reg(rd, sf))
+                .reg(reg(rn, sf))
+                .reg(reg(rm, sf))
+                .writes(rd)
+        }
+    };
+    Some(match shift_op {
+        Some(shift_op) => insn.op(shift_op),
+        None => insn,
+    })
+}
+
+fn add_sub_shifted(w: u32) -> Option<Insn> {
+    let sf = bit(w, 31);
+    let op = bit(w, 30);
+    let s = bit(w, 29);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    let rm = bits(w, 20, 16);
+    let shift = bits(w, 23, 22);
+    let amount = bits(w, 15, 10);
+    if shift == 3 || (!sf && amount >= 32) {
+        return None;
+    }
+    let insn = if s && rd == 31 {
+        Insn::new(if op { "cmp" } else { "cmn" }).reg(reg(rn, sf))
+    } else if op && rn == 31 {
+        Insn::new(if s { "negs" } else { "neg" })
+            .reg(reg(rd, sf))
+            .writes(rd)
+    } else {
+        let name = match (op, s) {
+            (false, false) => "add",
+            (false, true) => "adds",
+            (true, false) => "sub",
+            (true, true) => "subs",
+        };
+        Insn::new(name).reg(reg(rd, sf)).reg(reg(rn, sf)).writes(rd)
+    };
+    let insn = insn.reg(reg(rm, sf));
+    Some(if shift != 0 || amount != 0 {
+        insn.op(Shift(SHIFTS[shift as usize], amount))
+    } else {
+        insn
+    })
+}
+
+fn add_sub_extended(w: u32) -> Option<Insn> {
+    let sf = bit(w, 31);
+    let op = bit(w, 30);
+    let s = bit(w, 29);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    let rm = bits(w, 20, 16);
+    let option = bits(w, 15, 13);
+    let imm3 = bits(w, 12, 10);
+    if bits(w, 23, 22) != 0 || imm3 > 4 {
+        return None;
+    }
+    const EXTENDS: [&str; 8] = [
+        "uxtb", "uxth", "uxtw", "uxtx", "sxtb", "sxth", "sxtw", "sxtx",
+    ];
+    let index = if sf && option & 3 == 3 {
+        x(rm)
+    } else {
+        reg(rm, false)
+    };
+    let uses_sp = (rd == 31 && !s) || rn == 31;
+    let extend = if uses_sp && option == if sf { 3 } else { 2 } {
+        (imm3 != 0).then_some(Shift("lsl", imm3))
+    } else {
+        Some(Extend(
+            EXTENDS[option as usize],
+            (imm3 != 0).then_some(imm3),
+        ))
+    };
+    let insn = if s && rd == 31 {
+        Insn::new(if op { "cmp" } else { "cmn" })
+    } else {
+        let name = match (op, s) {
+            (false, false) => "add",
+            (false, true) => "adds",
+            (true, false) => "sub",
+            (true, true) => "subs",
+        };
+        let dest = if s { reg(rd, sf) } else { reg_sp(rd, sf) };
+        Insn::new(name).reg(dest).writes(rd)
+    };
+    let insn = insn.reg(reg_sp(rn, sf)).reg(index);
+    Some(match extend {
+        Some(extend) => insn.op(extend),
+        None => insn,
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Floating point and Advanced SIMD
+
+fn simd_fp(w: u32) -> Option<Insn> {
+    if bits(w, 28, 24) == 0b11110 && !bit(w, 30) && !bit(w, 29) {
+        return fp_scalar(w);
+    }
+    if bits(w, 28, 24) == 0b11111 && bits(w, 31, 29) == 0 {
+        return fp_three_source(w);
+    }
+    if bit(w, 31) {
+        return None;
+    }
+    if bits(w, 28, 19) == 0b01111_00000 && bit(w, 10) {
+        return simd_modified_immediate(w);
+    }
+    if bits(w, 28, 23) == 0b011110 && bit(w, 10) {
+        return simd_shift_immediate(w);
+    }
+    if bits(w, 28, 24) == 0b01111 && !bit(w, 10) {
+        return simd_by_element(w);
+    }
+    if bits(w, 28, 24) != 0b01110 {
+        return None;
+    }
+    if bit(w, 29) && bits(w, 23, 21) == 0 && !bit(w, 15) && !bit(w, 10) {
+        let bytes = if bit(w, 30) { "16b" } else { "8b" };
+        let imm4 = bits(w, 14, 11);
+        if !bit(w, 30) && imm4 >= 8 {
+            return None;
+        }
+        return Some(
+            Insn::new("ext")
+                .reg(vreg(bits(w, 4, 0), bytes))
+                .reg(vreg(bits(w, 9, 5), bytes))
+                .reg(vreg(bits(w, 20, 16), bytes))
+                .imm(dec(imm4 as i64)),
+        );
+    }
+    if !bit(w, 29) && !bit(w, 21) && !bit(w, 15) && bits(w, 11, 10) == 0b10 {
+        return simd_permute(w);
+    }
+    if !bit(w, 29) && bits(w, 23, 21) == 0 && !bit(w, 15) && bits(w, 11, 10) == 0 {
+        let bytes = if bit(w, 30) { "16b" } else { "8b" };
+        let rn = bits(w, 9, 5);
+        let table = (0..=bits(w, 14, 13))
+            .map(|i| vreg((rn + i) % 32, "16b"))
+            .collect();
+        let name = if bit(w, 12) { "tbx" } else { "tbl" };
+        return Some(
+            Insn::new(name)
+                .reg(vreg(bits(w, 4, 0), bytes))
+                .op(RegList(table))
+                .reg(vreg(bits(w, 20, 16), bytes)),
+        );
+    }
+    if bits(w, 23, 21) == 0 && !bit(w, 15) && bit(w, 10) {
+        return simd_copy(w);
+    }
+    if bit(w, 21) && bit(w, 10) {
+        return simd_three_same(w);
+    }
+    if bits(w, 21, 17) == 0b10000 && bits(w, 11, 10) == 0b10 {
+        return simd_two_reg_misc(w);
+    }
+    if bits(w, 21, 17) == 0b11000 && bits(w, 11, 10) == 0b10 {
+        return simd_across_lanes(w);
+    }
+    None
+}
+
+fn fp_register(ftype: u32, n: u32) -> Option<String> {
+    let prefix = match ftype {
+        0 => 's',
+        1 => 'd',
+        3 => 'h',
+        _ => return None,
+    };
+    Some(format!("{}{}", prefix, n))
+}
+
+/// Expand the 8-bit floating point immediate of `fmov` (VFPExpandImm).
+fn fp_immediate(imm8: u32) -> String {
+    let sign = if imm8 & 0x80 != 0 { -1.0 } else { 1.0 };
+    let b = (imm8 >> 6) & 1;
+    let cd = ((imm8 >> 4) & 3) as i32;
+    let exponent = if b == 0 { 1 + cd } else { cd - 3 };
+    let fraction = 1.0 + (imm8 & 0xf) as f64 / 16.0;
+    let value = sign * fraction * 2f64.powi(exponent);
+    format!("#{:.8}", value)
+}
+
+fn fp_scalar(w: u32) -> Option<Insn> {
+    let sf = bit(w, 31);
+    let ftype = bits(w, 23, 22);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    let rm = bits(w, 20, 16);
+    if !bit(w, 21) {
+        return fp_fixed_conversion(w);
+    }
+    if bits(w, 15, 10) == 0 {
+        return fp_int_conversion(w);
+    }
+    if sf {
+        return None;
+    }
+    let fp = |n| fp_register(ftype, n);
+    match bits(w, 11, 10) {
+        0b01 => {
+            let name = if bit(w, 4) { "fccmpe" } else { "fccmp" };
+            Some(
+                Insn::new(name)
+                    .reg(fp(rn)?)
+                    .reg(fp(rm)?)
+                    .imm(hex(bits(w, 3, 0) as u64))
+                    .op(cond(bits(w, 15, 12))),
+            )
+        }
+        0b10 => {
+            let name = match bits(w, 15, 12) {
+                0 => "fmul",
+                1 => "fdiv",
+                2 => "fadd",
+                3 => "fsub",
+                4 => "fmax",
+                5 => "fmin",
+                6 => "fmaxnm",
+                7 => "fminnm",
+                8 => "fnmul",
+                _ => return None,
+            };
+            Some(Insn::new(name).reg(fp(rd)?).reg(fp(rn)?).reg(fp(rm)?))
+        }
+        0b11 => Some(
+            Insn::new("fcsel")
+                .reg(fp(rd)?)
+                .reg(fp(rn)?)
+                .reg(fp(rm)?)
+                .op(cond(bits(w, 15, 12))),
+        ),
+        _ => {
+            if bits(w, 12, 10) == 0b100 && bits(w, 9, 5) == 0 {
+                return Some(
+                    Insn::new("fmov")
+                        .reg(fp(rd)?)
+                        .imm(fp_immediate(bits(w, 20, 13))),
+                );
+            }
+            if bits(w, 13, 10) == 0b1000 && bits(w, 15, 14) == 0 {
+                let insn = match bits(w, 4, 0) {
+                    0b00000 => Insn::new("fcmp").reg(fp(rn)?).reg(fp(rm)?),
+                    0b01000 => Insn::new("fcmp").reg(fp(rn)?).imm("#0.0".to_string()),
+                    0b10000 => Insn::new("fcmpe").reg(fp(rn)?).reg(fp(rm)?),
+                    0b11000 => Insn::new("fcmpe").reg(fp(rn)?).imm("#0.0".to_string()),
+                    _ => return None,
+                };
+                return Some(insn);
+            }
+            if bits(w, 14, 10) == 0b10000 {
+                let opcode = bits(w, 20, 15);
+                if opcode & 0b111100 == 0b000100 {
+                    let dest = fp_register(opcode & 3, rd)?;
+                    if opcode & 3 == ftype {
+                        return None;
+                    }
+                    return Some(Insn::new("fcvt").reg(dest).reg(fp(rn)?));
+                }
+                let name = match opcode {
+                    0 => "fmov",
+                    1 => "fabs",
+                    2 => "fneg",
+                    3 => "fsqrt",
+                    8 => "frintn",
+                    9 => "frintp",
+                    10 => "frintm",
+                    11 => "frintz",
+                    12 => "frinta",
+                    14 => "frintx",
+                    15 => "frinti",
+                    _ => return None,
+                };
+                return Some(Insn::new(name).reg(fp(rd)?).reg(fp(rn)?));
+            }
+            None
+        }
+    }
+}
+
+fn fp_int_conversion(w: u32) -> Option<Insn> {
+    let sf = bit(w, 31);
+    let ftype = bits(w, 23, 22);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    let rmode = bits(w, 20, 19);
+    let opcode = bits(w, 18, 16);
+    let to_int = match (rmode, opcode) {
+        (0, 0) => Some("fcvtns"),
+        (0, 1) => Some("fcvtnu"),
+        (0, 4) => Some("fcvtas"),
+        (0, 5) => Some("fcvtau"),
+        (1, 0) => Some("fcvtps"),
+        (1, 1) => Some("fcvtpu"),
+        (2, 0) => Some("fcvtms"),
+        (2, 1) => Some("fcvtmu"),
+        (3, 0) => Some("fcvtzs"),
+        (3, 1) => Some("fcvtzu"),
+        _ => None,
+    };
+    if let Some(name) = to_int {
+        return Some(
+            Insn::new(name)
+                .reg(reg(rd, sf))
+                .reg(fp_register(ftype, rn)?)
+                .writes(rd),
+        );
+    }
+    match (rmode, opcode) {
+        (0, 2) | (0, 3) => {
+            let name = if opcode == 2 { "scvtf" } else { "ucvtf" };
+            Some(
+                Insn::new(name)
+                    .reg(fp_register(ftype, rd)?)
+                    .reg(reg(rn, sf)),
+            )
+        }
+        (0, 6) | (0, 7) => {
+            let matches = matches!((sf, ftype), (false, 0) | (true, 1) | (_, 3));
+            if !matches {
+                return None;
+            }
+            if opcode == 6 {
+                Some(
+                    Insn::new("fmov")
+                        .reg(reg(rd, sf))
+                        .reg(fp_register(ftype, rn)?)
+                        .writes(rd),
+                )
+            } else {
+                Some(
+                    Insn::new("fmov")
+                        .reg(fp_register(ftype, rd)?)
+                        .reg(reg(rn, sf)),
+                )
+            }
+        }
+        (1, 6) if sf && ftype == 2 => Some(
+            Insn::new("fmov")
+                .reg(x(rd))
+                .reg(format!("v{}.d[1]", rn))
+                .writes(rd),
+        ),
+        (1, 7) if sf && ftype == 2 => {
+            Some(Insn::new("fmov").reg(format!("v{}.d[1]", rd)).reg(x(rn)))
+        }
+        _ => None,
+    }
+}
+
+fn fp_fixed_conversion(w: u32) -> Option<Insn> {
+    let sf = bit(w, 31);
+    let ftype = bits(w, 23, 22);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    let scale = bits(w, 15, 10);
+    if !sf && scale < 32 {
+        return None;
+    }
+    let fbits = dec(64 - scale as i64);
+    match bits(w, 20, 16) {
+        0b00010 | 0b00011 => {
+            let name = if bit(w, 16) { "ucvtf" } else { "scvtf" };
+            Some(
+                Insn::new(name)
+                    .reg(fp_register(ftype, rd)?)
+                    .reg(reg(rn, sf))
+                    .imm(fbits),
+            )
+        }
+        0b11000 | 0b11001 => {
+            let name = if bit(w, 16) { "fcvtzu" } else { "fcvtzs" };
+            Some(
+                Insn::new(name)
+                    .reg(reg(rd, sf))
+                    .reg(fp_register(ftype, rn)?)
+                    .imm(fbits)
+                    .writes(rd),
+            )
+        }
+        _ => None,
+    }
+}
+
+fn fp_three_source(w: u32) -> Option<Insn> {
+    let ftype = bits(w, 23, 22);
+    let name = match (bit(w, 21), bit(w, 15)) {
+        (false, false) => "fmadd",
+        (false, true) => "fmsub",
+        (true, false) => "fnmadd",
+        (true, true) => "fnmsub",
+    };
+    Some(
+        Insn::new(name)
+            .reg(fp_register(ftype, bits(w, 4, 0))?)
+            .reg(fp_register(ftype, bits(w, 9, 5))?)
+            .reg(fp_register(ftype, bits(w, 20, 16))?)
+            .reg(fp_register(ftype, bits(w, 14, 10))?),
+    )
+}
+
+fn vector_arrangement(size: u32, q: bool) -> Option<&'static str> {
+    Some(match (size, q) {
+        (0, false) => "8b",
+        (0, true) => "16b",
+        (1, false) => "4h",
+        (1, true) => "8h",
+        (2, false) => "2s",
+        (2, true) => "4s",
+        (3, false) => "1d",
+        (3, true) => "2d",
+        _ => return None,
+    })
+}
+
+fn vreg(n: u32, arrangement: &str) -> String {
+    format!("v{}.{}", n, arrangement)
+}
+
+fn simd_three_same(w: u32) -> Option<Insn> {
+    let q = bit(w, 30);
+    let u = bit(w, 29);
+    let size = bits(w, 23, 22);
+    let opcode = bits(w, 15, 11);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    let rm = bits(w, 20, 16);
+
+    if opcode >= 0b11000 {
+        let arrangement = match (bit(w, 22), q) {
+            (false, false) => "2s",
+            (false, true) => "4s",
+            (true, true) => "2d",
+            (true, false) => return None,
+        };
+        let name = match (u, bit(w, 23), opcode) {
+            (false, false, 0b11010) => "fadd",
+            (false, true, 0b11010) => "fsub",
+            (true, false, 0b11010) => "faddp",
+            (true, true, 0b11010) => "fabd",
+            (true, false, 0b11011) => "fmul",
+            (false, false, 0b11011) => "fmulx",
+            (true, false, 0b11111) => "fdiv",
+            (false, false, 0b11110) => "fmax",
+            (false, true, 0b11110) => "fmin",
+            (true, false, 0b11110) => "fmaxp",
+            (true, true, 0b11110) => "fminp",
+            (false, false, 0b11000) => "fmaxnm",
+            (false, true, 0b11000) => "fminnm",
+            (false, false, 0b11001) => "fmla",
+            (false, true, 0b11001) => "fmls",
+            (false, false, 0b11100) => "fcmeq",
+            (true, false, 0b11100) => "fcmge",
+            (true, true, 0b11100) => "fcmgt",
+            _ => return None,
+        };
+        return Some(
+            Insn::new(name)
+                .reg(vreg(rd, arrangement))
+                .reg(vreg(rn, arrangement))
+                .reg(vreg(rm, arrangement)),
+        );
+    }
+
+    if opcode == 0b00011 {
+        let bytes = if q { "16b" } else { "8b" };
+        let name = match (u, size) {
+            (false, 0) => "and",
+            (false, 1) => "bic",
+            (false, 2) if rn == rm => {
+                return Some(Insn::new("mov").reg(vreg(rd, bytes)).reg(vreg(rn, bytes)));
+            }
+            (false, 2) => "orr",
+            (false, _) => "orn",
+            (true, 0) => "eor",
+            (true, 1) => "bsl",
+            (true, 2) => "bit",
+            (true, _) => "bif",
+        };
+        return Some(
+            Insn::new(name)
+                .reg(vreg(rd, bytes))
+                .reg(vreg(rn, bytes))
+                .reg(vreg(rm, bytes)),
+        );
+    }
+
+    if size == 3 && !q {
+        return None;
+    }
+    let arrangement = vector_arrangement(size, q)?;
+    let name = match (u, opcode) {
+        (false, 0b00000) if size != 3 => "shadd",
+        (true, 0b00000) if size != 3 => "uhadd",
+        (false, 0b00001) => "sqadd",
+        (true, 0b00001) => "uqadd",
+        (false, 0b00010) if size != 3 => "srhadd",
+        (true, 0b00010) if size != 3 => "urhadd",
+        (false, 0b00101) => "sqsub",
+        (true, 0b00101) => "uqsub",
+        (false, 0b00110) => "cmgt",
+        (true, 0b00110) => "cmhi",
+        (false, 0b00111) => "cmge",
+        (true, 0b00111) => "cmhs",
+        (false, 0b01000) => "sshl",
+        (true, 0b01000) => "ushl",
+        (false, 0b01100) if size != 3 => "smax",
+        (true, 0b01100) if size != 3 => "umax",
+        (false, 0b01101) if size != 3 => "smin",
+        (true, 0b01101) if size != 3 => "umin",
+        (false, 0b01110) if size != 3 => "sabd",
+        (true, 0b01110) if size != 3 => "uabd",
+        (false, 0b10000) => "add",
+        (true, 0b10000) => "sub",
+        (false, 0b10001) => "cmtst",
+        (true, 0b10001) => "cmeq",
+        (false, 0b10011) if size != 3 => "mul",
+        (true, 0b10011) if size == 0 => "pmul",
+        (false, 0b10100) if size != 3 => "smaxp",
+        (true, 0b10100) if size != 3 => "umaxp",
+        (false, 0b10101) if size != 3 => "sminp",
+        (true, 0b10101) if size != 3 => "uminp",
+        (false, 0b10111) => "addp",
+        _ => return None,
+    };
+    Some(
+        Insn::new(name)
+            .reg(vreg(rd, arrangement))
+            .reg(vreg(rn, arrangement))
+            .reg(vreg(rm, arrangement)),
+    )
+}
+
+fn simd_permute(w: u32) -> Option<Insn> {
+    let q = bit(w, 30);
+    let size = bits(w, 23, 22);
+    if size == 3 && !q {
+        return None;
+    }
+    let name = match bits(w, 14, 12) {
+        0b001 => "uzp1",
+        0b010 => "trn1",
+        0b011 => "zip1",
+        0b101 => "uzp2",
+        0b110 => "trn2",
+        0b111 => "zip2",
+        _ => return None,
+    };
+    let arrangement = vector_arrangement(size, q)?;
+    Some(
+        Insn::new(name)
+            .reg(vreg(bits(w, 4, 0), arrangement))
+            .reg(vreg(bits(w, 9, 5), arrangement))
+            .reg(vreg(bits(w, 20, 16), arrangement)),
+    )
+}
+
+/// Vector multiplies where the second source is a single lane, `mul v0.4s, v1.4s, v2.s[1]`.
+fn simd_by_element(w: u32) -> Option<Insn> {
+    let q = bit(w, 30);
+    let u = bit(w, 29);
+    let size = bits(w, 23, 22);
+    let (l, m, h) = (bits(w, 21, 21), bits(w, 20, 20), bits(w, 11, 11));
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    let fp = match (u, bits(w, 15, 12)) {
+        (false, 0b0001) => Some("fmla"),
+        (false, 0b0101) => Some("fmls"),
+        (false, 0b1001) => Some("fmul"),
+        (true, 0b1001) => Some("fmulx"),
+        _ => None,
+    };
+    if let Some(name) = fp {
+        let (arrangement, element, index) = match (size, q) {
+            (2, false) => ("2s", "s", (h << 1) | l),
+            (2, true) => ("4s", "s", (h << 1) | l),
+            (3, true) if l == 0 => ("2d", "d", h),
+            _ => return None,
+        };
+        let rm = bits(w, 20, 16);
+        return Some(
+            Insn::new(name)
+                .reg(vreg(rd, arrangement))
+                .reg(vreg(rn, arrangement))
+                .reg(format!("v{}.{}[{}]", rm, element, index)),
+        );
+    }
+    let name = match (u, bits(w, 15, 12)) {
+        (false, 0b1000) => "mul",
+        (true, 0b0000) => "mla",
+        (true, 0b0100) => "mls",
+        _ => return None,
+    };
+    let (element, index, rm) = match size {
+        1 => ("h", (h << 2) | (l << 1) | m, bits(w, 19, 16)),
+        2 => ("s", (h << 1) | l, bits(w, 20, 16)),
+        _ => return None,
+    };
+    let arrangement = vector_arrangement(size, q)?;
+    Some(
+        Insn::new(name)
+            .reg(vreg(rd, arrangement))
+            .reg(vreg(rn, arrangement))
+            .reg(format!("v{}.{}[{}]", rm, element, index)),
+    )
+}
+
+fn simd_two_reg_misc(w: u32) -> Option<Insn> {
+    let q = bit(w, 30);
+    let u = bit(w, 29);
+    let size = bits(w, 23, 22);
+    let opcode = bits(w, 16, 12);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+
+    // 浮点运算只用 size 的低位区分单/双精度
+    let fp_arrangement = match (bit(w, 22), q) {
+        (false, false) => Some("2s"),
+        (false, true) => Some("4s"),
+        (true, true) => Some("2d"),
+        (true, false) => None,
+    };
+    let fp_name = match (u, bit(w, 23), opcode) {
+        (false, false, 0b11101) => Some("scvtf"),
+        (true, false, 0b11101) => Some("ucvtf"),
+        (false, true, 0b11011) => Some("fcvtzs"),
+        (true, true, 0b11011) => Some("fcvtzu"),
+        (false, true, 0b01111) => Some("fabs"),
+        (true, true, 0b01111) => Some("fneg"),
+        (true, true, 0b11111) => Some("fsqrt"),
+        _ => None,
+    };
+    if let Some(name) = fp_name {
+        let arrangement = fp_arrangement?;
+        return Some(
+            Insn::new(name)
+                .reg(vreg(rd, arrangement))
+                .reg(vreg(rn, arrangement)),
+        );
+    }
+
+    if opcode == 0b10010 && !u {
+        // xtn: 目标元素宽度是源元素的一半
+        let narrow = vector_arrangement(size, q)?;
+        let wide = ["8h", "4s", "2d"].get(size as usize)?;
+        let name = if q { "xtn2" } else { "xtn" };
+        return Some(Insn::new(name).reg(vreg(rd, narrow)).reg(vreg(rn, wide)));
+    }
+
+    let arrangement = vector_arrangement(size, q)?;
+    let zero = |name: &str| {
+        if size == 3 && !q {
+            return None;
+        }
+        Some(
+            Insn::new(name)
+                .reg(vreg(rd, arrangement))
+                .reg(vreg(rn, arrangement))
+                .imm("#0".to_string()),
+        )
+    };
+    let name = match (u, opcode) {
+        (false, 0b00000) if size < 3 => "rev64",
+        (true, 0b00000) if size < 2 => "rev32",
+        (false, 0b00001) if size == 0 => "rev16",
+        (false, 0b00100) if size < 3 => "cls",
+        (true, 0b00100) if size < 3 => "clz",
+        (false, 0b00101) if size == 0 => "cnt",
+        (true, 0b00101) if size == 0 => "mvn",
+        (true, 0b00101) if size == 1 => {
+            let bytes = if q { "16b" } else { "8b" };
+            return Some(Insn::new("rbit").reg(vreg(rd, bytes)).reg(vreg(rn, bytes)));
+        }
+        (false, 0b01000) => return zero("cmgt"),
+        (true, 0b01000) => return zero("cmge"),
+        (false, 0b01001) => return zero("cmeq"),
+        (true, 0b01001) => return zero("cmle"),
+        (false, 0b01010) => return zero("cmlt"),
+        (false, 0b01011) if size != 3 || q => "abs",
+        (true, 0b01011) if size != 3 || q => "neg",
+        _ => return None,
+    };
+    Some(
+        Insn::new(name)
+            .reg(vreg(rd, arrangement))
+            .reg(vreg(rn, arrangement)),
+    )
+}
+
+fn simd_across_lanes(w: u32) -> Option<Insn> {
+    let q = bit(w, 30);
+    let u = bit(w, 29);
+    let size = bits(w, 23, 22);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    if size == 3 || (size == 2 && !q) {
+        return None;
+    }
+    let (name, widen) = match (u, bits(w, 16, 12)) {
+        (false, 0b11011) => ("addv", false),
+        (false, 0b01010) => ("smaxv", false),
+        (true, 0b01010) => ("umaxv", false),
+        (false, 0b11010) => ("sminv", false),
+        (true, 0b11010) => ("uminv", false),
+        (false, 0b00011) => ("saddlv", true),
+        (true, 0b00011) => ("uaddlv", true),
+        _ => return None,
+    };
+    let dest_size = if widen { size + 1 } else { size };
+    let dest = format!("{}{}", ['b', 'h', 's', 'd'][dest_size as usize], rd);
+    Some(
+        Insn::new(name)
+            .reg(dest)
+            .reg(vreg(rn, vector_arrangement(size, q)?)),
+    )
+}
+
+fn simd_copy(w: u32) -> Option<Insn> {
+    let q = bit(w, 30);
+    let op = bit(w, 29);
+    let imm5 = bits(w, 20, 16);
+    let imm4 = bits(w, 14, 11);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    let size = imm5.trailing_zeros();
+    if size > 3 {
+        return None;
+    }
+    let element = ["b", "h", "s", "d"][size as usize];
+    let index = imm5 >> (size + 1);
+    let lane = |n: u32, index: u32| format!("v{}.{}[{}]", n, element, index);
+    let insn = match (op, imm4) {
+        (false, 0b0000) => {
+            if size == 3 && !q {
+                return None;
+            }
+            Insn::new("dup")
+                .reg(vreg(rd, vector_arrangement(size, q)?))
+                .reg(lane(rn, index))
+        }
+        (false, 0b0001) => {
+            if size == 3 && !q {
+                return None;
+            }
+            Insn::new("dup")
+                .reg(vreg(rd, vector_arrangement(size, q)?))
+                .reg(reg(rn, size == 3))
+        }
+        (false, 0b0101) if size < 2 || (size == 2 && q) => Insn::new("smov")
+            .reg(reg(rd, q))
+            .reg(lane(rn, index))
+            .writes(rd),
+        (false, 0b0111) => {
+            let name = match (size, q) {
+                (3, true) | (2, false) => "mov",
+                (0 | 1, false) => "umov",
+                _ => return None,
+            };
+            Insn::new(name)
+                .reg(reg(rd, q))
+                .reg(lane(rn, index))
+                .writes(rd)
+        }
+        (false, 0b0011) if q => Insn::new("mov")
+            .reg(lane(rd, index))
+            .reg(reg(rn, size == 3)),
+        (true, _) if q => Insn::new("mov")
+            .reg(lane(rd, index))
+            .reg(lane(rn, imm4 >> size)),
+        _ => return None,
+    };
+    Some(insn)
+}
+
+fn simd_modified_immediate(w: u32) -> Option<Insn> {
+    let q = bit(w, 30);
+    let op = bit(w, 29);
+    let cmode = bits(w, 15, 12);
+    let rd = bits(w, 4, 0);
+    let imm8 = (bits(w, 18, 16) << 5) | bits(w, 9, 5);
+    if bit(w, 11) {
+        return None;
+    }
+    let words = if q { "4s" } else { "2s" };
+    let halves = if q { "8h" } else { "4h" };
+    let shifted = |name: &str, arrangement: &str, amount: u32| {
+        let insn = Insn::new(name)
+            .reg(vreg(rd, arrangement))
+            .imm(hex(imm8 as u64));
+        if amount > 0 {
+            insn.op(Shift("lsl", amount))
+        } else {
+            insn
+        }
+    };
+    let ones = |name: &str| {
+        Insn::new(name)
+            .reg(vreg(rd, words))
+            .imm(hex(imm8 as u64))
+            .op(Shift("msl", 8 * ((cmode & 1) + 1)))
+    };
+    let insn = match (op, cmode) {
+        (false, c) if c & 0b1001 == 0b0000 => shifted("movi", words, 8 * ((c >> 1) & 3)),
+        (false, c) if c & 0b1001 == 0b0001 => shifted("orr", words, 8 * ((c >> 1) & 3)),
+        (false, c) if c & 0b1101 == 0b1000 => shifted("movi", halves, 8 * ((c >> 1) & 1)),
+        (false, c) if c & 0b1101 == 0b1001 => shifted("orr", halves, 8 * ((c >> 1) & 1)),
+        (false, c) if c & 0b1110 == 0b1100 => ones("movi"),
+        (false, 0b1110) => Insn::new("movi")
+            .reg(vreg(rd, if q { "16b" } else { "8b" }))
+            .imm(hex(imm8 as u64)),
+        (false, _) => Insn::new("fmov")
+            .reg(vreg(rd, words))
+            .imm(fp_immediate(imm8)),
+        (true, c) if c & 0b1001 == 0b0000 => shifted("mvni", words, 8 * ((c >> 1) & 3)),
+        (true, c) if c & 0b1001 == 0b0001 => shifted("bic", words, 8 * ((c >> 1) & 3)),
+        (true, c) if c & 0b1101 == 0b1000 => shifted("mvni", halves, 8 * ((c >> 1) & 1)),
+        (true, c) if c & 0b1101 == 0b1001 => shifted("bic", halves, 8 * ((c >> 1) & 1)),
+        (true, c) if c & 0b1110 == 0b1100 => ones("mvni"),
+        (true, 0b1110) => {
+            // 每一位展开成一个字节
+            let imm64 = (0..8).fold(0u64, |acc, i| {
+                if imm8 & (1 << i) != 0 {
+                    acc | (0xff << (i * 8))
+                } else {
+                    acc
+                }
+            });
+            let dest = if q {
+                vreg(rd, "2d")
+            } else {
+                format!("d{}", rd)
+            };
+            Insn::new("movi").reg(dest).imm(hex(imm64))
+        }
+        (true, _) if q => Insn::new("fmov")
+            .reg(vreg(rd, "2d"))
+            .imm(fp_immediate(imm8)),
+        _ => return None,
+    };
+    Some(insn)
+}
+
+fn simd_shift_immediate(w: u32) -> Option<Insn> {
+    let q = bit(w, 30);
+    let u = bit(w, 29);
+    let immh = bits(w, 22, 19);
+    let immhb = bits(w, 22, 16);
+    let rd = bits(w, 4, 0);
+    let rn = bits(w, 9, 5);
+    if immh == 0 {
+        return None;
+    }
+    let size = 31 - immh.leading_zeros();
+    let esize = 8u32 << size;
+    match (u, bits(w, 15, 11)) {
+        (_, 0b00000) | (false, 0b01010) | (true, 0b01010) => {
+            if size == 3 && !q {
+                return None;
+            }
+            let arrangement = vector_arrangement(size, q)?;
+            let (name, shift) = match (u, bits(w, 15, 11)) {
+                (false, 0b00000) => ("sshr", 2 * esize - immhb),
+                (true, 0b00000) => ("ushr", 2 * esize - immhb),
+                (false, _) => ("shl", immhb - esize),
+                (true, _) => ("sli", immhb - esize),
+            };
+            Some(
+                Insn::new(name)
+                    .reg(vreg(rd, arrangement))
+                    .reg(vreg(rn, arrangement))
+                    .imm(dec(shift as i64)),
+            )
+        }
+        (_, 0b10100) if size < 3 => {
+            let shift = immhb - esize;
+            let wide = ["8h", "4s", "2d"][size as usize];
+            let narrow = vector_arrangement(size, q)?;
+            let stem = match (u, shift) {
+                (false, 0) => "sxtl",
+                (true, 0) => "uxtl",
+                (false, _) => "sshll",
+                (true, _) => "ushll",
+            };
+            let name = format!("{}{}", stem, if q { "2" } else { "" });
+            let insn = Insn::new(name).reg(vreg(rd, wide)).reg(vreg(rn, narrow));
+            Some(if shift == 0 {
+                insn
+            } else {
+                insn.imm(dec(shift as i64))
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `w` decoded at `pc`, with the padding after the mnemonic collapsed.
+    fn text(w: u32, pc: u64, symbols: &SymbolMap) -> String {
+        let mut out = Tokens::default();
+        format(&decode(w, pc), &mut out, symbols);
+        let text: String = out.vec.iter().map(|(text, _)| text.as_str()).collect();
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    fn check(cases: &[(u32, &str)]) {
+        let symbols = SymbolMap::new();
+        for &(w, expected) in cases {
+            assert_eq!(text(w, 0x1000, &symbols), expected, "0x{:08x}", w);
+        }
+    }
+
+    #[test]
+    fn data_processing() {
+        check(&[
+            (0x91004020, "add x0, x1, #0x10"),
+            (0xd10083ff, "sub sp, sp, #0x20"),
+            (0x92401cc5, "and x5, x6, #0xff"),
+            (0x8b020c20, "add x0, x1, x2, lsl #3"),
+            (0x9b031041, "madd x1, x2, x3, x4"),
+            (0x1ac20c20, "sdiv w0, w1, w2"),
+            (0xd3442c41, "ubfx x1, x2, #4, #8"),
+            (0x9a820020, "csel x0, x1, x2, eq"),
+        ]);
+    }
+
+    #[test]
+    fn aliases() {
+        check(&[
+            (0x910003fd, "mov x29, sp"),
+            (0x7100145f, "cmp w2, #0x5"),
+            (0xd2a24683, "mov x3, #0x12340000"),
+            (0x12800004, "mov w4, #0xffffffff"),
+            (0xaa0103e0, "mov x0, x1"),
+            (0xeb04007f, "cmp x3, x4"),
+            (0xcb0603e5, "neg x5, x6"),
+            (0x9b037c41, "mul x1, x2, x3"),
+            (0xd37cec41, "lsl x1, x2, #4"),
+            (0x53037c41, "lsr w1, w2, #3"),
+            (0x937ffc41, "asr x1, x2, #63"),
+            (0x93407c20, "sxtw x0, w1"),
+            (0x53001c20, "uxtb w0, w1"),
+            (0x1a9f07e0, "cset w0, ne"),
+            (0x9a81a420, "cinc x0, x1, lt"),
+            (0x4ea11c20, "mov v0.16b, v1.16b"),
+        ]);
+    }
+
+    #[test]
+    fn loads_and_stores() {
+        check(&[
+            (0xf94007e0, "ldr x0, [sp, #8]"),
+            (0xb8404c41, "ldr w1, [x2, #4]!"),
+            (0x38401483, "ldrb w3, [x4], #1"),
+            (0xf8227820, "str x0, [x1, x2, lsl #3]"),
+            (0xa9bf7bfd, "stp x29, x30, [sp, #-16]!"),
+            (0xa8c17bfd, "ldp x29, x30, [sp], #16"),
+            (0xc85f7c20, "ldxr x0, [x1]"),
+            (0xc8027c83, "stxr w2, x3, [x4]"),
+            (0x88dffc20, "ldar w0, [x1]"),
+            (0xf8200041, "ldadd x0, x1, [x2]"),
+            (0x4c407000, "ld1 {v0.16b}, [x0]"),
+        ]);
+    }
+
+    #[test]
+    fn branches_and_system() {
+        check(&[
+            (0xd65f03c0, "ret"),
+            (0xd61f0200, "br x16"),
+            (0xd63f0100, "blr x8"),
+            (0xd503201f, "nop"),
+            (0xd5033bbf, "dmb ish"),
+            (0xd53bd040, "mrs x0, tpidr_el0"),
+            (0xd4000001, "svc #0x0"),
+            (0xd4207d00, "brk #0x3e8"),
+        ]);
+    }
+
+    #[test]
+    fn floating_point_and_simd() {
+        check(&[
+            (0x1e622820, "fadd d0, d1, d2"),
+            (0x1e2e1000, "fmov s0, #1.00000000"),
+            (0x1e780020, "fcvtzs w0, d1"),
+            (0x9e620020, "scvtf d0, x1"),
+            (0x1f420c20, "fmadd d0, d1, d2, d3"),
+            (0x4ea28420, "add v0.4s, v1.4s, v2.4s"),
+            (0x4ea26c20, "smin v0.4s, v1.4s, v2.4s"),
+            (0x6e656483, "umax v3.8h, v4.8h, v5.8h"),
+            (0x6e62dc20, "fmul v0.2d, v1.2d, v2.2d"),
+            (0x4e040c20, "dup v0.4s, w1"),
+            (0x6f00e400, "movi v0.2d, #0x0"),
+            (0x4eb1b820, "addv s0, v1.4s"),
+            (0x6f7d0420, "ushr v0.2d, v1.2d, #3"),
+        ]);
+    }
+
+    #[test]
+    fn unallocated() {
+        // 64 位元素没有 smin/smax/shadd/sabd 等整数向量运算
+        check(&[
+            (0x4ee36e18, ".inst 0x4ee36e18"),
+            (0x4ee36618, ".inst 0x4ee36618"),
+            (0x6ee06400, ".inst 0x6ee06400"),
+            (0x4ee00400, ".inst 0x4ee00400"),
+            (0x4ee07400, ".inst 0x4ee07400"),
+            (0x4ee09c00, ".inst 0x4ee09c00"),
+            (0x00000000, "udf #0"),
+        ]);
+        let (step, _) = step(&decode(0x4ee36e18, 0x1000), 0x1000, &mut [None; 31]);
+        assert_eq!(step.flow, Flow::Stop);
+    }
+
+    #[test]
+    fn pc_relative_targets() {
+        let mut symbols = SymbolMap::new();
+        symbols.insert(0x1000, "func".to_string(), 0x30);
+        symbols.insert(0x1040, "next".to_string(), 0x10);
+        // 超出 next 大小的地址仍在同一节内, 按 next+偏移 显示
+        symbols.insert_section(0x1000, 0x100);
+        let cases = [
+            (0x14000010, "b next", Flow::Jump(Some(0x1040))),
+            (0x97ffffc0, "bl 0xf00", Flow::Call(Some(0xf00))),
+            (0x54000101, "b.ne func+0x20", Flow::Branch(0x1020)),
+            (0xb4ffffc0, "cbz x0, 0xff8", Flow::Branch(0xff8)),
+            (0x37180081, "tbnz w1, #3, func+0x10", Flow::Branch(0x1010)),
+            (0x10000120, "adr x0, func+0x24", Flow::Next),
+            (0x90000090, "adrp x16, 0x11000", Flow::Next),
+            (0x58000401, "ldr x1, next+0x40", Flow::Next),
+        ];
+        for (w, expected, flow) in cases {
+            assert_eq!(text(w, 0x1000, &symbols), expected, "0x{:08x}", w);
+            let (step, _) = step(&decode(w, 0x1000), 0x1000, &mut [None; 31]);
+            assert_eq!(step.flow, flow, "0x{:08x}", w);
+        }
+    }
+
+    #[test]
+    fn adrp_pairs() {
+        let mut symbols = SymbolMap::new();
+        symbols.insert(0x11010, "puts@GOT".to_string(), 8);
+        let mut disassembler = Aarch64Disassembler::new(Rc::new(symbols));
+        // adrp x16, 0x11000; ldr x17, [x16, #16]; br x17
+        let code: Vec<u8> = [0x90000090u32, 0xf9400a11, 0xd61f0220]
+            .iter()
+            .flat_map(|w| w.to_le_bytes())
+            .collect();
+        let decoded = disassembler.disassemble(&code, 0x1000);
+        let text: String = decoded[1]
+            .tokens
+            .iter()
+            .map(|(text, _)| text.as_str())
+            .collect();
+        assert_eq!(
+            text.split_whitespace().collect::<Vec<_>>().join(" "),
+            "ldr x17, [x16, #16] // puts@GOT"
+        );
+        assert_eq!(decoded[1].step.data, Some(0x11010));
+        assert_eq!(decoded[2].step.flow, Flow::Jump(None));
+    }
+
+    #[test]
+    fn loads_tell_how_their_data_is_used() {
+        let words: [u32; 10] = [
+            0x90000000, // adrp x0, 0x1000
+            0xfd400800, // ldr d0, [x0, #16]
+            0xbd401001, // ldr s1, [x0, #16]
+            0xf9400802, // ldr x2, [x0, #16]
+            0xb9401003, // ldr w3, [x0, #16]
+            0x39404004, // ldrb w4, [x0, #16]
+            0x91004005, // add x5, x0, #0x10
+            0x58000206, // ldr x6, 0x105c
+            0x5c000207, // ldr d7, 0x1060
+            0x98000208, // ldrsw x8, 0x1064
+        ];
+        let code: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        let mut disassembler = Aarch64Disassembler::new(Rc::new(SymbolMap::new()));
+        let decoded = disassembler.disassemble(&code, 0x1000);
+        let uses: Vec<(Option<u64>, DataUse)> = decoded
+            .iter()
+            .map(|insn| (insn.step.data, insn.data_use))
+            .collect();
+        assert_eq!(
+            uses[1..],
+            [
+                (Some(0x1010), DataUse::Float64),
+                (Some(0x1010), DataUse::Float32),
+                (Some(0x1010), DataUse::Integer(8)),
+                (Some(0x1010), DataUse::Integer(4)),
+                (Some(0x1010), DataUse::Integer(1)),
+                (Some(0x1010), DataUse::Address),
+                (Some(0x105c), DataUse::Integer(8)),
+                (Some(0x1060), DataUse::Float64),
+                (Some(0x1064), DataUse::Integer(4)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,404 @@
+//! Disassembler backends, chosen from the ELF header's `e_machine`.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use clap::ValueEnum;
+use elf::abi;
+use elf::endian::AnyEndian;
+use elf::ElfBytes;
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Span;
+
+use crate::demangle::demangle;
+use crate::elf::code_bitness;
+
+pub mod aarch64;
+pub mod riscv;
+pub mod x86;
+
+/// Names of code and data addresses, used to label branch targets and data references.
+#[derive(Default)]
+pub struct SymbolMap {
+    /// Address to name and size.
+    symbols: BTreeMap<u64, (String, u64)>,
+    /// Start to end address of the sections loaded in memory.
+    sections: BTreeMap<u64, u64>,
+}
+
+impl SymbolMap {
+    pub fn new() -> SymbolMap {
+        SymbolMap::default()
+    }
+
+    /// Name `address`; the `size` bytes from it resolve to `name+offset`.
+    pub fn insert(&mut self, address: u64, name: String, size: u64) {
+        self.symbols.insert(address, (name, size));
+    }
+
+    /// Like `insert`, but keeps the name the address already has.
+    pub fn insert_missing(&mut self, address: u64, name: String, size: u64) {
+        self.symbols.entry(address).or_insert((name, size));
+    }
+
+    /// Record a section, so that addresses past the end of a symbol still
+    /// resolve to it when they are in the same section.
+    pub fn insert_section(&mut self, address: u64, size: u64) {
+        self.sections.insert(address, address.saturating_add(size));
+    }
+
+    /// The symbol starting exactly at `address`.
+    pub fn get(&self, address: u64) -> Option<&str> {
+        self.symbols.get(&address).map(|(name, _)| name.as_str())
+    }
+
+    /// The nearest symbol at or before `address` and its start, so the
+    /// address can be printed as `name+offset`: the symbol must cover the
+    /// address, or else be in the same section.
+    pub fn resolve(&self, address: u64) -> Option<(&str, u64)> {
+        let (&start, (name, size)) = self.symbols.range(..=address).next_back()?;
+        let covers = start == address || address - start < *size;
+        let same_section = self
+            .section_of(start)
+            .is_some_and(|section| Some(section) == self.section_of(address));
+        (covers || same_section).then_some((name.as_str(), start))
+    }
+
+    /// Start of the section containing `address`.
+    fn section_of(&self, address: u64) -> Option<u64> {
+        self.sections
+            .range(..=address)
+            .next_back()
+            .filter(|&(_, &end)| address < end)
+            .map(|(&start, _)| start)
+    }
+
+    /// Replace the names with how `options` shows them.
+    pub fn demangle(&mut self, options: FormatOptions) {
+        for (name, _) in self.symbols.values_mut() {
+            if let Cow::Owned(demangled) = demangle(name, options) {
+                *name = demangled;
+            }
+        }
+    }
+}
+
+/// Assembly dialect of the x86 backend; the other architectures have a single syntax.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Syntax {
+    #[default]
+    Intel,
+    /// AT&T, as printed by GNU `as` and `objdump`.
+    #[value(alias = "gas")]
+    Att,
+    Nasm,
+    Masm,
+}
+
+impl Syntax {
+    pub fn name(self) -> &'static str {
+        match self {
+            Syntax::Intel => "Intel",
+            Syntax::Att => "AT&T",
+            Syntax::Nasm => "NASM",
+            Syntax::Masm => "MASM",
+        }
+    }
+
+    /// The syntax after this one, for cycling through them with a key.
+    pub fn next(self) -> Syntax {
+        match self {
+            Syntax::Intel => Syntax::Att,
+            Syntax::Att => Syntax::Nasm,
+            Syntax::Nasm => Syntax::Masm,
+            Syntax::Masm => Syntax::Intel,
+        }
+    }
+}
+
+/// How hexadecimal numbers are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum HexStyle {
+    /// What the syntax normally uses: `10h`, or `0x10` for AT&T.
+    #[default]
+    Default,
+    /// `0x10`
+    Prefix,
+    /// `10h`
+    Suffix,
+}
+
+/// When memory operands get a size keyword (`dword ptr`, `qword`, ...).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum MemorySize {
+    /// Only where the assembler needs it to tell the size apart.
+    #[default]
+    Default,
+    Always,
+    /// Only where the size can't be known from the other operands.
+    Minimal,
+    Never,
+}
+
+/// Formatting choices for disassembly listings. The syntax, hex style and
+/// memory sizes only apply to the x86 backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub syntax: Syntax,
+    pub hex_style: HexStyle,
+    pub memory_size: MemorySize,
+    /// Print the encoded bytes of each instruction.
+    pub show_bytes: bool,
+    /// Print where each instruction is in the file.
+    pub show_offset: bool,
+    /// Show symbol names as they are in the file instead of demangling them.
+    pub mangled: bool,
+    /// Leave the `::h0123...` hash out of demangled Rust names.
+    pub hide_hash: bool,
+}
+
+/// Whether `for_elf` picks a backend that honours `FormatOptions` for this machine.
+pub fn has_syntaxes(machine: u16) -> bool {
+    matches!(machine, abi::EM_386 | abi::EM_IAMCU | abi::EM_X86_64)
+}
+
+/// Kind of a piece of formatted instruction text, used to pick its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Text,
+    Directive,
+    Prefix,
+    Mnemonic,
+    Keyword,
+    Register,
+    Number,
+    Symbol,
+}
+
+/// One decoded instruction, already formatted into colored tokens.
+pub struct DecodedInstruction {
+    pub step: Step,
+    pub tokens: Vec<(String, TokenKind)>,
+    /// How the bytes at `step.data` are used.
+    pub data_use: DataUse,
+}
+
+/// What an instruction does with its data address, so the listing can
+/// decode the bytes there for a comment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataUse {
+    /// Takes the address (`lea`, `adrp` + `add`), or the size is not known.
+    Address,
+    Float32,
+    Float64,
+    /// Loads or stores an integer of this many bytes.
+    Integer(usize),
+}
+
+/// Text starting the comments the backends add after an instruction.
+pub const COMMENT: &str = "    // ";
+
+/// Where control goes after an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    /// Falls through to the next instruction.
+    Next,
+    /// Calls a function and then falls through; `None` when the callee is computed.
+    Call(Option<u64>),
+    /// Conditional branch: to the target, or to the next instruction.
+    Branch(u64),
+    /// Unconditional jump; `None` when the target is computed (switch tables, tail calls through a pointer).
+    Jump(Option<u64>),
+    /// Returns, traps or can't be decoded: nothing follows.
+    Stop,
+}
+
+impl Flow {
+    /// The known address control may go to, besides the next instruction.
+    pub fn target(self) -> Option<u64> {
+        match self {
+            Flow::Call(target) | Flow::Jump(target) => target,
+            Flow::Branch(target) => Some(target),
+            Flow::Next | Flow::Stop => None,
+        }
+    }
+
+    /// Whether the instruction ends a straight-line run of code.
+    pub fn ends_run(self) -> bool {
+        !matches!(self, Flow::Next | Flow::Call(_))
+    }
+}
+
+/// Length, control flow and data reference of one instruction, without its text.
+#[derive(Debug, Clone, Copy)]
+pub struct Step {
+    pub address: u64,
+    pub len: usize,
+    pub flow: Flow,
+    /// Address of the data the instruction reads, writes or takes the address of.
+    pub data: Option<u64>,
+}
+
+pub trait Disassembler {
+    /// Decode every instruction in `code`, whose first byte lives at `address`.
+    fn disassemble(&mut self, code: &[u8], address: u64) -> Vec<DecodedInstruction>;
+
+    /// Decode `code` without formatting it, for function discovery and cross
+    /// references. With `run_only`, stop after the first instruction that
+    /// ends the straight-line run.
+    fn steps(&mut self, code: &[u8], address: u64, run_only: bool) -> Vec<Step>;
+
+    /// What the instruction at the start of `code` reads and writes, as lines
+    /// for the detail pane. Empty when the backend can't tell.
+    fn describe(&mut self, _code: &[u8], _address: u64) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// Pick the backend matching the file's architecture; `symbols` labels the
+/// addresses it prints.
+pub fn for_elf(
+    elf: &ElfBytes<'_, AnyEndian>,
+    symbols: Rc<SymbolMap>,
+    options: FormatOptions,
+) -> Result<Box<dyn Disassembler>, String> {
+    match elf.ehdr.e_machine {
+        abi::EM_386 | abi::EM_IAMCU | abi::EM_X86_64 => Ok(Box::new(x86::X86Disassembler::new(
+            code_bitness(elf),
+            symbols,
+            options,
+        ))),
+        abi::EM_AARCH64 => Ok(Box::new(aarch64::Aarch64Disassembler::new(symbols))),
+        abi::EM_RISCV => Ok(Box::new(riscv::RiscvDisassembler::new(
+            code_bitness(elf),
+            symbols,
+        ))),
+        machine => Err(format!(
+            "Disassembly is not supported for machine type 0x{:X}",
+            machine
+        )),
+    }
+}
+
+pub fn get_color<'a>(s: String, kind: TokenKind) -> Span<'a> {
+    match kind {
+        TokenKind::Directive | TokenKind::Keyword => {
+            Span::styled(s, Style::new().yellow().italic())
+        }
+        TokenKind::Prefix | TokenKind::Mnemonic => Span::styled(s, Style::default().bold()),
+        TokenKind::Register => Span::styled(s, Style::new().green()),
+        TokenKind::Number => Span::styled(s, Style::new().cyan()),
+        TokenKind::Symbol => Span::styled(s, Style::new().magenta()),
+        TokenKind::Text => Span::styled(s, Style::default()),
+    }
+}
+
+/// Token builder shared by the backends that format their own instructions.
+#[derive(Default)]
+pub struct Tokens {
+    pub vec: Vec<(String, TokenKind)>,
+}
+
+impl Tokens {
+    pub fn push(&mut self, text: impl Into<String>, kind: TokenKind) {
+        self.vec.push((text.into(), kind));
+    }
+
+    pub fn mnemonic(&mut self, text: &str) {
+        self.push(format!("{:<7} ", text), TokenKind::Mnemonic);
+    }
+
+    pub fn text(&mut self, text: &str) {
+        self.push(text, TokenKind::Text);
+    }
+
+    pub fn register(&mut self, text: impl Into<String>) {
+        self.push(text, TokenKind::Register);
+    }
+
+    pub fn number(&mut self, text: impl Into<String>) {
+        self.push(text, TokenKind::Number);
+    }
+
+    pub fn keyword(&mut self, text: impl Into<String>) {
+        self.push(text, TokenKind::Keyword);
+    }
+
+    /// A code or data address: `name` or `name+0x..` when a symbol covers it, else the address.
+    pub fn target(&mut self, address: u64, symbols: &SymbolMap) {
+        match symbols.resolve(address) {
+            Some((name, start)) if start == address => self.push(name, TokenKind::Symbol),
+            Some((name, start)) => {
+                self.push(name, TokenKind::Symbol);
+                self.number(format!("+0x{:x}", address - start));
+            }
+            None => self.number(format!("0x{:x}", address)),
+        }
+    }
+
+    /// Trailing `// ...` annotation, e.g. the GOT slot a PLT stub loads.
+    pub fn comment(&mut self, address: u64, symbols: &SymbolMap) {
+        self.text(COMMENT);
+        self.target(address, symbols);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> SymbolMap {
+        let mut symbols = SymbolMap::new();
+        symbols.insert(0x1000, "foo".to_string(), 0x20);
+        symbols.insert(0x1100, "bar".to_string(), 0);
+        symbols
+    }
+
+    fn text(address: u64, symbols: &SymbolMap) -> String {
+        let mut tokens = Tokens::default();
+        tokens.target(address, symbols);
+        tokens.vec.into_iter().map(|(text, _)| text).collect()
+    }
+
+    #[test]
+    fn resolves_inside_symbols() {
+        let symbols = map();
+        assert_eq!(symbols.resolve(0x1000), Some(("foo", 0x1000)));
+        assert_eq!(symbols.resolve(0x101f), Some(("foo", 0x1000)));
+        // 大小为 0 的符号只命中自己的地址
+        assert_eq!(symbols.resolve(0x1100), Some(("bar", 0x1100)));
+        assert_eq!(text(0x1000, &symbols), "foo");
+        assert_eq!(text(0x1010, &symbols), "foo+0x10");
+        assert_eq!(symbols.resolve(0xfff), None);
+        assert_eq!(text(0xfff, &symbols), "0xfff");
+    }
+
+    #[test]
+    fn past_the_end_of_a_symbol() {
+        let mut symbols = map();
+        assert_eq!(symbols.resolve(0x1020), None);
+        assert_eq!(symbols.resolve(0x1101), None);
+        // 同一个节里仍算作前一个符号
+        symbols.insert_section(0x1000, 0x80);
+        symbols.insert_section(0x1080, 0x100);
+        assert_eq!(symbols.resolve(0x1020), Some(("foo", 0x1000)));
+        assert_eq!(text(0x1040, &symbols), "foo+0x40");
+        assert_eq!(symbols.resolve(0x1101), Some(("bar", 0x1100)));
+        assert_eq!(symbols.resolve(0x1080), None);
+        // 节之外
+        assert_eq!(symbols.resolve(0x1180), None);
+    }
+
+    #[test]
+    fn insert_missing_keeps_existing_names() {
+        let mut symbols = map();
+        symbols.insert_missing(0x1000, "sub_1000".to_string(), 0x10);
+        symbols.insert_missing(0x1200, "sub_1200".to_string(), 0x10);
+        assert_eq!(symbols.get(0x1000), Some("foo"));
+        assert_eq!(symbols.resolve(0x1010), Some(("foo", 0x1000)));
+        assert_eq!(symbols.get(0x1200), Some("sub_1200"));
+        symbols.insert(0x1000, "renamed".to_string(), 0x20);
+        assert_eq!(symbols.get(0x1000), Some("renamed"));
+    }
+}
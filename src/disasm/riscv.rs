@@ -0,0 +1,1371 @@
+//! RISC-V decoder for RV32GC / RV64GC (IMAFD, Zicsr, Zifencei and the C extension).
+//!
+//! Registers use their ABI names and compressed instructions are shown in their
+//! expanded form, with the usual assembler aliases (`li`, `mv`, `ret`, `j`, ...).
+
+use std::rc::Rc;
+
+use super::{DataUse, DecodedInstruction, Disassembler, Flow, Step, SymbolMap, TokenKind, Tokens};
+
+pub struct RiscvDisassembler {
+    xlen: u32,
+    symbols: Rc<SymbolMap>,
+}
+
+impl RiscvDisassembler {
+    pub fn new(xlen: u32, symbols: Rc<SymbolMap>) -> RiscvDisassembler {
+        RiscvDisassembler { xlen, symbols }
+    }
+}
+
+impl Disassembler for RiscvDisassembler {
+    fn disassemble(&mut self, code: &[u8], address: u64) -> Vec<DecodedInstruction> {
+        // auipc 写入寄存器的地址, 用于解析 auipc + addi/ld/jalr 组合
+        let mut pc_relative: [Option<u64>; 32] = [None; 32];
+        let mut buffer = vec![];
+        let mut offset = 0;
+        while offset < code.len() {
+            let pc = address + offset as u64;
+            let rest = &code[offset..];
+            let mut out = Tokens::default();
+            let (step, data_use) = match self.decode_at(rest, pc) {
+                Some((insn, len)) => {
+                    offset += len;
+                    format(&insn, &mut out, &self.symbols);
+                    let (step, resolved) = step(&insn, pc, len, &mut pc_relative);
+                    if let Some(address) = resolved {
+                        out.comment(address, &self.symbols);
+                    }
+                    (step, insn.data_use)
+                }
+                None => {
+                    offset = code.len();
+                    out.push(".byte   ", TokenKind::Directive);
+                    let bytes: Vec<String> = rest.iter().map(|b| format!("0x{:02x}", b)).collect();
+                    out.number(bytes.join(", "));
+                    let step = Step {
+                        address: pc,
+                        len: rest.len(),
+                        flow: Flow::Stop,
+                        data: None,
+                    };
+                    (step, DataUse::Address)
+                }
+            };
+            buffer.push(DecodedInstruction {
+                step,
+                tokens: out.vec,
+                data_use,
+            });
+        }
+        buffer
+    }
+
+    fn steps(&mut self, code: &[u8], address: u64, run_only: bool) -> Vec<Step> {
+        let mut pc_relative: [Option<u64>; 32] = [None; 32];
+        let mut steps = vec![];
+        let mut offset = 0;
+        while offset < code.len() {
+            let pc = address + offset as u64;
+            let Some((insn, len)) = self.decode_at(&code[offset..], pc) else {
+                break;
+            };
+            let (step, _) = step(&insn, pc, len, &mut pc_relative);
+            steps.push(step);
+            if run_only && step.flow.ends_run() {
+                break;
+            }
+            offset += len;
+        }
+        steps
+    }
+}
+
+impl RiscvDisassembler {
+    /// The instruction at the start of `code` and its length; `None` when
+    /// fewer bytes than an instruction are left.
+    fn decode_at(&self, code: &[u8], pc: u64) -> Option<(Insn, usize)> {
+        let (mut insn, len) = match code {
+            [lo, hi, ..] if lo & 3 != 3 => (
+                decode_compressed(u16::from_le_bytes([*lo, *hi]), pc, self.xlen),
+                2,
+            ),
+            [a, b, c, d, ..] => (
+                decode(u32::from_le_bytes([*a, *b, *c, *d]), pc, self.xlen),
+                4,
+            ),
+            _ => return None,
+        };
+        if self.xlen == 32 {
+            insn.wrap_addresses();
+        }
+        Some((insn, len))
+    }
+}
+
+/// The instruction's step, and the address completed by an `auipc` pair.
+fn step(
+    insn: &Insn,
+    pc: u64,
+    len: usize,
+    registers: &mut [Option<u64>; 32],
+) -> (Step, Option<u64>) {
+    // auipc + jalr 组成的远调用, 目标地址由 auipc 给出
+    let resolved = track_pc_relative(insn, registers);
+    let step = Step {
+        address: pc,
+        len,
+        flow: flow(insn, resolved),
+        data: resolved.filter(|_| !insn.branch),
+    };
+    (step, resolved)
+}
+
+fn flow(insn: &Insn, resolved: Option<u64>) -> Flow {
+    let target = insn.operands.iter().find_map(|operand| match operand {
+        Target(address) => Some(*address),
+        _ => None,
+    });
+    if !insn.branch {
+        return match insn.mnemonic.as_str() {
+            "ebreak" | "unimp" | ".2byte" | ".4byte" => Flow::Stop,
+            _ => Flow::Next,
+        };
+    }
+    match insn.mnemonic.as_str() {
+        "j" => Flow::Jump(target),
+        "jal" => Flow::Call(target),
+        "jr" => Flow::Jump(resolved),
+        "jalr" => Flow::Call(resolved),
+        "ret" => Flow::Stop,
+        _ => target.map_or(Flow::Stop, Flow::Branch),
+    }
+}
+
+const REGISTERS: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+const FP_REGISTERS: [&str; 32] = [
+    "ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7", "fs0", "fs1", "fa0", "fa1", "fa2",
+    "fa3", "fa4", "fa5", "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7", "fs8", "fs9",
+    "fs10", "fs11", "ft8", "ft9", "ft10", "ft11",
+];
+
+const RA: u32 = 1;
+const SP: u32 = 2;
+
+enum Operand {
+    Reg(&'static str),
+    Imm(String),
+    /// PC-relative address of a branch or jump.
+    Target(u64),
+    /// `offset(base)`, or `(base)` for atomics.
+    Mem(Option<i64>, &'static str),
+    /// Rounding modes, fence sets and CSR names.
+    Name(String),
+}
+
+use Operand::*;
+
+struct Insn {
+    mnemonic: String,
+    operands: Vec<Operand>,
+    /// Integer register written by the instruction.
+    writes: Option<u32>,
+    /// Control flow leaves the straight line, forget what registers hold.
+    branch: bool,
+    /// `auipc` destination and the address it computes.
+    auipc: Option<(u32, u64)>,
+    /// Base register and offset of an instruction that may complete an `auipc`.
+    pc_ref: Option<(u32, i64)>,
+    /// What a load or store does with the bytes at its address.
+    data_use: DataUse,
+}
+
+impl Insn {
+    fn new(mnemonic: impl Into<String>) -> Insn {
+        Insn {
+            mnemonic: mnemonic.into(),
+            operands: vec![],
+            writes: None,
+            branch: false,
+            auipc: None,
+            pc_ref: None,
+            data_use: DataUse::Address,
+        }
+    }
+
+    fn op(mut self, operand: Operand) -> Insn {
+        self.operands.push(operand);
+        self
+    }
+
+    fn x(self, n: u32) -> Insn {
+        self.op(Reg(REGISTERS[n as usize]))
+    }
+
+    fn f(self, n: u32) -> Insn {
+        self.op(Reg(FP_REGISTERS[n as usize]))
+    }
+
+    fn imm(self, value: i64) -> Insn {
+        self.op(Imm(value.to_string()))
+    }
+
+    fn hex(self, value: u64) -> Insn {
+        self.op(Imm(format!("0x{:x}", value)))
+    }
+
+    fn mem(self, offset: i64, base: u32) -> Insn {
+        self.op(Mem(Some(offset), REGISTERS[base as usize]))
+    }
+
+    fn writes(mut self, rd: u32) -> Insn {
+        self.writes = Some(rd);
+        self
+    }
+
+    fn branch(mut self) -> Insn {
+        self.branch = true;
+        self
+    }
+
+    fn pc_ref(mut self, base: u32, offset: i64) -> Insn {
+        self.pc_ref = Some((base, offset));
+        self
+    }
+
+    fn data_use(mut self, data_use: DataUse) -> Insn {
+        self.data_use = data_use;
+        self
+    }
+
+    /// RV32 addresses wrap around at 4 GiB.
+    fn wrap_addresses(&mut self) {
+        for operand in self.operands.iter_mut() {
+            if let Target(address) = operand {
+                *address &= 0xffff_ffff;
+            }
+        }
+        if let Some((_, value)) = self.auipc.as_mut() {
+            *value &= 0xffff_ffff;
+        }
+    }
+}
+
+fn format(insn: &Insn, out: &mut Tokens, symbols: &SymbolMap) {
+    if insn.mnemonic.starts_with('.') {
+        out.push(format!("{:<7} ", insn.mnemonic), TokenKind::Directive);
+    } else {
+        out.mnemonic(&insn.mnemonic);
+    }
+    for (i, operand) in insn.operands.iter().enumerate() {
+        if i > 0 {
+            out.text(", ");
+        }
+        match operand {
+            Reg(name) => out.register(*name),
+            Imm(text) => out.number(text.clone()),
+            Target(address) => out.target(*address, symbols),
+            Mem(offset, base) => {
+                if let Some(offset) = offset {
+                    out.number(offset.to_string());
+                }
+                out.text("(");
+                out.register(*base);
+                out.text(")");
+            }
+            Name(name) => out.keyword(name.clone()),
+        }
+    }
+}
+
+/// Follow `auipc` + `addi`/load/store/`jalr` pairs and return the address
+/// they form: PLT stubs load their GOT slot this way and far calls jump with it.
+fn track_pc_relative(insn: &Insn, registers: &mut [Option<u64>; 32]) -> Option<u64> {
+    let address = insn
+        .pc_ref
+        .and_then(|(base, offset)| registers[base as usize].map(|v| v.wrapping_add(offset as u64)));
+    if insn.branch {
+        *registers = [None; 32];
+    }
+    if let Some(rd) = insn.writes {
+        registers[rd as usize] = None;
+    }
+    if let Some((rd, value)) = insn.auipc {
+        registers[rd as usize] = Some(value);
+    }
+    address
+}
+
+fn bits(w: u32, hi: u32, lo: u32) -> u32 {
+    (w >> lo) & ((1u32 << (hi - lo + 1)) - 1)
+}
+
+fn sext(value: u32, width: u32) -> i64 {
+    let shift = 64 - width;
+    ((value as i64) << shift) >> shift
+}
+
+fn unknown32(w: u32) -> Insn {
+    Insn::new(".4byte").hex(w as u64)
+}
+
+fn unknown16(w: u16) -> Insn {
+    Insn::new(".2byte").hex(w as u64)
+}
+
+// ---------------------------------------------------------------------------
+// 32-bit encodings
+
+fn decode(w: u32, pc: u64, xlen: u32) -> Insn {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let rs2 = bits(w, 24, 20);
+    let funct3 = bits(w, 14, 12);
+    let funct7 = bits(w, 31, 25);
+    let imm_i = sext(bits(w, 31, 20), 12);
+    let rv64 = xlen == 64;
+
+    let insn = match w & 0x7f {
+        0x37 => Some(
+            Insn::new("lui")
+                .x(rd)
+                .hex(bits(w, 31, 12) as u64)
+                .writes(rd),
+        ),
+        0x17 => {
+            let value = pc.wrapping_add(sext(w & 0xffff_f000, 32) as u64);
+            let mut insn = Insn::new("auipc")
+                .x(rd)
+                .hex(bits(w, 31, 12) as u64)
+                .writes(rd);
+            if rd != 0 {
+                insn.auipc = Some((rd, value));
+            }
+            Some(insn)
+        }
+        0x6f => {
+            let offset = (bits(w, 31, 31) << 20)
+                | (bits(w, 19, 12) << 12)
+                | (bits(w, 20, 20) << 11)
+                | (bits(w, 30, 21) << 1);
+            let target = pc.wrapping_add(sext(offset, 21) as u64);
+            Some(jal(rd, target))
+        }
+        0x67 if funct3 == 0 => Some(jalr(rd, rs1, imm_i)),
+        0x63 => {
+            let offset = (bits(w, 31, 31) << 12)
+                | (bits(w, 7, 7) << 11)
+                | (bits(w, 30, 25) << 5)
+                | (bits(w, 11, 8) << 1);
+            let target = pc.wrapping_add(sext(offset, 13) as u64);
+            branch(funct3, rs1, rs2, target)
+        }
+        0x03 => {
+            let name = match funct3 {
+                0 => "lb",
+                1 => "lh",
+                2 => "lw",
+                3 if rv64 => "ld",
+                4 => "lbu",
+                5 => "lhu",
+                6 if rv64 => "lwu",
+                _ => return unknown32(w),
+            };
+            Some(
+                Insn::new(name)
+                    .x(rd)
+                    .mem(imm_i, rs1)
+                    .writes(rd)
+                    .pc_ref(rs1, imm_i)
+                    .data_use(DataUse::Integer(1 << (funct3 & 3))),
+            )
+        }
+        0x23 => {
+            let offset = sext((funct7 << 5) | rd, 12);
+            let name = match funct3 {
+                0 => "sb",
+                1 => "sh",
+                2 => "sw",
+                3 if rv64 => "sd",
+                _ => return unknown32(w),
+            };
+            Some(
+                Insn::new(name)
+                    .x(rs2)
+                    .mem(offset, rs1)
+                    .pc_ref(rs1, offset)
+                    .data_use(DataUse::Integer(1 << funct3)),
+            )
+        }
+        0x13 => op_imm(w, xlen),
+        0x1b if rv64 => op_imm_32(w),
+        0x33 => op(w),
+        0x3b if rv64 => op_32(w),
+        0x0f => misc_mem(w),
+        0x73 => system(w),
+        0x2f => atomic(w, rv64),
+        0x07 | 0x27 => {
+            let name = match (w & 0x7f, funct3) {
+                (0x07, 2) => "flw",
+                (0x07, 3) => "fld",
+                (0x27, 2) => "fsw",
+                (0x27, 3) => "fsd",
+                _ => return unknown32(w),
+            };
+            let offset = if w & 0x7f == 0x07 {
+                imm_i
+            } else {
+                sext((funct7 << 5) | rd, 12)
+            };
+            let reg = if w & 0x7f == 0x07 { rd } else { rs2 };
+            let data_use = if funct3 == 2 {
+                DataUse::Float32
+            } else {
+                DataUse::Float64
+            };
+            Some(
+                Insn::new(name)
+                    .f(reg)
+                    .mem(offset, rs1)
+                    .pc_ref(rs1, offset)
+                    .data_use(data_use),
+            )
+        }
+        0x43 | 0x47 | 0x4b | 0x4f => fused_multiply_add(w),
+        0x53 => op_fp(w, rv64),
+        _ => None,
+    };
+    insn.unwrap_or_else(|| unknown32(w))
+}
+
+fn jal(rd: u32, target: u64) -> Insn {
+    let insn = match rd {
+        0 => Insn::new("j"),
+        RA => Insn::new("jal"),
+        _ => Insn::new("jal").x(rd),
+    };
+    insn.op(Target(target)).writes(rd).branch()
+}
+
+fn jalr(rd: u32, rs1: u32, offset: i64) -> Insn {
+    let insn = match (rd, rs1, offset) {
+        (0, RA, 0) => Insn::new("ret"),
+        (0, _, 0) => Insn::new("jr").x(rs1),
+        (RA, _, 0) => Insn::new("jalr").x(rs1),
+        (0, _, _) => Insn::new("jr").mem(offset, rs1),
+        (RA, _, _) => Insn::new("jalr").mem(offset, rs1),
+        (_, _, 0) => Insn::new("jalr").x(rd).x(rs1),
+        _ => Insn::new("jalr").x(rd).mem(offset, rs1),
+    };
+    insn.writes(rd).pc_ref(rs1, offset).branch()
+}
+
+fn branch(funct3: u32, rs1: u32, rs2: u32, target: u64) -> Option<Insn> {
+    let insn = match (funct3, rs1, rs2) {
+        (0, _, 0) => Insn::new("beqz").x(rs1),
+        (1, _, 0) => Insn::new("bnez").x(rs1),
+        (4, _, 0) => Insn::new("bltz").x(rs1),
+        (5, _, 0) => Insn::new("bgez").x(rs1),
+        (4, 0, _) => Insn::new("bgtz").x(rs2),
+        (5, 0, _) => Insn::new("blez").x(rs2),
+        _ => {
+            let name = match funct3 {
+                0 => "beq",
+                1 => "bne",
+                4 => "blt",
+                5 => "bge",
+                6 => "bltu",
+                7 => "bgeu",
+                _ => return None,
+            };
+            Insn::new(name).x(rs1).x(rs2)
+        }
+    };
+    Some(insn.op(Target(target)).branch())
+}
+
+fn op_imm(w: u32, xlen: u32) -> Option<Insn> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let imm = sext(bits(w, 31, 20), 12);
+    let shamt_bits = if xlen == 64 { 6 } else { 5 };
+    let shamt = bits(w, 20 + shamt_bits - 1, 20);
+    let funct6 = bits(w, 31, 20 + shamt_bits);
+    let insn = match bits(w, 14, 12) {
+        0 => match (rd, rs1, imm) {
+            (0, 0, 0) => Insn::new("nop"),
+            (_, 0, _) => Insn::new("li").x(rd).imm(imm),
+            (_, _, 0) => Insn::new("mv").x(rd).x(rs1),
+            _ => Insn::new("addi").x(rd).x(rs1).imm(imm).pc_ref(rs1, imm),
+        },
+        1 if funct6 == 0 => Insn::new("slli").x(rd).x(rs1).imm(shamt as i64),
+        2 => Insn::new("slti").x(rd).x(rs1).imm(imm),
+        3 if imm == 1 => Insn::new("seqz").x(rd).x(rs1),
+        3 => Insn::new("sltiu").x(rd).x(rs1).imm(imm),
+        4 if imm == -1 => Insn::new("not").x(rd).x(rs1),
+        4 => Insn::new("xori").x(rd).x(rs1).imm(imm),
+        5 if funct6 == 0 => Insn::new("srli").x(rd).x(rs1).imm(shamt as i64),
+        // srai 的高位是 0100000 (RV32) 或 010000 (RV64)
+        5 if funct6 == 1 << (10 - shamt_bits) => Insn::new("srai").x(rd).x(rs1).imm(shamt as i64),
+        6 => Insn::new("ori").x(rd).x(rs1).imm(imm),
+        7 => Insn::new("andi").x(rd).x(rs1).imm(imm),
+        _ => return None,
+    };
+    Some(insn.writes(rd))
+}
+
+fn op_imm_32(w: u32) -> Option<Insn> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let imm = sext(bits(w, 31, 20), 12);
+    let shamt = bits(w, 24, 20) as i64;
+    let insn = match (bits(w, 14, 12), bits(w, 31, 25)) {
+        (0, _) if imm == 0 => Insn::new("sext.w").x(rd).x(rs1),
+        (0, _) => Insn::new("addiw").x(rd).x(rs1).imm(imm),
+        (1, 0) => Insn::new("slliw").x(rd).x(rs1).imm(shamt),
+        (5, 0) => Insn::new("srliw").x(rd).x(rs1).imm(shamt),
+        (5, 0b0100000) => Insn::new("sraiw").x(rd).x(rs1).imm(shamt),
+        _ => return None,
+    };
+    Some(insn.writes(rd))
+}
+
+fn op(w: u32) -> Option<Insn> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let rs2 = bits(w, 24, 20);
+    let insn = match (bits(w, 31, 25), bits(w, 14, 12)) {
+        (0b0100000, 0) if rs1 == 0 => Insn::new("neg").x(rd).x(rs2),
+        (0, 3) if rs1 == 0 => Insn::new("snez").x(rd).x(rs2),
+        (0, 2) if rs2 == 0 => Insn::new("sltz").x(rd).x(rs1),
+        (0, 2) if rs1 == 0 => Insn::new("sgtz").x(rd).x(rs2),
+        (funct7, funct3) => {
+            let name = match (funct7, funct3) {
+                (0, 0) => "add",
+                (0b0100000, 0) => "sub",
+                (0, 1) => "sll",
+                (0, 2) => "slt",
+                (0, 3) => "sltu",
+                (0, 4) => "xor",
+                (0, 5) => "srl",
+                (0b0100000, 5) => "sra",
+                (0, 6) => "or",
+                (0, 7) => "and",
+                (1, 0) => "mul",
+                (1, 1) => "mulh",
+                (1, 2) => "mulhsu",
+                (1, 3) => "mulhu",
+                (1, 4) => "div",
+                (1, 5) => "divu",
+                (1, 6) => "rem",
+                (1, 7) => "remu",
+                _ => return None,
+            };
+            Insn::new(name).x(rd).x(rs1).x(rs2)
+        }
+    };
+    Some(insn.writes(rd))
+}
+
+fn op_32(w: u32) -> Option<Insn> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let rs2 = bits(w, 24, 20);
+    let name = match (bits(w, 31, 25), bits(w, 14, 12)) {
+        (0b0100000, 0) if rs1 == 0 => {
+            return Some(Insn::new("negw").x(rd).x(rs2).writes(rd));
+        }
+        (0, 0) => "addw",
+        (0b0100000, 0) => "subw",
+        (0, 1) => "sllw",
+        (0, 5) => "srlw",
+        (0b0100000, 5) => "sraw",
+        (1, 0) => "mulw",
+        (1, 4) => "divw",
+        (1, 5) => "divuw",
+        (1, 6) => "remw",
+        (1, 7) => "remuw",
+        _ => return None,
+    };
+    Some(Insn::new(name).x(rd).x(rs1).x(rs2).writes(rd))
+}
+
+fn misc_mem(w: u32) -> Option<Insn> {
+    if bits(w, 11, 7) != 0 || bits(w, 19, 15) != 0 {
+        return None;
+    }
+    match bits(w, 14, 12) {
+        0 => {
+            let pred = bits(w, 27, 24);
+            let succ = bits(w, 23, 20);
+            if bits(w, 31, 28) == 0b1000 && pred == 0b0011 && succ == 0b0011 {
+                return Some(Insn::new("fence.tso"));
+            }
+            if pred == 0b1111 && succ == 0b1111 {
+                return Some(Insn::new("fence"));
+            }
+            Some(
+                Insn::new("fence")
+                    .op(Name(fence_set(pred)))
+                    .op(Name(fence_set(succ))),
+            )
+        }
+        1 if bits(w, 31, 20) == 0 => Some(Insn::new("fence.i")),
+        _ => None,
+    }
+}
+
+fn fence_set(set: u32) -> String {
+    let names = [(8, 'i'), (4, 'o'), (2, 'r'), (1, 'w')];
+    let text: String = names
+        .iter()
+        .filter(|(bit, _)| set & bit != 0)
+        .map(|(_, c)| c)
+        .collect();
+    if text.is_empty() {
+        "0".to_string()
+    } else {
+        text
+    }
+}
+
+fn system(w: u32) -> Option<Insn> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let csr = bits(w, 31, 20);
+    let funct3 = bits(w, 14, 12);
+    if funct3 == 0 {
+        let insn = match w {
+            0x0000_0073 => Insn::new("ecall"),
+            0x0010_0073 => Insn::new("ebreak"),
+            0x1020_0073 => Insn::new("sret"),
+            0x3020_0073 => Insn::new("mret"),
+            0x1050_0073 => Insn::new("wfi"),
+            _ if bits(w, 31, 25) == 0b0001001 && rd == 0 => {
+                let rs2 = bits(w, 24, 20);
+                match (rs1, rs2) {
+                    (0, 0) => Insn::new("sfence.vma"),
+                    (_, 0) => Insn::new("sfence.vma").x(rs1),
+                    _ => Insn::new("sfence.vma").x(rs1).x(rs2),
+                }
+            }
+            _ => return None,
+        };
+        return Some(insn.branch());
+    }
+    let name = csr_name(csr);
+    let immediate = funct3 >= 5;
+    let insn = match (funct3 & 3, rd, rs1) {
+        // 读取计数器和浮点状态的常用简写
+        (2, _, 0) if !immediate => match csr {
+            0x001 => Insn::new("frflags").x(rd),
+            0x002 => Insn::new("frrm").x(rd),
+            0x003 => Insn::new("frcsr").x(rd),
+            0xc00 => Insn::new("rdcycle").x(rd),
+            0xc01 => Insn::new("rdtime").x(rd),
+            0xc02 => Insn::new("rdinstret").x(rd),
+            _ => Insn::new("csrr").x(rd).op(Name(name)),
+        },
+        (1, 0, _) if !immediate => match csr {
+            0x001 => Insn::new("fsflags").x(rs1),
+            0x002 => Insn::new("fsrm").x(rs1),
+            0x003 => Insn::new("fscsr").x(rs1),
+            _ => Insn::new("csrw").op(Name(name)).x(rs1),
+        },
+        (op, 0, _) => {
+            let stem = match op {
+                1 => "csrw",
+                2 => "csrs",
+                3 => "csrc",
+                _ => return None,
+            };
+            let insn =
+                Insn::new(format!("{}{}", stem, if immediate { "i" } else { "" })).op(Name(name));
+            if immediate {
+                insn.imm(rs1 as i64)
+            } else {
+                insn.x(rs1)
+            }
+        }
+        (op, _, _) => {
+            let stem = match op {
+                1 => "csrrw",
+                2 => "csrrs",
+                3 => "csrrc",
+                _ => return None,
+            };
+            let insn = Insn::new(format!("{}{}", stem, if immediate { "i" } else { "" }))
+                .x(rd)
+                .op(Name(name));
+            if immediate {
+                insn.imm(rs1 as i64)
+            } else {
+                insn.x(rs1)
+            }
+        }
+    };
+    Some(insn.writes(rd))
+}
+
+fn csr_name(csr: u32) -> String {
+    let name = match csr {
+        0x001 => "fflags",
+        0x002 => "frm",
+        0x003 => "fcsr",
+        0xc00 => "cycle",
+        0xc01 => "time",
+        0xc02 => "instret",
+        0xc80 => "cycleh",
+        0xc81 => "timeh",
+        0xc82 => "instreth",
+        0x100 => "sstatus",
+        0x104 => "sie",
+        0x105 => "stvec",
+        0x106 => "scounteren",
+        0x140 => "sscratch",
+        0x141 => "sepc",
+        0x142 => "scause",
+        0x143 => "stval",
+        0x144 => "sip",
+        0x180 => "satp",
+        0x300 => "mstatus",
+        0x301 => "misa",
+        0x302 => "medeleg",
+        0x303 => "mideleg",
+        0x304 => "mie",
+        0x305 => "mtvec",
+        0x306 => "mcounteren",
+        0x340 => "mscratch",
+        0x341 => "mepc",
+        0x342 => "mcause",
+        0x343 => "mtval",
+        0x344 => "mip",
+        0x3a0 => "pmpcfg0",
+        0x3b0 => "pmpaddr0",
+        0xb00 => "mcycle",
+        0xb02 => "minstret",
+        0xf11 => "mvendorid",
+        0xf12 => "marchid",
+        0xf13 => "mimpid",
+        0xf14 => "mhartid",
+        _ => return format!("0x{:x}", csr),
+    };
+    name.to_string()
+}
+
+fn atomic(w: u32, rv64: bool) -> Option<Insn> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let rs2 = bits(w, 24, 20);
+    let width = match bits(w, 14, 12) {
+        2 => "w",
+        3 if rv64 => "d",
+        _ => return None,
+    };
+    let order = match bits(w, 26, 25) {
+        0 => "",
+        1 => ".rl",
+        2 => ".aq",
+        _ => ".aqrl",
+    };
+    let op = match bits(w, 31, 27) {
+        0b00010 if rs2 == 0 => "lr",
+        0b00010 => return None,
+        0b00011 => "sc",
+        0b00001 => "amoswap",
+        0b00000 => "amoadd",
+        0b00100 => "amoxor",
+        0b01100 => "amoand",
+        0b01000 => "amoor",
+        0b10000 => "amomin",
+        0b10100 => "amomax",
+        0b11000 => "amominu",
+        0b11100 => "amomaxu",
+        _ => return None,
+    };
+    let insn = Insn::new(format!("{}.{}{}", op, width, order)).x(rd);
+    let insn = if op == "lr" { insn } else { insn.x(rs2) };
+    Some(insn.op(Mem(None, REGISTERS[rs1 as usize])).writes(rd))
+}
+
+fn fp_format(fmt: u32) -> Option<&'static str> {
+    match fmt {
+        0 => Some("s"),
+        1 => Some("d"),
+        _ => None,
+    }
+}
+
+/// Static rounding mode operand; the dynamic mode (7) is left implicit.
+fn rounding(insn: Insn, rm: u32) -> Option<Insn> {
+    let name = match rm {
+        0 => "rne",
+        1 => "rtz",
+        2 => "rdn",
+        3 => "rup",
+        4 => "rmm",
+        7 => return Some(insn),
+        _ => return None,
+    };
+    Some(insn.op(Name(name.to_string())))
+}
+
+fn fused_multiply_add(w: u32) -> Option<Insn> {
+    let fmt = fp_format(bits(w, 26, 25))?;
+    let op = match w & 0x7f {
+        0x43 => "fmadd",
+        0x47 => "fmsub",
+        0x4b => "fnmsub",
+        _ => "fnmadd",
+    };
+    let insn = Insn::new(format!("{}.{}", op, fmt))
+        .f(bits(w, 11, 7))
+        .f(bits(w, 19, 15))
+        .f(bits(w, 24, 20))
+        .f(bits(w, 31, 27));
+    rounding(insn, bits(w, 14, 12))
+}
+
+fn op_fp(w: u32, rv64: bool) -> Option<Insn> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let rs2 = bits(w, 24, 20);
+    let rm = bits(w, 14, 12);
+    let funct7 = bits(w, 31, 25);
+    let fmt = fp_format(funct7 & 3)?;
+    let int_type = |n: u32| match n {
+        0 => Some("w"),
+        1 => Some("wu"),
+        2 if rv64 => Some("l"),
+        3 if rv64 => Some("lu"),
+        _ => None,
+    };
+    match funct7 >> 2 {
+        0b00000..=0b00011 => {
+            let op = ["fadd", "fsub", "fmul", "fdiv"][(funct7 >> 2) as usize];
+            rounding(Insn::new(format!("{}.{}", op, fmt)).f(rd).f(rs1).f(rs2), rm)
+        }
+        0b01011 if rs2 == 0 => rounding(Insn::new(format!("fsqrt.{}", fmt)).f(rd).f(rs1), rm),
+        0b00100 => {
+            let (op, alias) = match rm {
+                0 => ("fsgnj", "fmv"),
+                1 => ("fsgnjn", "fneg"),
+                2 => ("fsgnjx", "fabs"),
+                _ => return None,
+            };
+            Some(if rs1 == rs2 {
+                Insn::new(format!("{}.{}", alias, fmt)).f(rd).f(rs1)
+            } else {
+                Insn::new(format!("{}.{}", op, fmt)).f(rd).f(rs1).f(rs2)
+            })
+        }
+        0b00101 => {
+            let op = match rm {
+                0 => "fmin",
+                1 => "fmax",
+                _ => return None,
+            };
+            Some(Insn::new(format!("{}.{}", op, fmt)).f(rd).f(rs1).f(rs2))
+        }
+        0b01000 => {
+            let source = fp_format(rs2)?;
+            if source == fmt {
+                return None;
+            }
+            let insn = Insn::new(format!("fcvt.{}.{}", fmt, source)).f(rd).f(rs1);
+            // 单精度转双精度是精确的, 不显示舍入模式
+            if fmt == "d" {
+                Some(insn)
+            } else {
+                rounding(insn, rm)
+            }
+        }
+        0b10100 => {
+            let op = match rm {
+                0 => "fle",
+                1 => "flt",
+                2 => "feq",
+                _ => return None,
+            };
+            Some(
+                Insn::new(format!("{}.{}", op, fmt))
+                    .x(rd)
+                    .f(rs1)
+                    .f(rs2)
+                    .writes(rd),
+            )
+        }
+        0b11000 => rounding(
+            Insn::new(format!("fcvt.{}.{}", int_type(rs2)?, fmt))
+                .x(rd)
+                .f(rs1)
+                .writes(rd),
+            rm,
+        ),
+        0b11010 => {
+            let source = int_type(rs2)?;
+            let insn = Insn::new(format!("fcvt.{}.{}", fmt, source)).f(rd).x(rs1);
+            // 32 位整数转双精度同样是精确的
+            if fmt == "d" && source.starts_with('w') {
+                Some(insn)
+            } else {
+                rounding(insn, rm)
+            }
+        }
+        0b11100 if rs2 == 0 => {
+            let insn = match (rm, fmt) {
+                (0, "s") => Insn::new("fmv.x.w"),
+                (0, _) if rv64 => Insn::new("fmv.x.d"),
+                (1, _) => Insn::new(format!("fclass.{}", fmt)),
+                _ => return None,
+            };
+            Some(insn.x(rd).f(rs1).writes(rd))
+        }
+        0b11110 if rs2 == 0 && rm == 0 => {
+            let name = match fmt {
+                "s" => "fmv.w.x",
+                _ if rv64 => "fmv.d.x",
+                _ => return None,
+            };
+            Some(Insn::new(name).f(rd).x(rs1))
+        }
+        _ => None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Compressed (16-bit) encodings, shown as the instruction they expand to.
+// HINTs, which write `zero` or shift by nothing, have no effect and keep
+// their compressed name, as the assemblers print them.
+
+fn decode_compressed(h: u16, pc: u64, xlen: u32) -> Insn {
+    let w = h as u32;
+    let rv64 = xlen == 64;
+    let funct3 = bits(w, 15, 13);
+    // rd'/rs1'/rs2' 只能访问 x8-x15
+    let rd_p = bits(w, 4, 2) + 8;
+    let rs1_p = bits(w, 9, 7) + 8;
+    let rd = bits(w, 11, 7);
+    let rs2 = bits(w, 6, 2);
+    let imm6 = sext((bits(w, 12, 12) << 5) | bits(w, 6, 2), 6);
+    let shamt = ((bits(w, 12, 12) << 5) | bits(w, 6, 2)) as i64;
+
+    let insn = match (w & 3, funct3) {
+        (0, 0) => {
+            let imm = (bits(w, 12, 11) << 4)
+                | (bits(w, 10, 7) << 6)
+                | (bits(w, 6, 6) << 2)
+                | (bits(w, 5, 5) << 3);
+            if w == 0 {
+                Some(Insn::new("unimp"))
+            } else if imm == 0 {
+                None
+            } else {
+                Some(Insn::new("addi").x(rd_p).x(SP).imm(imm as i64).writes(rd_p))
+            }
+        }
+        (0, 1) | (0, 3) | (0, 5) | (0, 7) if funct3 != 3 && funct3 != 7 || rv64 => {
+            // 8 字节对齐的访问: c.fld / c.ld / c.fsd / c.sd
+            let offset = ((bits(w, 12, 10) << 3) | (bits(w, 6, 5) << 6)) as i64;
+            Some(match funct3 {
+                1 => Insn::new("fld").f(rd_p).mem(offset, rs1_p),
+                3 => Insn::new("ld").x(rd_p).mem(offset, rs1_p).writes(rd_p),
+                5 => Insn::new("fsd").f(rd_p).mem(offset, rs1_p),
+                _ => Insn::new("sd").x(rd_p).mem(offset, rs1_p),
+            })
+        }
+        (0, 2) | (0, 3) | (0, 6) | (0, 7) => {
+            let offset =
+                ((bits(w, 12, 10) << 3) | (bits(w, 6, 6) << 2) | (bits(w, 5, 5) << 6)) as i64;
+            Some(match funct3 {
+                2 => Insn::new("lw").x(rd_p).mem(offset, rs1_p).writes(rd_p),
+                3 => Insn::new("flw").f(rd_p).mem(offset, rs1_p),
+                6 => Insn::new("sw").x(rd_p).mem(offset, rs1_p),
+                _ => Insn::new("fsw").f(rd_p).mem(offset, rs1_p),
+            })
+        }
+        (1, 0) if rd == 0 && imm6 == 0 => Some(Insn::new("nop")),
+        (1, 0) if rd == 0 => Some(Insn::new("c.nop").imm(imm6)),
+        (1, 0) if imm6 == 0 => Some(Insn::new("c.addi").x(rd).imm(0)),
+        (1, 0) => Some(Insn::new("addi").x(rd).x(rd).imm(imm6).writes(rd)),
+        (1, 1) if rv64 && rd == 0 => None,
+        (1, 1) if rv64 => Some(if imm6 == 0 {
+            Insn::new("sext.w").x(rd).x(rd).writes(rd)
+        } else {
+            Insn::new("addiw").x(rd).x(rd).imm(imm6).writes(rd)
+        }),
+        (1, 1) | (1, 5) => {
+            let offset = (bits(w, 12, 12) << 11)
+                | (bits(w, 11, 11) << 4)
+                | (bits(w, 10, 9) << 8)
+                | (bits(w, 8, 8) << 10)
+                | (bits(w, 7, 7) << 6)
+                | (bits(w, 6, 6) << 7)
+                | (bits(w, 5, 3) << 1)
+                | (bits(w, 2, 2) << 5);
+            let target = pc.wrapping_add(sext(offset, 12) as u64);
+            Some(jal(if funct3 == 1 { RA } else { 0 }, target))
+        }
+        (1, 2) if rd == 0 => Some(Insn::new("c.li").x(0).imm(imm6)),
+        (1, 2) => Some(Insn::new("li").x(rd).imm(imm6).writes(rd)),
+        (1, 3) if rd == SP => {
+            let imm = (bits(w, 12, 12) << 9)
+                | (bits(w, 6, 6) << 4)
+                | (bits(w, 5, 5) << 6)
+                | (bits(w, 4, 3) << 7)
+                | (bits(w, 2, 2) << 5);
+            let imm = sext(imm, 10);
+            (imm != 0).then(|| Insn::new("addi").x(SP).x(SP).imm(imm).writes(SP))
+        }
+        (1, 3) if rd == 0 => {
+            (imm6 != 0).then(|| Insn::new("c.lui").x(0).hex((imm6 as u64) & 0xfffff))
+        }
+        (1, 3) => (imm6 != 0).then(|| {
+            Insn::new("lui")
+                .x(rd)
+                .hex((imm6 as u64) & 0xfffff)
+                .writes(rd)
+        }),
+        (1, 4) if bits(w, 11, 10) < 2 && shamt == 0 => {
+            let name = if bits(w, 10, 10) == 0 {
+                "c.srli64"
+            } else {
+                "c.srai64"
+            };
+            Some(Insn::new(name).x(rs1_p))
+        }
+        // RV32 的移位量不能超过 31
+        (1, 4) if bits(w, 11, 10) < 2 && !rv64 && shamt >= 32 => None,
+        (1, 4) => match bits(w, 11, 10) {
+            0 => Some(Insn::new("srli").x(rs1_p).x(rs1_p).imm(shamt).writes(rs1_p)),
+            1 => Some(Insn::new("srai").x(rs1_p).x(rs1_p).imm(shamt).writes(rs1_p)),
+            2 => Some(Insn::new("andi").x(rs1_p).x(rs1_p).imm(imm6).writes(rs1_p)),
+            _ => {
+                let name = match (bits(w, 12, 12), bits(w, 6, 5)) {
+                    (0, 0) => "sub",
+                    (0, 1) => "xor",
+                    (0, 2) => "or",
+                    (0, 3) => "and",
+                    (1, 0) if rv64 => "subw",
+                    (1, 1) if rv64 => "addw",
+                    _ => return unknown16(h),
+                };
+                Some(Insn::new(name).x(rs1_p).x(rs1_p).x(rd_p).writes(rs1_p))
+            }
+        },
+        (1, 6) | (1, 7) => {
+            let offset = (bits(w, 12, 12) << 8)
+                | (bits(w, 11, 10) << 3)
+                | (bits(w, 6, 5) << 6)
+                | (bits(w, 4, 3) << 1)
+                | (bits(w, 2, 2) << 5);
+            let target = pc.wrapping_add(sext(offset, 9) as u64);
+            let name = if funct3 == 6 { "beqz" } else { "bnez" };
+            Some(Insn::new(name).x(rs1_p).op(Target(target)).branch())
+        }
+        (2, 0) if !rv64 && shamt >= 32 => None,
+        (2, 0) if rd == 0 => Some(Insn::new("c.slli").x(0).imm(shamt)),
+        (2, 0) if shamt == 0 => Some(Insn::new("c.slli64").x(rd)),
+        (2, 0) => Some(Insn::new("slli").x(rd).x(rd).imm(shamt).writes(rd)),
+        (2, 1) | (2, 3) if funct3 == 1 || rv64 => {
+            let offset =
+                ((bits(w, 12, 12) << 5) | (bits(w, 6, 5) << 3) | (bits(w, 4, 2) << 6)) as i64;
+            Some(if funct3 == 1 {
+                Insn::new("fld").f(rd).mem(offset, SP)
+            } else if rd == 0 {
+                return unknown16(h);
+            } else {
+                Insn::new("ld").x(rd).mem(offset, SP).writes(rd)
+            })
+        }
+        (2, 2) | (2, 3) => {
+            let offset =
+                ((bits(w, 12, 12) << 5) | (bits(w, 6, 4) << 2) | (bits(w, 3, 2) << 6)) as i64;
+            Some(if funct3 == 3 {
+                Insn::new("flw").f(rd).mem(offset, SP)
+            } else if rd == 0 {
+                return unknown16(h);
+            } else {
+                Insn::new("lw").x(rd).mem(offset, SP).writes(rd)
+            })
+        }
+        (2, 4) => match (bits(w, 12, 12), rd, rs2) {
+            (0, 0, 0) => None,
+            (0, 0, _) => Some(Insn::new("c.mv").x(0).x(rs2)),
+            (0, _, 0) => Some(jalr(0, rd, 0)),
+            (0, _, _) => Some(Insn::new("mv").x(rd).x(rs2).writes(rd)),
+            (_, 0, 0) => Some(Insn::new("ebreak")),
+            (_, _, 0) => Some(jalr(RA, rd, 0)),
+            (_, 0, _) => Some(Insn::new("c.add").x(0).x(rs2)),
+            _ => Some(Insn::new("add").x(rd).x(rd).x(rs2).writes(rd)),
+        },
+        (2, 5) | (2, 7) if funct3 == 5 || rv64 => {
+            let offset = ((bits(w, 12, 10) << 3) | (bits(w, 9, 7) << 6)) as i64;
+            Some(if funct3 == 5 {
+                Insn::new("fsd").f(rs2).mem(offset, SP)
+            } else {
+                Insn::new("sd").x(rs2).mem(offset, SP)
+            })
+        }
+        (2, 6) | (2, 7) => {
+            let offset = ((bits(w, 12, 9) << 2) | (bits(w, 8, 7) << 6)) as i64;
+            Some(if funct3 == 6 {
+                Insn::new("sw").x(rs2).mem(offset, SP)
+            } else {
+                Insn::new("fsw").f(rs2).mem(offset, SP)
+            })
+        }
+        _ => None,
+    };
+    insn.unwrap_or_else(|| unknown16(h))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tokens joined into text, with the padding after the mnemonic collapsed.
+    fn text(tokens: &[(String, TokenKind)]) -> String {
+        let text: String = tokens.iter().map(|(text, _)| text.as_str()).collect();
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    fn check(xlen: u32, cases: &[(&[u8], &str)]) {
+        check_with(
+            &RiscvDisassembler::new(xlen, Rc::new(SymbolMap::new())),
+            cases,
+        );
+    }
+
+    fn check_with(disassembler: &RiscvDisassembler, cases: &[(&[u8], &str)]) {
+        for &(bytes, expected) in cases {
+            let (insn, len) = disassembler.decode_at(bytes, 0x1000).unwrap();
+            let mut out = Tokens::default();
+            format(&insn, &mut out, &disassembler.symbols);
+            assert_eq!(text(&out.vec), expected, "{:02x?}", bytes);
+            assert_eq!(len, bytes.len(), "{:02x?}", bytes);
+        }
+    }
+
+    #[test]
+    fn base_instructions() {
+        check(
+            64,
+            &[
+                (&[0x13, 0x05, 0x01, 0x01], "addi a0, sp, 16"),
+                (&[0xb3, 0x02, 0x73, 0x00], "add t0, t1, t2"),
+                (&[0x33, 0x05, 0xb5, 0x40], "sub a0, a0, a1"),
+                (&[0x33, 0x85, 0xc5, 0x02], "mul a0, a1, a2"),
+                (&[0x33, 0xbe, 0xee, 0x01], "sltu t3, t4, t5"),
+                (&[0x13, 0x15, 0x35, 0x00], "slli a0, a0, 3"),
+                (&[0x93, 0xd5, 0xf5, 0x43], "srai a1, a1, 63"),
+                (&[0x1b, 0x05, 0x15, 0x00], "addiw a0, a0, 1"),
+                (&[0xb7, 0x57, 0x34, 0x12], "lui a5, 0x12345"),
+                (&[0x83, 0x30, 0x81, 0x00], "ld ra, 8(sp)"),
+                (&[0x23, 0x2e, 0x94, 0xfe], "sw s1, -4(s0)"),
+                (&[0x83, 0x46, 0x07, 0x00], "lbu a3, 0(a4)"),
+                (&[0x73, 0x00, 0x00, 0x00], "ecall"),
+                (&[0x73, 0x00, 0x10, 0x00], "ebreak"),
+                (&[0x0f, 0x00, 0x30, 0x03], "fence rw, rw"),
+                (&[0x2f, 0xb5, 0x05, 0x10], "lr.d a0, (a1)"),
+                (&[0x2f, 0x25, 0xb6, 0x00], "amoadd.w a0, a1, (a2)"),
+                (&[0x53, 0xf5, 0xc5, 0x02], "fadd.d fa0, fa1, fa2"),
+                (&[0x07, 0x34, 0x01, 0x01], "fld fs0, 16(sp)"),
+                (&[0x53, 0x15, 0x05, 0xc2], "fcvt.w.d a0, fa0, rtz"),
+            ],
+        );
+    }
+
+    #[test]
+    fn aliases() {
+        check(
+            64,
+            &[
+                (&[0x93, 0x05, 0xb0, 0xff], "li a1, -5"),
+                (&[0x13, 0x04, 0x06, 0x00], "mv s0, a2"),
+                (&[0x1b, 0x85, 0x05, 0x00], "sext.w a0, a1"),
+                (&[0x67, 0x80, 0x00, 0x00], "ret"),
+                (&[0x67, 0x80, 0x07, 0x00], "jr a5"),
+                (&[0xe7, 0x00, 0x03, 0x00], "jalr t1"),
+                (&[0x73, 0x25, 0x00, 0xc0], "rdcycle a0"),
+            ],
+        );
+    }
+
+    #[test]
+    fn compressed() {
+        check(
+            64,
+            &[
+                (&[0x00, 0x08], "addi s0, sp, 16"),
+                (&[0xc8, 0x41], "lw a0, 4(a1)"),
+                (&[0x90, 0x64], "ld a2, 8(s1)"),
+                (&[0x7d, 0x55], "li a0, -1"),
+                (&[0x85, 0x65], "lui a1, 0x1"),
+                (&[0x3d, 0x71], "addi sp, sp, -32"),
+                (&[0x05, 0x05], "addi a0, a0, 1"),
+                (&[0x01, 0x25], "sext.w a0, a0"),
+                (&[0x11, 0x82], "srli a2, a2, 4"),
+                (&[0x9d, 0x8a], "andi a3, a3, 7"),
+                (&[0x05, 0x8c], "sub s0, s0, s1"),
+                (&[0x3d, 0x9f], "addw a4, a4, a5"),
+                (&[0x0a, 0x09], "slli s2, s2, 2"),
+                (&[0x2e, 0x85], "mv a0, a1"),
+                (&[0x2e, 0x95], "add a0, a0, a1"),
+                (&[0x82, 0x80], "ret"),
+                (&[0x82, 0x92], "jalr t0"),
+                (&[0x02, 0x90], "ebreak"),
+                (&[0xe2, 0x60], "ld ra, 24(sp)"),
+                (&[0x22, 0xe8], "sd s0, 16(sp)"),
+                (&[0x01, 0x00], "nop"),
+                (&[0x00, 0x00], "unimp"),
+            ],
+        );
+    }
+
+    #[test]
+    fn hints() {
+        check(
+            64,
+            &[
+                (&[0x06, 0x00], "c.slli zero, 1"),
+                (&[0x15, 0x40], "c.li zero, 5"),
+                (&[0x02, 0x05], "c.slli64 a0"),
+                (&[0x01, 0x81], "c.srli64 a0"),
+                (&[0x01, 0x85], "c.srai64 a0"),
+                (&[0x2e, 0x80], "c.mv zero, a1"),
+                (&[0x2e, 0x90], "c.add zero, a1"),
+                (&[0x05, 0x60], "c.lui zero, 0x1"),
+                (&[0x01, 0x05], "c.addi a0, 0"),
+                (&[0x0d, 0x00], "c.nop 3"),
+            ],
+        );
+        // RV32 没有 32 位以上的移位量
+        check(
+            32,
+            &[
+                (&[0x02, 0x15], ".2byte 0x1502"),
+                (&[0x01, 0x91], ".2byte 0x9101"),
+            ],
+        );
+        // HINT 不写寄存器, 不会打断 auipc 组合
+        let mut registers = [None; 32];
+        registers[10] = Some(0x2000);
+        let (insn, _) = RiscvDisassembler::new(64, Rc::new(SymbolMap::new()))
+            .decode_at(&[0x02, 0x05], 0x1000)
+            .unwrap();
+        let (step, _) = step(&insn, 0x1000, 2, &mut registers);
+        assert_eq!(step.flow, Flow::Next);
+        assert_eq!(registers[10], Some(0x2000));
+    }
+
+    #[test]
+    fn jump_and_branch_targets() {
+        let mut symbols = SymbolMap::new();
+        symbols.insert(0x1000, "func".to_string(), 0x40);
+        symbols.insert(0x1040, "next".to_string(), 0x10);
+        let disassembler = RiscvDisassembler::new(64, Rc::new(symbols));
+        check_with(
+            &disassembler,
+            &[
+                (&[0xef, 0x00, 0x00, 0x04], "jal next"),
+                (&[0x6f, 0xf0, 0x1f, 0xff], "j 0xff0"),
+                (&[0xef, 0x02, 0x80, 0x00], "jal t0, func+0x8"),
+                (&[0x63, 0x00, 0xb5, 0x02], "beq a0, a1, func+0x20"),
+                (&[0xe3, 0xfe, 0x62, 0xfe], "bgeu t0, t1, 0xffc"),
+                (&[0x21, 0xa0], "j func+0x8"),
+                (&[0x01, 0xc9], "beqz a0, func+0x10"),
+                (&[0xfd, 0xfc], "bnez s1, 0xffe"),
+            ],
+        );
+        let flows: [(&[u8], Flow); 5] = [
+            (&[0xef, 0x00, 0x00, 0x04], Flow::Call(Some(0x1040))),
+            (&[0x6f, 0xf0, 0x1f, 0xff], Flow::Jump(Some(0xff0))),
+            (&[0x63, 0x00, 0xb5, 0x02], Flow::Branch(0x1020)),
+            (&[0x21, 0xa0], Flow::Jump(Some(0x1008))),
+            (&[0x82, 0x80], Flow::Stop),
+        ];
+        for (bytes, flow) in flows {
+            let (insn, len) = disassembler.decode_at(bytes, 0x1000).unwrap();
+            let (step, _) = step(&insn, 0x1000, len, &mut [None; 32]);
+            assert_eq!(step.flow, flow, "{:02x?}", bytes);
+        }
+
+        // c.jal 只在 RV32 上有, 地址在 4 GiB 处回绕
+        let disassembler = RiscvDisassembler::new(32, Rc::new(SymbolMap::new()));
+        let (insn, _) = disassembler.decode_at(&[0xfd, 0x3f], 0).unwrap();
+        let mut out = Tokens::default();
+        format(&insn, &mut out, &disassembler.symbols);
+        assert_eq!(text(&out.vec), "jal 0xfffffffe");
+    }
+
+    #[test]
+    fn auipc_pairs() {
+        let mut symbols = SymbolMap::new();
+        symbols.insert(0x2020, "puts".to_string(), 0x10);
+        symbols.insert(0x3000, "puts@GOT".to_string(), 8);
+        symbols.insert(0x1050, "msg".to_string(), 8);
+        let mut disassembler = RiscvDisassembler::new(64, Rc::new(symbols));
+        let code = [
+            0x17, 0x13, 0x00, 0x00, // auipc t1, 0x1
+            0xe7, 0x00, 0x03, 0x02, // jalr 32(t1)
+            0x17, 0x25, 0x00, 0x00, // auipc a0, 0x2
+            0x03, 0x35, 0x85, 0xff, // ld a0, -8(a0)
+            0x97, 0x05, 0x00, 0x00, // auipc a1, 0x0
+            0x93, 0x85, 0x45, 0x04, // addi a1, a1, 68
+        ];
+        let decoded = disassembler.disassemble(&code, 0x1000);
+        let lines: Vec<String> = decoded.iter().map(|insn| text(&insn.tokens)).collect();
+        assert_eq!(
+            lines,
+            [
+                "auipc t1, 0x1",
+                "jalr 32(t1) // puts",
+                "auipc a0, 0x2",
+                "ld a0, -8(a0) // puts@GOT",
+                "auipc a1, 0x0",
+                "addi a1, a1, 68 // msg+0x4",
+            ]
+        );
+        assert_eq!(decoded[1].step.flow, Flow::Call(Some(0x2020)));
+        assert_eq!(decoded[1].step.data, None);
+        assert_eq!(decoded[3].step.data, Some(0x3000));
+        assert_eq!(decoded[5].step.data, Some(0x1054));
+    }
+
+    #[test]
+    fn loads_tell_how_their_data_is_used() {
+        let mut disassembler = RiscvDisassembler::new(64, Rc::new(SymbolMap::new()));
+        let code = [
+            0x17, 0x05, 0x00, 0x00, // auipc a0, 0x0
+            0x07, 0x25, 0x05, 0x01, // flw fa0, 16(a0)
+            0x97, 0x05, 0x00, 0x00, // auipc a1, 0x0
+            0x87, 0xb5, 0x05, 0x01, // fld fa1, 16(a1)
+            0x17, 0x06, 0x00, 0x00, // auipc a2, 0x0
+            0x23, 0x38, 0xd6, 0x00, // sd a3, 16(a2)
+            0x03, 0x26, 0x06, 0x01, // lw a2, 16(a2)
+        ];
+        let decoded = disassembler.disassemble(&code, 0x1000);
+        let uses: Vec<(Option<u64>, DataUse)> = decoded
+            .iter()
+            .map(|insn| (insn.step.data, insn.data_use))
+            .collect();
+        assert_eq!(
+            uses,
+            [
+                (None, DataUse::Address),
+                (Some(0x1010), DataUse::Float32),
+                (None, DataUse::Address),
+                (Some(0x1018), DataUse::Float64),
+                (None, DataUse::Address),
+                (Some(0x1020), DataUse::Integer(8)),
+                (Some(0x1020), DataUse::Integer(4)),
+            ]
+        );
+    }
+}
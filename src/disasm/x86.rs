@@ -0,0 +1,334 @@
+use std::rc::Rc;
+
+use iced_x86::{
+    Decoder, DecoderOptions, FlowControl, Formatter, FormatterOutput, FormatterTextKind,
+    GasFormatter, Instruction, InstructionInfoFactory, IntelFormatter, MasmFormatter,
+    MemorySize as OperandSize, MemorySizeOptions, Mnemonic, NasmFormatter, OpAccess, OpKind,
+    Register, RflagsBits, SymbolResolver, SymbolResult, UsedMemory,
+};
+
+use crate::isa::is_baseline;
+
+use super::{
+    DataUse, DecodedInstruction, Disassembler, Flow, FormatOptions, HexStyle, MemorySize, Step,
+    SymbolMap, Syntax, TokenKind,
+};
+
+pub struct X86Disassembler {
+    bitness: u32,
+    formatter: Box<dyn Formatter>,
+}
+
+impl X86Disassembler {
+    pub fn new(bitness: u32, symbols: Rc<SymbolMap>, options: FormatOptions) -> X86Disassembler {
+        let resolver: Box<dyn SymbolResolver> = Box::new(MySymbolResolver { symbols });
+        let mut formatter: Box<dyn Formatter> = match options.syntax {
+            Syntax::Intel => Box::new(IntelFormatter::with_options(Some(resolver), None)),
+            Syntax::Att => Box::new(GasFormatter::with_options(Some(resolver), None)),
+            Syntax::Nasm => Box::new(NasmFormatter::with_options(Some(resolver), None)),
+            Syntax::Masm => Box::new(MasmFormatter::with_options(Some(resolver), None)),
+        };
+        let settings = formatter.options_mut();
+        match options.hex_style {
+            HexStyle::Default => {}
+            HexStyle::Prefix => {
+                settings.set_hex_prefix("0x");
+                settings.set_hex_suffix("");
+            }
+            HexStyle::Suffix => {
+                settings.set_hex_prefix("");
+                settings.set_hex_suffix("h");
+            }
+        }
+        settings.set_memory_size_options(match options.memory_size {
+            MemorySize::Default => MemorySizeOptions::Default,
+            MemorySize::Always => MemorySizeOptions::Always,
+            MemorySize::Minimal => MemorySizeOptions::Minimal,
+            MemorySize::Never => MemorySizeOptions::Never,
+        });
+        X86Disassembler { bitness, formatter }
+    }
+}
+
+impl Disassembler for X86Disassembler {
+    fn disassemble(&mut self, code: &[u8], address: u64) -> Vec<DecodedInstruction> {
+        let mut decoder = Decoder::with_ip(self.bitness, code, address, DecoderOptions::NONE);
+        let mut instruction = Instruction::default();
+        let mut buffer = vec![];
+        while decoder.can_decode() {
+            decoder.decode_out(&mut instruction);
+
+            let mut output = MyFormatterOutput::new();
+            self.formatter.format(&instruction, &mut output);
+
+            buffer.push(DecodedInstruction {
+                step: step(&instruction),
+                tokens: output.vec,
+                data_use: data_use(&instruction),
+            });
+        }
+        buffer
+    }
+
+    fn steps(&mut self, code: &[u8], address: u64, run_only: bool) -> Vec<Step> {
+        let mut decoder = Decoder::with_ip(self.bitness, code, address, DecoderOptions::NONE);
+        let mut instruction = Instruction::default();
+        let mut steps = vec![];
+        while decoder.can_decode() {
+            decoder.decode_out(&mut instruction);
+            let step = step(&instruction);
+            steps.push(step);
+            if run_only && step.flow.ends_run() {
+                break;
+            }
+        }
+        steps
+    }
+
+    fn describe(&mut self, code: &[u8], address: u64) -> Vec<String> {
+        let mut decoder = Decoder::with_ip(self.bitness, code, address, DecoderOptions::NONE);
+        let instruction = decoder.decode();
+        if instruction.is_invalid() {
+            return vec![];
+        }
+        let mut factory = InstructionInfoFactory::new();
+        let info = factory.info(&instruction);
+        let registers = |accesses: &[OpAccess]| {
+            let names: Vec<String> = info
+                .used_registers()
+                .iter()
+                .filter(|used| accesses.contains(&used.access()))
+                .map(|used| register_name(used.register()))
+                .collect();
+            or_dash(names.join(" "))
+        };
+        let read = registers(&[
+            OpAccess::Read,
+            OpAccess::CondRead,
+            OpAccess::ReadWrite,
+            OpAccess::ReadCondWrite,
+        ]);
+        let written = registers(&[
+            OpAccess::Write,
+            OpAccess::CondWrite,
+            OpAccess::ReadWrite,
+            OpAccess::ReadCondWrite,
+        ]);
+        let memory: Vec<String> = info.used_memory().iter().map(memory_access).collect();
+        let features: Vec<String> = instruction
+            .cpuid_features()
+            .iter()
+            .filter(|&&feature| !is_baseline(feature))
+            .map(|feature| format!("{:?}", feature))
+            .collect();
+        vec![
+            format!(
+                "reads: {}  writes: {}  flags read: {}  flags written: {}",
+                read,
+                written,
+                flag_names(instruction.rflags_read()),
+                flag_names(instruction.rflags_modified())
+            ),
+            format!(
+                "memory: {}  cpuid: {}",
+                or_dash(memory.join(", ")),
+                or_dash(features.join(" "))
+            ),
+        ]
+    }
+}
+
+fn or_dash(text: String) -> String {
+    if text.is_empty() {
+        String::from("-")
+    } else {
+        text
+    }
+}
+
+fn register_name(register: Register) -> String {
+    format!("{:?}", register).to_lowercase()
+}
+
+fn flag_names(flags: u32) -> String {
+    const NAMES: [(u32, &str); 10] = [
+        (RflagsBits::OF, "OF"),
+        (RflagsBits::SF, "SF"),
+        (RflagsBits::ZF, "ZF"),
+        (RflagsBits::AF, "AF"),
+        (RflagsBits::CF, "CF"),
+        (RflagsBits::PF, "PF"),
+        (RflagsBits::DF, "DF"),
+        (RflagsBits::IF, "IF"),
+        (RflagsBits::AC, "AC"),
+        (RflagsBits::UIF, "UIF"),
+    ];
+    let names: Vec<&str> = NAMES
+        .iter()
+        .filter(|(bit, _)| flags & bit != 0)
+        .map(|&(_, name)| name)
+        .collect();
+    or_dash(names.join(" "))
+}
+
+/// `read 8 bytes at [rsp+0x8]`
+fn memory_access(memory: &UsedMemory) -> String {
+    let access = match memory.access() {
+        OpAccess::Read => "read",
+        OpAccess::CondRead => "may read",
+        OpAccess::Write => "write",
+        OpAccess::CondWrite => "may write",
+        OpAccess::ReadWrite => "read/write",
+        OpAccess::ReadCondWrite => "read/may write",
+        _ => "address of",
+    };
+    // RIP 相对寻址的位移已经是绝对地址
+    let mut parts = vec![];
+    if !matches!(
+        memory.base(),
+        Register::None | Register::RIP | Register::EIP
+    ) {
+        parts.push(register_name(memory.base()));
+    }
+    if memory.index() != Register::None {
+        parts.push(match memory.scale() {
+            1 => register_name(memory.index()),
+            scale => format!("{}*{}", register_name(memory.index()), scale),
+        });
+    }
+    let displacement = memory.displacement() as i64;
+    let mut address = parts.join("+");
+    if parts.is_empty() {
+        address = format!("0x{:x}", memory.displacement());
+    } else if displacement < 0 {
+        address.push_str(&format!("-0x{:x}", displacement.unsigned_abs()));
+    } else if displacement > 0 {
+        address.push_str(&format!("+0x{:x}", displacement));
+    }
+    match memory.memory_size().size() {
+        0 => format!("{} [{}]", access, address),
+        size => format!("{} {} bytes at [{}]", access, size, address),
+    }
+}
+
+fn step(instruction: &Instruction) -> Step {
+    Step {
+        address: instruction.ip(),
+        len: instruction.len(),
+        flow: flow(instruction),
+        data: data_reference(instruction),
+    }
+}
+
+/// Whether the memory operand's displacement is an address: RIP-relative,
+/// absolute or indexing a table, but not an offset from a base register or
+/// into the thread block (`fs:[28h]`).
+fn is_address_operand(instruction: &Instruction) -> bool {
+    matches!(
+        instruction.memory_base(),
+        Register::None | Register::RIP | Register::EIP
+    ) && !matches!(instruction.memory_segment(), Register::FS | Register::GS)
+}
+
+fn data_reference(instruction: &Instruction) -> Option<u64> {
+    let has_memory = (0..instruction.op_count()).any(|i| instruction.op_kind(i) == OpKind::Memory);
+    (has_memory && is_address_operand(instruction)).then(|| instruction.memory_displacement64())
+}
+
+fn data_use(instruction: &Instruction) -> DataUse {
+    if instruction.mnemonic() == Mnemonic::Lea {
+        return DataUse::Address;
+    }
+    match instruction.memory_size() {
+        OperandSize::Float32 => DataUse::Float32,
+        OperandSize::Float64 => DataUse::Float64,
+        size if !size.is_packed() && matches!(size.size(), 1 | 2 | 4 | 8) => {
+            DataUse::Integer(size.size())
+        }
+        _ => DataUse::Address,
+    }
+}
+
+fn flow(instruction: &Instruction) -> Flow {
+    // 远调用/远跳转的目标是段:偏移, 不是本文件里的地址
+    let near_target = || {
+        matches!(
+            instruction.op0_kind(),
+            OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64
+        )
+        .then(|| instruction.near_branch_target())
+    };
+    match instruction.flow_control() {
+        FlowControl::Next | FlowControl::XbeginXabortXend => Flow::Next,
+        FlowControl::Call => Flow::Call(near_target()),
+        FlowControl::IndirectCall => Flow::Call(None),
+        FlowControl::UnconditionalBranch => Flow::Jump(near_target()),
+        FlowControl::IndirectBranch => Flow::Jump(None),
+        FlowControl::ConditionalBranch => Flow::Branch(instruction.near_branch_target()),
+        // int3 填充在函数之间, int 0x80 之类的系统调用会返回
+        FlowControl::Interrupt if instruction.mnemonic() != Mnemonic::Int3 => Flow::Next,
+        _ => Flow::Stop,
+    }
+}
+
+struct MySymbolResolver {
+    symbols: Rc<SymbolMap>,
+}
+
+impl SymbolResolver for MySymbolResolver {
+    fn symbol(
+        &mut self,
+        instruction: &Instruction,
+        _operand: u32,
+        instruction_operand: Option<u32>,
+        address: u64,
+        _address_size: u32,
+    ) -> Option<SymbolResult<'_>> {
+        let operand = instruction.op_kind(instruction_operand?);
+        let resolved = match operand {
+            OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64 => {
+                self.symbols.resolve(address)
+            }
+            // 带基址寄存器时位移只是结构体或栈帧里的偏移, 不是地址
+            OpKind::Memory if is_address_operand(instruction) => self.symbols.resolve(address),
+            // 立即数通常只是数字
+            _ => None,
+        };
+        // 格式化器会在符号名后面加上偏移
+        resolved.map(|(name, start)| SymbolResult::with_str(start, name))
+    }
+}
+
+// Custom formatter output that stores the output in a vector.
+struct MyFormatterOutput {
+    vec: Vec<(String, TokenKind)>,
+}
+
+impl MyFormatterOutput {
+    pub fn new() -> Self {
+        Self { vec: Vec::new() }
+    }
+}
+
+impl FormatterOutput for MyFormatterOutput {
+    fn write(&mut self, text: &str, kind: FormatterTextKind) {
+        // This allocates a string. If that's a problem, just call print!() here
+        // instead of storing the result in a vector.
+        self.vec.push((String::from(text), token_kind(kind)));
+    }
+}
+
+fn token_kind(kind: FormatterTextKind) -> TokenKind {
+    match kind {
+        FormatterTextKind::Directive => TokenKind::Directive,
+        FormatterTextKind::Keyword => TokenKind::Keyword,
+        FormatterTextKind::Prefix => TokenKind::Prefix,
+        FormatterTextKind::Mnemonic => TokenKind::Mnemonic,
+        FormatterTextKind::Register => TokenKind::Register,
+        FormatterTextKind::Number => TokenKind::Number,
+        FormatterTextKind::Function | FormatterTextKind::Label | FormatterTextKind::Data => {
+            TokenKind::Symbol
+        }
+        _ => TokenKind::Text,
+    }
+}
@@ -0,0 +1,532 @@
+//! Recursive-descent function discovery, for binaries whose `.symtab` has been stripped.
+//!
+//! Decoding starts from the entry point, the exported functions, the
+//! `.init_array`/`.fini_array` entries and the `.eh_frame` FDEs, and follows
+//! branches inside each function and calls into new ones.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+
+use elf::abi;
+use elf::endian::{AnyEndian, EndianParse};
+use elf::file::Class;
+use elf::ElfBytes;
+
+use crate::disasm::{self, Flow, FormatOptions, SymbolMap};
+use crate::elf::relative_relocations;
+use crate::error::{Diagnostics, ElfError};
+use crate::utils::map_file;
+
+/// A function found by following control flow.
+pub struct Function {
+    pub address: u64,
+    /// Up to the end of the last instruction reached before the next function.
+    pub size: u64,
+    pub shndx: u16,
+}
+
+/// What the background search found, for the pages that list functions.
+#[derive(Default)]
+pub struct Discovery {
+    /// Empty when the file still has its `.symtab`.
+    pub functions: Vec<Function>,
+    /// Problems hit while reading the file, to record with the others.
+    pub problems: Vec<ElfError>,
+}
+
+/// Look for the functions of a stripped file in a background thread, like
+/// the hash. The symbol list and the instruction set scan share the result.
+pub fn spawn_discovery(path: &Path) -> Arc<OnceLock<Discovery>> {
+    let result = Arc::new(OnceLock::new());
+    let cell = result.clone();
+    let path = path.to_path_buf();
+    thread::spawn(move || {
+        let diagnostics = Diagnostics::new();
+        let functions = map_file(&path)
+            .ok()
+            .and_then(|mapping| {
+                let elf = crate::elf::parse(&mapping).ok()?;
+                // 有 .symtab 时不需要找
+                if matches!(elf.symbol_table(), Ok(Some(_))) {
+                    return None;
+                }
+                Some(discover_functions(&elf, &diagnostics))
+            })
+            .unwrap_or_default();
+        let problems = diagnostics.entries().clone();
+        let _ = cell.set(Discovery {
+            functions,
+            problems,
+        });
+    });
+    result
+}
+
+/// An executable section, the only place where functions are looked for.
+struct Region<'a> {
+    shndx: u16,
+    address: u64,
+    data: &'a [u8],
+}
+
+impl Region<'_> {
+    fn end(&self) -> u64 {
+        self.address.saturating_add(self.data.len() as u64)
+    }
+
+    fn contains(&self, address: u64) -> bool {
+        address >= self.address && address < self.end()
+    }
+}
+
+/// Find the functions of a linked file, sorted by address.
+pub fn discover_functions(
+    elf: &ElfBytes<'_, AnyEndian>,
+    diagnostics: &Diagnostics,
+) -> Vec<Function> {
+    // 可重定位文件的地址都从 0 开始, 调用目标要等重定位之后才知道
+    if elf.ehdr.e_type == abi::ET_REL {
+        return vec![];
+    }
+    let Ok(mut disassembler) =
+        disasm::for_elf(elf, Rc::new(SymbolMap::new()), FormatOptions::default())
+    else {
+        return vec![];
+    };
+    let regions = code_regions(elf);
+    let region_of = |address: u64| regions.iter().find(|region| region.contains(address));
+
+    // 可执行段的开头总是代码, 例如 .init 和 .fini 里的 _init/_fini
+    let mut queue: Vec<u64> = regions.iter().map(|region| region.address).collect();
+    queue.push(elf.ehdr.e_entry);
+    queue.extend(exported_functions(elf));
+    queue.extend(init_functions(elf));
+    queue.extend(eh_frame_functions(elf, diagnostics));
+    // 倒序, 按上面加入的顺序弹出
+    queue.reverse();
+
+    let mut starts = BTreeSet::new();
+    // 已经解码过的指令: 起始地址 -> 结束地址
+    let mut reached: BTreeMap<u64, u64> = BTreeMap::new();
+    while let Some(start) = queue.pop() {
+        if region_of(start).is_none() || !starts.insert(start) {
+            continue;
+        }
+        let mut blocks = vec![start];
+        while let Some(block) = blocks.pop() {
+            let Some(region) = region_of(block).filter(|_| !reached.contains_key(&block)) else {
+                continue;
+            };
+            let code = &region.data[(block - region.address) as usize..];
+            for step in disassembler.steps(code, block, true) {
+                let next = step.address.saturating_add(step.len as u64);
+                reached.insert(step.address, next);
+                match step.flow {
+                    Flow::Call(Some(target)) => queue.push(target),
+                    Flow::Branch(target) => blocks.extend([next, target]),
+                    // 跳到已知函数入口或者入口之前的是尾调用, 不属于当前函数
+                    Flow::Jump(Some(target)) if target < start || starts.contains(&target) => {
+                        queue.push(target)
+                    }
+                    Flow::Jump(Some(target)) => blocks.push(target),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let starts: Vec<u64> = starts.into_iter().collect();
+    starts
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &address)| {
+            let region = region_of(address)?;
+            let limit = starts
+                .get(i + 1)
+                .map_or(region.end(), |&next| next.min(region.end()));
+            // 函数之间的填充没有被执行到, 不计入函数
+            let end = reached
+                .range(address..limit)
+                .map(|(_, &end)| end.min(limit))
+                .max()
+                .unwrap_or(address);
+            Some(Function {
+                address,
+                size: end - address,
+                shndx: region.shndx,
+            })
+        })
+        .collect()
+}
+
+/// Executable sections with contents, minus the PLT which has its own tab.
+fn code_regions<'a>(elf: &ElfBytes<'a, AnyEndian>) -> Vec<Region<'a>> {
+    let Ok((Some(shdrs), strtab)) = elf.section_headers_with_strtab() else {
+        return vec![];
+    };
+    shdrs
+        .iter()
+        .enumerate()
+        .filter(|(_, shdr)| {
+            let name = strtab.and_then(|strtab| strtab.get(shdr.sh_name as usize).ok());
+            shdr.sh_flags & abi::SHF_EXECINSTR as u64 != 0
+                && shdr.sh_type != abi::SHT_NOBITS
+                && !name.is_some_and(|name| name.starts_with(".plt"))
+        })
+        .filter_map(|(i, shdr)| match elf.section_data(&shdr) {
+            Ok((data, None)) => Some(Region {
+                shndx: i as u16,
+                address: shdr.sh_addr,
+                data,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Functions exported through `.dynsym`, which stripping keeps.
+fn exported_functions(elf: &ElfBytes<'_, AnyEndian>) -> Vec<u64> {
+    let Ok(Some((dynsym, _))) = elf.dynamic_symbol_table() else {
+        return vec![];
+    };
+    dynsym
+        .iter()
+        .filter(|sym| {
+            !sym.is_undefined() && matches!(sym.st_symtype(), abi::STT_FUNC | abi::STT_GNU_IFUNC)
+        })
+        .map(|sym| sym.st_value)
+        .collect()
+}
+
+/// Constructors and destructors listed in `.preinit_array`, `.init_array` and `.fini_array`.
+fn init_functions(elf: &ElfBytes<'_, AnyEndian>) -> Vec<u64> {
+    let Some(shdrs) = elf.section_headers() else {
+        return vec![];
+    };
+    let word = match elf.ehdr.class {
+        Class::ELF32 => 4,
+        Class::ELF64 => 8,
+    };
+    let relative = relative_relocations(elf);
+    let mut functions = vec![];
+    for shdr in shdrs.iter().filter(|shdr| {
+        matches!(
+            shdr.sh_type,
+            abi::SHT_INIT_ARRAY | abi::SHT_FINI_ARRAY | abi::SHT_PREINIT_ARRAY
+        )
+    }) {
+        let Ok((data, None)) = elf.section_data(&shdr) else {
+            continue;
+        };
+        for (i, chunk) in data.chunks_exact(word).enumerate() {
+            let slot = shdr.sh_addr + (i * word) as u64;
+            // 位置无关的文件里, 数组的内容可能要等动态重定位填写
+            let value = match relative.get(&slot) {
+                Some(&addend) => Some(addend),
+                None if word == 4 => elf
+                    .ehdr
+                    .endianness
+                    .parse_u32_at(&mut 0, chunk)
+                    .ok()
+                    .map(u64::from),
+                None => elf.ehdr.endianness.parse_u64_at(&mut 0, chunk).ok(),
+            };
+            functions.extend(value);
+        }
+    }
+    functions
+}
+
+/// Start addresses of the `.eh_frame` FDEs: every function compiled with
+/// unwind tables has one, including the static ones.
+fn eh_frame_functions(elf: &ElfBytes<'_, AnyEndian>, diagnostics: &Diagnostics) -> Vec<u64> {
+    let Ok(Some(shdr)) = elf.section_header_by_name(".eh_frame") else {
+        return vec![];
+    };
+    let Ok((data, None)) = elf.section_data(&shdr) else {
+        return vec![];
+    };
+    let mut frames = EhFrame {
+        data,
+        address: shdr.sh_addr,
+        endian: elf.ehdr.endianness,
+        word: match elf.ehdr.class {
+            Class::ELF32 => 4,
+            Class::ELF64 => 8,
+        },
+        encodings: HashMap::new(),
+        functions: vec![],
+    };
+    if let Err(offset) = frames.parse() {
+        diagnostics.report(ElfError::BadTable {
+            table: ".eh_frame",
+            offset: shdr.sh_offset + offset as u64,
+            reason: format!("malformed CIE/FDE record at section offset 0x{:x}", offset),
+        });
+    }
+    frames.functions
+}
+
+// DW_EH_PE_* 指针编码
+const DW_EH_PE_OMIT: u8 = 0xff;
+const DW_EH_PE_PCREL: u8 = 0x10;
+
+/// Just enough of an `.eh_frame` parser to read the FDEs' initial locations.
+struct EhFrame<'a> {
+    data: &'a [u8],
+    address: u64,
+    endian: AnyEndian,
+    word: usize,
+    /// Pointer encoding of each CIE's FDEs, by the CIE's offset.
+    encodings: HashMap<usize, u8>,
+    functions: Vec<u64>,
+}
+
+impl EhFrame<'_> {
+    /// Walk the records; on error, the offset of the record that could not be read.
+    fn parse(&mut self) -> Result<(), usize> {
+        let mut offset = 0;
+        while offset + 4 <= self.data.len() {
+            let next = self.record(offset).ok_or(offset)?;
+            match next {
+                Some(next) => offset = next,
+                // 长度为 0 的记录是结束标记
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the CIE or FDE at `start`, returning where the next record begins.
+    fn record(&mut self, start: usize) -> Option<Option<usize>> {
+        let mut pos = start;
+        let (length, id_size) = match self.endian.parse_u32_at(&mut pos, self.data).ok()? {
+            0 => return Some(None),
+            0xffff_ffff => (self.endian.parse_u64_at(&mut pos, self.data).ok()?, 8),
+            length => (length as u64, 4),
+        };
+        let end = pos.checked_add(usize::try_from(length).ok()?)?;
+        if end > self.data.len() {
+            return None;
+        }
+        let id_pos = pos;
+        let record = &self.data[..end];
+        let id = match id_size {
+            4 => self.endian.parse_u32_at(&mut pos, record).ok()? as u64,
+            _ => self.endian.parse_u64_at(&mut pos, record).ok()?,
+        };
+        if id == 0 {
+            let encoding = self.cie(pos, end)?;
+            self.encodings.insert(start, encoding);
+        } else {
+            // FDE 中的 CIE 指针是相对于该字段自身的偏移
+            let cie = id_pos.checked_sub(usize::try_from(id).ok()?)?;
+            let encoding = match self.encodings.get(&cie) {
+                Some(&encoding) => encoding,
+                None => return Some(Some(end)),
+            };
+            if let Some(address) = self.pointer(&mut pos, end, encoding) {
+                self.functions.push(address);
+            }
+        }
+        Some(Some(end))
+    }
+
+    /// The FDE pointer encoding given by the CIE's `R` augmentation.
+    fn cie(&self, mut pos: usize, end: usize) -> Option<u8> {
+        let version = *self.data.get(pos)?;
+        pos += 1;
+        let augmentation_end = pos + self.data.get(pos..end)?.iter().position(|&b| b == 0)?;
+        let augmentation = &self.data[pos..augmentation_end];
+        pos = augmentation_end + 1;
+        if augmentation.starts_with(b"eh") {
+            pos += self.word;
+        }
+        self.uleb128(&mut pos, end)?; // code alignment
+        self.sleb128(&mut pos, end)?; // data alignment
+        match version {
+            1 => pos += 1,
+            _ => {
+                self.uleb128(&mut pos, end)?;
+            }
+        }
+        let Some(rest) = augmentation.strip_prefix(b"z") else {
+            return Some(0);
+        };
+        self.uleb128(&mut pos, end)?;
+        for &c in rest {
+            match c {
+                b'R' => return self.data.get(..end)?.get(pos).copied(),
+                b'L' => pos += 1,
+                b'P' => {
+                    let encoding = *self.data.get(pos)?;
+                    pos += 1;
+                    self.pointer(&mut pos, end, encoding & !DW_EH_PE_PCREL);
+                }
+                b'S' | b'B' | b'G' => {}
+                _ => return Some(0),
+            }
+        }
+        Some(0)
+    }
+
+    /// A `DW_EH_PE_*` encoded pointer; `None` for omitted or unsupported encodings.
+    fn pointer(&self, pos: &mut usize, end: usize, encoding: u8) -> Option<u64> {
+        if encoding == DW_EH_PE_OMIT {
+            return None;
+        }
+        let field = self.address.wrapping_add(*pos as u64);
+        let data = self.data.get(..end)?;
+        let value = match encoding & 0x0f {
+            0x00 if self.word == 4 => self.endian.parse_u32_at(pos, data).ok()? as u64,
+            0x00 => self.endian.parse_u64_at(pos, data).ok()?,
+            0x01 => self.uleb128(pos, end)?,
+            0x02 => self.endian.parse_u16_at(pos, data).ok()? as u64,
+            0x03 => self.endian.parse_u32_at(pos, data).ok()? as u64,
+            0x04 | 0x0c => self.endian.parse_u64_at(pos, data).ok()?,
+            0x09 => self.sleb128(pos, end)? as u64,
+            0x0a => self.endian.parse_u16_at(pos, data).ok()? as i16 as u64,
+            0x0b => self.endian.parse_i32_at(pos, data).ok()? as u64,
+            _ => return None,
+        };
+        match encoding & 0x70 {
+            0 => Some(value),
+            DW_EH_PE_PCREL => Some(field.wrapping_add(value)),
+            // datarel/textrel/funcrel 在 .eh_frame 中很少见
+            _ => None,
+        }
+    }
+
+    fn sleb128(&self, pos: &mut usize, end: usize) -> Option<i64> {
+        let start = *pos;
+        let value = self.uleb128(pos, end)?;
+        let bits = 7 * (*pos - start) as u32;
+        // 按最后一个字节的符号位扩展
+        if bits < 64 && value & (1 << (bits - 1)) != 0 {
+            Some((value | (u64::MAX << bits)) as i64)
+        } else {
+            Some(value as i64)
+        }
+    }
+
+    fn uleb128(&self, pos: &mut usize, end: usize) -> Option<u64> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = *self.data.get(..end)?.get(*pos)?;
+            *pos += 1;
+            if shift < 64 {
+                value |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use elf::endian::AnyEndian;
+
+    fn frames(data: &[u8], word: usize) -> EhFrame<'_> {
+        EhFrame {
+            data,
+            address: 0x2000,
+            endian: AnyEndian::Little,
+            word,
+            encodings: HashMap::new(),
+            functions: vec![],
+        }
+    }
+
+    /// A record: its length, then `body`.
+    fn record(body: &[u8]) -> Vec<u8> {
+        let mut record = (body.len() as u32).to_le_bytes().to_vec();
+        record.extend(body);
+        record
+    }
+
+    /// A version 1 CIE whose FDEs use `encoding`.
+    fn cie(encoding: u8) -> Vec<u8> {
+        // 代码对齐 1, 数据对齐 -8, 返回地址寄存器 16, 增强数据 1 字节
+        record(&[0, 0, 0, 0, 1, b'z', b'R', 0, 1, 0x78, 16, 1, encoding])
+    }
+
+    /// An FDE placed at `at`, for the CIE at `cie`, starting with `location`.
+    fn fde(at: usize, cie: usize, location: &[u8]) -> Vec<u8> {
+        let mut body = ((at + 4 - cie) as u32).to_le_bytes().to_vec();
+        body.extend(location);
+        body.extend([0x10, 0, 0, 0, 0]);
+        record(&body)
+    }
+
+    #[test]
+    fn reads_fde_locations() {
+        // pcrel | sdata4: 相对于字段自身的地址, 即 0x2000 + 17 + 8
+        let mut data = cie(0x1b);
+        data.extend(fde(17, 0, &(0x1000i32 - 0x2019).to_le_bytes()));
+        // 第二个 CIE 用绝对的 udata8
+        let second = data.len();
+        data.extend(cie(0x04));
+        data.extend(fde(data.len(), second, &0x1234_5678u64.to_le_bytes()));
+        data.extend([0; 4]);
+
+        let mut frames = frames(&data, 8);
+        assert_eq!(frames.parse(), Ok(()));
+        assert_eq!(frames.functions, [0x1000, 0x1234_5678]);
+    }
+
+    #[test]
+    fn pointer_encodings() {
+        let data = [0xf8, 0xff, 0xff, 0xff, 0x10, 0x20, 0x30, 0x40];
+        let frames = frames(&data, 4);
+        let read = |pos: usize, encoding: u8| frames.pointer(&mut { pos }, data.len(), encoding);
+        // sdata4
+        assert_eq!(read(0, 0x0b), Some(-8i64 as u64));
+        assert_eq!(read(0, 0x03), Some(0xffff_fff8));
+        // pcrel | sdata4, 字段在 0x2000
+        assert_eq!(read(0, 0x1b), Some(0x1ff8));
+        assert_eq!(read(4, 0x1b), Some(0x4030_2010 + 0x2004));
+        // 32 位文件的 absptr 是 4 字节
+        assert_eq!(read(4, 0x00), Some(0x4030_2010));
+        assert_eq!(read(4, 0x02), Some(0x2010));
+        assert_eq!(read(0, DW_EH_PE_OMIT), None);
+        // datarel 不支持
+        assert_eq!(read(0, 0x3b), None);
+        // 越过记录末尾
+        assert_eq!(read(6, 0x03), None);
+    }
+
+    #[test]
+    fn rejects_truncated_records() {
+        let mut data = cie(0x1b);
+        data.extend(fde(17, 0, &0i32.to_le_bytes()));
+        // 长度超过了节的末尾
+        let mut truncated = data.clone();
+        truncated.truncate(data.len() - 3);
+        let mut parsed = frames(&truncated, 8);
+        assert_eq!(parsed.parse(), Err(17));
+        assert!(parsed.functions.is_empty());
+
+        // 记录太短, 放不下 CIE 指针
+        let mut short = cie(0x1b);
+        short.extend(record(&[0x15, 0]));
+        assert_eq!(frames(&short, 8).parse(), Err(17));
+
+        // CIE 的增强字符串没有结尾
+        let unterminated = record(&[0, 0, 0, 0, 1, b'z', b'R', b'x']);
+        assert_eq!(frames(&unterminated, 8).parse(), Err(0));
+
+        // 每个前缀都只会出错, 不会 panic
+        for len in 0..data.len() {
+            let _ = frames(&data[..len], 8).parse();
+        }
+    }
+}
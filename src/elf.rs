@@ -1,192 +1,1381 @@
 use std::collections::HashMap;
-use std::mem;
-use std::path::Path;
-
-use elf::endian::AnyEndian;
-use elf::segment::ProgramHeader;
-use elf::ElfBytes;
-use iced_x86::FormatterOutput;
-use iced_x86::FormatterTextKind;
-use iced_x86::SymbolResolver;
-use iced_x86::SymbolResult;
-use iced_x86::{Decoder, DecoderOptions, Formatter, Instruction, NasmFormatter};
-use ratatui::style::Style;
-use ratatui::style::Stylize;
+use std::ops::Range;
+use std::rc::Rc;
+
+use elf::abi;
+use elf::endian::{AnyEndian, EndianParse};
+use elf::parse::ParseAt;
+use elf::relocation::{Rel, Rela};
+use elf::section::SectionHeader;
+use elf::string_table::StringTable;
+use elf::symbol::{Symbol, SymbolTable};
+use elf::{ElfBytes, ParseError};
+use ratatui::crossterm::event::KeyCode;
+use ratatui::style::{Style, Stylize};
 use ratatui::text::Line;
 use ratatui::text::Span;
 
-pub fn parse(file_data: &Vec<u8>) -> ElfBytes<'_, AnyEndian> {
-    ElfBytes::<AnyEndian>::minimal_parse(file_data).expect("Open elf file")
+use crate::disasm::{
+    self, get_color, has_syntaxes, DataUse, DecodedInstruction, FormatOptions, Step, SymbolMap,
+    Syntax, COMMENT,
+};
+use crate::error::{Diagnostics, ElfError, TableLocation};
+use crate::hex::{hex_line, BYTES_PER_LINE};
+
+// i386 relocation types, the elf crate only defines the x86-64 ones
+const R_386_GLOB_DAT: u32 = 6;
+const R_386_JUMP_SLOT: u32 = 7;
+const R_386_IRELATIVE: u32 = 42;
+
+// RISC-V relocation types, likewise missing from the elf crate
+const R_RISCV_64: u32 = 2;
+const R_RISCV_JUMP_SLOT: u32 = 5;
+const R_RISCV_IRELATIVE: u32 = 58;
+
+// 保留的段索引 (SHN_ABS, SHN_COMMON, SHN_XINDEX ...) 的起点
+pub const SHN_LORESERVE: u16 = 0xff00;
+
+/// Placeholder shown wherever a name could not be read from a string table.
+pub const INVALID_NAME: &str = "<invalid>";
+
+pub fn parse(file_data: &[u8]) -> Result<ElfBytes<'_, AnyEndian>, ElfError> {
+    ElfBytes::<AnyEndian>::minimal_parse(file_data).map_err(|e| ElfError::BadHeader {
+        reason: e.to_string(),
+    })
 }
 
-pub fn decompile_symbol<'a>(
+/// Location of the first section of the given type, e.g. `SHT_SYMTAB`.
+pub fn table_location(elf: &ElfBytes<'_, AnyEndian>, sh_type: u32) -> TableLocation {
+    elf.section_headers()
+        .and_then(|shdrs| shdrs.iter().find(|shdr| shdr.sh_type == sh_type))
+        .map(|shdr| TableLocation::of(&shdr))
+        .unwrap_or_default()
+}
+
+/// Location of the section header table itself.
+pub fn section_table_location(elf: &ElfBytes<'_, AnyEndian>) -> TableLocation {
+    TableLocation {
+        offset: elf.ehdr.e_shoff,
+        entsize: elf.ehdr.e_shentsize as u64,
+    }
+}
+
+/// Record every section whose contents cannot be read from the file.
+pub fn check_sections(elf: &ElfBytes<'_, AnyEndian>, diagnostics: &Diagnostics) {
+    let Ok((Some(shdrs), strtab)) = elf.section_headers_with_strtab() else {
+        return;
+    };
+    for shdr in shdrs.iter() {
+        if elf.section_data(&shdr).is_err() {
+            let name = strtab
+                .and_then(|strtab| strtab.get(shdr.sh_name as usize).ok())
+                .unwrap_or(INVALID_NAME);
+            diagnostics.report(ElfError::TruncatedSection {
+                name: name.to_string(),
+                offset: shdr.sh_offset,
+                size: shdr.sh_size,
+            });
+        }
+    }
+}
+
+/// The section a symbol lives in, with its name.
+///
+/// `st_shndx` decides when it is a real section index; absolute symbols and
+/// symbols whose index overflowed into `SHT_SYMTAB_SHNDX` are looked up by
+/// address among the allocated sections instead.
+pub fn symbol_section(
+    elf: &ElfBytes<'_, AnyEndian>,
+    shndx: u16,
+    address: u64,
+) -> Option<(SectionHeader, String)> {
+    let (Some(shdrs), strtab) = elf.section_headers_with_strtab().ok()? else {
+        return None;
+    };
+    let shdr = if shndx != abi::SHN_UNDEF && shndx < SHN_LORESERVE {
+        shdrs.get(shndx as usize).ok()?
+    } else if elf.ehdr.e_type != abi::ET_REL {
+        // 可重定位文件的段地址都是 0, 无法按地址查找
+        shdrs.iter().find(|s| {
+            s.sh_flags & (abi::SHF_ALLOC as u64) != 0
+                && s.sh_flags & (abi::SHF_TLS as u64) == 0
+                && address >= s.sh_addr
+                && address - s.sh_addr < s.sh_size
+        })?
+    } else {
+        return None;
+    };
+    let name = strtab
+        .and_then(|strtab| strtab.get(shdr.sh_name as usize).ok())
+        .unwrap_or(INVALID_NAME);
+    Some((shdr, name.to_string()))
+}
+
+/// Address of a thread-local symbol, whose `st_value` is an offset into the
+/// TLS template in linked files.
+pub fn tls_symbol_address(elf: &ElfBytes<'_, AnyEndian>, value: u64) -> u64 {
+    if elf.ehdr.e_type == abi::ET_REL {
+        return value;
+    }
+    elf.segments()
+        .and_then(|segments| segments.iter().find(|ph| ph.p_type == abi::PT_TLS))
+        .map_or(value, |tls| tls.p_vaddr.wrapping_add(value))
+}
+
+/// The bytes of a symbol inside its section, or the lines explaining why they can't be read.
+fn symbol_bytes<'a>(
     elf: &ElfBytes<'a, AnyEndian>,
+    shdr: &SectionHeader,
+    section_name: &str,
     symbol_address: u64,
     symbol_size: usize,
-    section_name: &str,
-) -> Vec<Line<'a>> {
+    diagnostics: &Diagnostics,
+) -> Result<&'a [u8], Vec<Line<'a>>> {
     // 读取内存片段
-    let shdr = elf
-        .section_header_by_name(section_name)
-        .expect("Section not found");
-    let (section, _header) = elf
-        .section_data(&shdr.unwrap())
-        .expect("Section data not found");
-
-    if symbol_address < shdr.unwrap().sh_addr {
-        return vec![Line::from(format!(
+    let section = match elf.section_data(shdr) {
+        Ok((section, _)) => section,
+        Err(_) => {
+            diagnostics.report(ElfError::TruncatedSection {
+                name: section_name.to_string(),
+                offset: shdr.sh_offset,
+                size: shdr.sh_size,
+            });
+            return Err(vec![Line::from(format!(
+                "Section data not found: {}",
+                section_name
+            ))]);
+        }
+    };
+
+    let Some(code_offset) = symbol_address
+        .checked_sub(shdr.sh_addr)
+        .map(|o| o as usize)
+        .filter(|&o| o < section.len())
+    else {
+        return Err(vec![Line::from(format!(
             "Symbol out of range: {:08X}",
             symbol_address
-        ))];
-    }
-
-    let code_offset = (symbol_address - shdr.unwrap().sh_addr) as usize;
+        ))]);
+    };
 
-    if (code_offset + symbol_size) > shdr.unwrap().sh_size as usize {
-        return vec![Line::from(format!(
+    // 符号起始于本段内, 但结尾超出了段的范围
+    let Some(code_end) = code_offset
+        .checked_add(symbol_size)
+        .filter(|&end| end <= section.len())
+    else {
+        diagnostics.report(ElfError::SymbolOutOfRange {
+            what: format!(
+                "symbol {:08X} ({} bytes) extends past the end of {}",
+                symbol_address, symbol_size, section_name
+            ),
+            offset: shdr.sh_offset + code_offset as u64,
+        });
+        return Err(vec![Line::from(format!(
             "Symbol out of range: {:08X}",
             symbol_address
-        ))];
+        ))]);
+    };
+
+    Ok(&section[code_offset..code_end])
+}
+
+/// Data symbols longer than this many words aren't shown as pointer tables.
+const MAX_POINTERS: usize = 64;
+
+/// Instructions longer than this many bytes overflow the bytes column.
+const MAX_BYTES_COLUMN: usize = 8;
+
+/// Change the listing `options` for a key of the pages that show disassembly:
+/// `s` cycles the syntax (when the backend has several), `b` and `o` toggle
+/// the bytes and file offset columns, `d` switches between demangled and
+/// mangled names. Returns whether the key was used.
+pub fn listing_key(options: &mut FormatOptions, key: KeyCode, has_syntaxes: bool) -> bool {
+    match key {
+        KeyCode::Char('s') if has_syntaxes => options.syntax = options.syntax.next(),
+        KeyCode::Char('b') => options.show_bytes = !options.show_bytes,
+        KeyCode::Char('o') => options.show_offset = !options.show_offset,
+        KeyCode::Char('d') => options.mangled = !options.mangled,
+        _ => return false,
+    }
+    true
+}
+
+/// Bytes of code decoded at a time, as the listing is scrolled.
+const DECODE_CHUNK: usize = 4096;
+
+/// Longest instruction of any backend: x86 allows 15 bytes.
+const MAX_INSTRUCTION_LEN: usize = 15;
+
+/// Instructions decoded before the first line shown, so that the `adrp`
+/// and `auipc` pairs starting above it still resolve.
+const WARM_UP: usize = 16;
+
+/// Lines of the Assembly pane. Code is decoded as far as it is scrolled
+/// and only the lines on screen are formatted.
+pub struct Listing<'a> {
+    /// Messages and data dumps, shown as they are.
+    text: Vec<Line<'a>>,
+    code: Option<Code<'a>>,
+}
+
+/// What a line of code shows; a whole section may have millions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Blank,
+    /// The name of the known symbol at the address.
+    Label(u64),
+    Instruction(u64),
+}
+
+struct Code<'a> {
+    bytes: &'a [u8],
+    address: u64,
+    /// File offset of the first byte, for the offsets column.
+    offset: u64,
+    labels: bool,
+    options: FormatOptions,
+    lookup: Lookup<'a>,
+    disassembler: Box<dyn disasm::Disassembler>,
+    has_syntaxes: bool,
+    address_width: usize,
+    bytes_width: usize,
+    rows: Vec<Row>,
+    /// Bytes decoded into `rows` so far; each decoding resumes from here.
+    decoded: usize,
+    /// The lines formatted last, reused while the same lines are shown.
+    window: Option<(Range<usize>, Vec<Line<'a>>)>,
+}
+
+impl<'a> From<Vec<Line<'a>>> for Listing<'a> {
+    fn from(lines: Vec<Line<'a>>) -> Listing<'a> {
+        Listing {
+            text: lines,
+            code: None,
+        }
+    }
+}
+
+impl<'a> Listing<'a> {
+    /// Lines decoded so far.
+    pub fn len(&self) -> usize {
+        match &self.code {
+            Some(code) => code.rows.len(),
+            None => self.text.len(),
+        }
     }
 
-    let code: &[u8] = &section[code_offset..code_offset + symbol_size];
-    let mut decoder = Decoder::with_ip(64, code, symbol_address, DecoderOptions::NONE);
-    let resolver = MySymbolResolver::create_box(elf);
-    let mut formatter: iced_x86::IntelFormatter =
-        iced_x86::IntelFormatter::with_options(Some(resolver), None);
+    /// Lines the whole listing will have, extrapolated from what has been
+    /// decoded so far, for the scroll bar.
+    pub fn estimated_len(&self) -> usize {
+        match &self.code {
+            Some(code) if code.decoded > 0 && code.decoded < code.bytes.len() => {
+                (code.rows.len() as u64 * code.bytes.len() as u64 / code.decoded as u64) as usize
+            }
+            _ => self.len(),
+        }
+    }
 
-    let mut instruction = Instruction::default();
-    let mut buffer: Vec<Line<'a>> = vec![];
-    while decoder.can_decode() {
-        decoder.decode_out(&mut instruction);
+    /// Decode until line `line` exists or the code ends.
+    pub fn decode_to(&mut self, line: usize) {
+        if let Some(code) = &mut self.code {
+            while code.rows.len() <= line && code.decode_chunk() {}
+        }
+    }
 
-        let mut output = MyFormatterOutput::new();
-        formatter.format(&instruction, &mut output);
+    /// The line of the instruction containing `address`.
+    pub fn line_of(&mut self, address: u64) -> Option<usize> {
+        let code = self.code.as_mut()?;
+        let offset = address.checked_sub(code.address)? as usize;
+        while code.decoded <= offset && code.decode_chunk() {}
+        code.rows
+            .iter()
+            .rposition(|row| matches!(row, Row::Instruction(start) if *start <= address))
+    }
 
+    /// The lines in `range`, formatted for the screen.
+    pub fn lines(&mut self, range: Range<usize>) -> Vec<Line<'a>> {
+        self.decode_to(range.end.saturating_sub(1));
+        let Some(code) = &mut self.code else {
+            let end = range.end.min(self.text.len());
+            return self.text[range.start.min(end)..end].to_vec();
+        };
+        let range = range.start.min(code.rows.len())..range.end.min(code.rows.len());
+        if let Some((shown, lines)) = &code.window {
+            if *shown == range {
+                return lines.clone();
+            }
+        }
+        let lines = code.format(range.clone());
+        code.window = Some((range, lines.clone()));
+        lines
+    }
+
+    /// Every line, for the listings that are shown whole.
+    pub fn into_lines(mut self) -> Vec<Line<'a>> {
+        self.decode_to(usize::MAX);
+        self.lines(0..self.len())
+    }
+
+    /// The instruction on line `line`; `None` for labels, blank lines and
+    /// messages.
+    pub fn step(&mut self, line: usize) -> Option<Step> {
+        self.decode_to(line);
+        let code = self.code.as_mut()?;
+        let Row::Instruction(address) = *code.rows.get(line)? else {
+            return None;
+        };
+        let from = code.warm_up_start(line);
+        let end = code.end_of(line);
+        let code_bytes = code.slice(from, end);
+        code.disassembler
+            .steps(code_bytes, from, false)
+            .into_iter()
+            .find(|step| step.address == address)
+    }
+
+    /// The instruction of every line, decoding the whole listing.
+    pub fn steps(&mut self) -> Vec<Option<Step>> {
+        self.decode_to(usize::MAX);
+        let Some(code) = &mut self.code else {
+            return vec![None; self.text.len()];
+        };
+        let mut steps = code
+            .disassembler
+            .steps(code.bytes, code.address, false)
+            .into_iter()
+            .peekable();
+        code.rows
+            .iter()
+            .map(|row| match *row {
+                Row::Instruction(address) => {
+                    while steps.next_if(|step| step.address < address).is_some() {}
+                    steps.next_if(|step| step.address == address)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The code from `address` to the end of the listing.
+    pub fn bytes_at(&self, address: u64) -> Option<&'a [u8]> {
+        let code = self.code.as_ref()?;
+        let offset = usize::try_from(address.checked_sub(code.address)?).ok()?;
+        code.bytes.get(offset..)
+    }
+}
+
+impl<'a> Code<'a> {
+    /// Index the next chunk of instructions. Returns whether there was any.
+    fn decode_chunk(&mut self) -> bool {
+        let start = self.decoded;
+        if start >= self.bytes.len() {
+            return false;
+        }
+        // 多读一条指令的长度, 跨过块尾的指令也能完整解码
+        let limit = start.saturating_add(DECODE_CHUNK);
+        let end = limit
+            .saturating_add(MAX_INSTRUCTION_LEN)
+            .min(self.bytes.len());
+        let address = self.address + start as u64;
+        let steps = self
+            .disassembler
+            .steps(&self.bytes[start..end], address, false);
+        for step in steps {
+            let offset = (step.address - self.address) as usize;
+            if offset >= limit && end < self.bytes.len() {
+                break;
+            }
+            self.push_row(step.address);
+            self.decoded = offset + step.len.max(1);
+        }
+        // 末尾不足一条指令的字节单独一行, 显示为 .byte
+        if self.decoded == start || (end == self.bytes.len() && self.decoded < end) {
+            self.push_row(self.address + self.decoded as u64);
+            self.decoded = end;
+        }
+        true
+    }
+
+    fn push_row(&mut self, address: u64) {
+        if self.labels && self.lookup.symbols.get(address).is_some() {
+            if !self.rows.is_empty() {
+                self.rows.push(Row::Blank);
+            }
+            self.rows.push(Row::Label(address));
+        }
+        self.rows.push(Row::Instruction(address));
+    }
+
+    /// Address of the instruction `WARM_UP` instructions above line `line`.
+    fn warm_up_start(&self, line: usize) -> u64 {
+        self.rows[..=line]
+            .iter()
+            .rev()
+            .filter_map(|row| match row {
+                Row::Instruction(address) => Some(*address),
+                _ => None,
+            })
+            .take(WARM_UP + 1)
+            .last()
+            .unwrap_or(self.address)
+    }
+
+    /// Address after the instruction on line `line`.
+    fn end_of(&self, line: usize) -> u64 {
+        self.rows[line + 1..]
+            .iter()
+            .find_map(|row| match row {
+                Row::Instruction(address) => Some(*address),
+                _ => None,
+            })
+            .unwrap_or(self.address + self.decoded as u64)
+    }
+
+    fn slice(&self, from: u64, to: u64) -> &'a [u8] {
+        let start = (from - self.address) as usize;
+        let end = (to - self.address) as usize;
+        &self.bytes[start..end]
+    }
+
+    fn format(&mut self, range: Range<usize>) -> Vec<Line<'a>> {
+        let Some(last) = range
+            .clone()
+            .rev()
+            .find(|&line| matches!(self.rows[line], Row::Instruction(_)))
+        else {
+            return range.map(|line| self.label(self.rows[line])).collect();
+        };
+        let from = self.warm_up_start(range.start.min(last));
+        let to = self.end_of(last);
+        let mut decoded = self
+            .disassembler
+            .disassemble(self.slice(from, to), from)
+            .into_iter()
+            .peekable();
+        let mut lines = vec![];
+        for line in range {
+            let row = self.rows[line];
+            let Row::Instruction(address) = row else {
+                lines.push(self.label(row));
+                continue;
+            };
+            while decoded
+                .next_if(|insn| insn.step.address < address)
+                .is_some()
+            {}
+            lines.push(match decoded.next_if(|insn| insn.step.address == address) {
+                Some(instruction) => self.instruction_line(instruction),
+                None => Line::from(""),
+            });
+        }
+        lines
+    }
+
+    fn label(&self, row: Row) -> Line<'a> {
+        let Row::Label(address) = row else {
+            return Line::from("");
+        };
+        let name = self.lookup.symbols.get(address).unwrap_or_default();
+        Line::from(Span::styled(
+            format!(
+                "{:0width$X} <{}>:",
+                address,
+                name,
+                width = self.address_width
+            ),
+            Style::new().magenta().bold(),
+        ))
+    }
+
+    fn instruction_line(&self, instruction: DecodedInstruction) -> Line<'a> {
+        let address = instruction.step.address;
+        let options = self.options;
         let mut line_buf = vec![];
-        line_buf.push(Span::from(format!("    {:016X}    ", instruction.ip())));
+        line_buf.push(Span::from(format!(
+            "    {:0width$X}",
+            address,
+            width = self.address_width
+        )));
+        if options.show_offset {
+            let offset = self.offset + (address - self.address);
+            line_buf.push(Span::styled(
+                format!("  {:08X}", offset),
+                Style::new().dark_gray(),
+            ));
+        }
+        if options.show_bytes {
+            let start = (address - self.address) as usize;
+            let bytes = self
+                .bytes
+                .get(start..start + instruction.step.len)
+                .unwrap_or_default()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(" ");
+            line_buf.push(Span::styled(
+                format!("  {:<w$}", bytes, w = self.bytes_width),
+                Style::new().dark_gray(),
+            ));
+        }
+        line_buf.push(Span::from("    "));
 
-        for (text, kind) in output.vec {
+        let commented = instruction.tokens.iter().any(|(text, _)| text == COMMENT);
+        for (text, kind) in instruction.tokens {
             line_buf.push(get_color(text, kind));
         }
+        let (symbols, data) = (&self.lookup.symbols, &self.lookup.data);
+        let comment = instruction
+            .step
+            .data
+            .and_then(|target| data.comment(symbols, target, instruction.data_use));
+        if let Some(comment) = comment {
+            // 已有注释 (adrp 配对的目标) 时接在后面
+            let prefix = match (commented, self.has_syntaxes) {
+                (true, _) => " ",
+                (false, true) if options.syntax == Syntax::Att => "    # ",
+                (false, true) => "    ; ",
+                (false, false) => COMMENT,
+            };
+            line_buf.push(Span::styled(
+                format!("{}{}", prefix, comment),
+                Style::new().dark_gray(),
+            ));
+        }
+        Line::from(line_buf)
+    }
+}
+
+/// Prepare the disassembly of `symbol_size` bytes at `symbol_address`. With
+/// `labels`, the names of known symbols are printed before their first
+/// instruction, which is how whole sections are swept when there are no
+/// function symbols.
+#[allow(clippy::too_many_arguments)]
+pub fn decompile_symbol<'a>(
+    elf: &ElfBytes<'a, AnyEndian>,
+    symbol_address: u64,
+    symbol_size: usize,
+    shdr: &SectionHeader,
+    section_name: &str,
+    labels: bool,
+    options: FormatOptions,
+    lookup: &Lookup<'a>,
+    diagnostics: &Diagnostics,
+) -> Listing<'a> {
+    let code = match symbol_bytes(
+        elf,
+        shdr,
+        section_name,
+        symbol_address,
+        symbol_size,
+        diagnostics,
+    ) {
+        Ok(code) => code,
+        Err(lines) => return lines.into(),
+    };
+    let disassembler = match disasm::for_elf(elf, lookup.symbols.clone(), options) {
+        Ok(disassembler) => disassembler,
+        Err(message) => return vec![Line::from(message)].into(),
+    };
+    // 指令在显示时才解码, 字节列的宽度要预先定下: 定长的指令集为 4 字节,
+    // x86 不让个别很长的指令把整列撑开
+    let bytes_width = match elf.ehdr.e_machine {
+        abi::EM_AARCH64 | abi::EM_RISCV => 4,
+        _ => MAX_BYTES_COLUMN,
+    } * 3;
+    Listing {
+        text: vec![],
+        code: Some(Code {
+            bytes: code,
+            address: symbol_address,
+            offset: shdr.sh_offset + (symbol_address - shdr.sh_addr),
+            labels,
+            options,
+            lookup: lookup.clone(),
+            disassembler,
+            has_syntaxes: has_syntaxes(elf.ehdr.e_machine),
+            address_width: code_bitness(elf) as usize / 4,
+            bytes_width,
+            rows: vec![],
+            decoded: 0,
+            window: None,
+        }),
+    }
+}
+
+/// What the listings of a page look addresses up in, built once and shared
+/// by every symbol the page shows.
+#[derive(Clone)]
+pub struct Lookup<'a> {
+    /// The names as the page's options show them.
+    pub symbols: Rc<SymbolMap>,
+    pub data: Rc<DataSections<'a>>,
+}
+
+impl<'a> Lookup<'a> {
+    pub fn new(
+        elf: &ElfBytes<'a, AnyEndian>,
+        options: FormatOptions,
+        diagnostics: &Diagnostics,
+    ) -> Lookup<'a> {
+        Lookup {
+            symbols: Rc::new(listing_symbols(elf, options, diagnostics)),
+            data: Rc::new(DataSections::new(elf)),
+        }
+    }
 
-        buffer.push(Line::from(line_buf));
+    /// Name the symbols again after the options switched between mangled
+    /// and demangled names; the data sections stay.
+    pub fn rename(
+        &mut self,
+        elf: &ElfBytes<'a, AnyEndian>,
+        options: FormatOptions,
+        diagnostics: &Diagnostics,
+    ) {
+        self.symbols = Rc::new(listing_symbols(elf, options, diagnostics));
     }
-    buffer
 }
 
-struct MySymbolResolver {
-    addr_to_symbol: HashMap<u64, String>,
+fn listing_symbols(
+    elf: &ElfBytes<'_, AnyEndian>,
+    options: FormatOptions,
+    diagnostics: &Diagnostics,
+) -> SymbolMap {
+    let mut symbols = collect_symbols(elf, diagnostics);
+    symbols.demangle(options);
+    symbols
+}
+
+/// The allocated data sections, whose bytes instructions may load:
+/// `.rodata`, `.data`, `.got`, ...
+pub struct DataSections<'a> {
+    /// Address and contents of each section.
+    sections: Vec<(u64, &'a [u8])>,
+    /// Pointers that the dynamic loader fills in, for position-independent files.
+    relative: HashMap<u64, u64>,
+    endian: AnyEndian,
+    word_size: usize,
 }
 
-impl MySymbolResolver {
-    pub fn new(elf: &ElfBytes<'_, AnyEndian>) -> MySymbolResolver {
-        let mut addr_to_symbol = HashMap::new();
-        // 解析符号表
-        let sym_table = elf.symbol_table().expect("symtab should parse");
-        match sym_table {
-            Some((symbols, strtab)) => {
-                for symbol in symbols.iter() {
-                    if let Ok(name) = strtab.get(symbol.st_name as usize) {
-                        addr_to_symbol.insert(symbol.st_value, name.to_string());
+impl<'a> DataSections<'a> {
+    fn new(elf: &ElfBytes<'a, AnyEndian>) -> DataSections<'a> {
+        let sections = match elf.section_headers() {
+            Some(shdrs) => shdrs
+                .iter()
+                .filter(|shdr| {
+                    shdr.sh_flags & abi::SHF_ALLOC as u64 != 0
+                        && shdr.sh_flags & abi::SHF_EXECINSTR as u64 == 0
+                        && shdr.sh_type != abi::SHT_NOBITS
+                })
+                .filter_map(|shdr| match elf.section_data(&shdr) {
+                    Ok((data, None)) => Some((shdr.sh_addr, data)),
+                    _ => None,
+                })
+                .collect(),
+            None => vec![],
+        };
+        DataSections {
+            sections,
+            relative: relative_relocations(elf),
+            endian: elf.ehdr.endianness,
+            word_size: match elf.ehdr.class {
+                elf::file::Class::ELF32 => 4,
+                elf::file::Class::ELF64 => 8,
+            },
+        }
+    }
+
+    /// The bytes from `address` to the end of its section.
+    fn bytes_at(&self, address: u64) -> Option<&'a [u8]> {
+        self.sections.iter().find_map(|&(start, data)| {
+            let offset = usize::try_from(address.checked_sub(start)?).ok()?;
+            data.get(offset..).filter(|rest| !rest.is_empty())
+        })
+    }
+
+    /// Comment for the data an instruction refers to: the C string at the
+    /// address it takes, the float it loads, or what a loaded pointer points at.
+    fn comment(&self, symbols: &SymbolMap, address: u64, data_use: DataUse) -> Option<String> {
+        let bytes = self.bytes_at(address)?;
+        match data_use {
+            DataUse::Float32 => {
+                let value = f32::from_bits(self.endian.parse_u32_at(&mut 0, bytes).ok()?);
+                Some(format!("{:?}", value))
+            }
+            DataUse::Float64 => {
+                let value = f64::from_bits(self.endian.parse_u64_at(&mut 0, bytes).ok()?);
+                Some(format!("{:?}", value))
+            }
+            // 普通整数不注释, 只有指针才标出它指向的符号或字符串
+            DataUse::Integer(size) if size == self.word_size => {
+                Some(format!("-> {}", self.pointer(symbols, address)?))
+            }
+            DataUse::Integer(_) => None,
+            DataUse::Address => c_string(bytes),
+        }
+    }
+
+    /// The symbol or string that the pointer stored at `address` points at.
+    fn pointer(&self, symbols: &SymbolMap, address: u64) -> Option<String> {
+        let bytes = self.bytes_at(address)?;
+        let value = match self.word_size {
+            4 => self.endian.parse_u32_at(&mut 0, bytes).ok()? as u64,
+            _ => self.endian.parse_u64_at(&mut 0, bytes).ok()?,
+        };
+        // 位置无关的文件里指针由加载器填写, 文件中可能是 0
+        let value = match value {
+            0 => *self.relative.get(&address)?,
+            value => value,
+        };
+        match symbols.resolve(value) {
+            Some((name, start)) if start == value => Some(name.to_string()),
+            Some((name, start)) => Some(format!("{}+0x{:x}", name, value - start)),
+            None => c_string(self.bytes_at(value)?),
+        }
+    }
+}
+
+/// The NUL-terminated text at the start of `bytes`, quoted and escaped, when
+/// it looks like a string.
+fn c_string(bytes: &[u8]) -> Option<String> {
+    const MAX_CHARS: usize = 60;
+    let end = bytes.iter().position(|&b| b == 0)?;
+    let text = std::str::from_utf8(&bytes[..end]).ok()?;
+    let printable = text
+        .chars()
+        .all(|c| !c.is_control() || matches!(c, '\n' | '\t' | '\r'));
+    if text.chars().count() < 2 || !printable {
+        return None;
+    }
+    let shown: String = text.chars().take(MAX_CHARS).collect();
+    let more = if text.chars().count() > MAX_CHARS {
+        "..."
+    } else {
+        ""
+    };
+    Some(format!("\"{}\"{}", shown.escape_debug(), more))
+}
+
+/// What the `size` bytes of a data symbol hold, when they look like a C
+/// string, a table of pointers or a number; shown above its hex dump.
+pub fn typed_data<'a>(lookup: &Lookup<'_>, address: u64, size: usize) -> Vec<Line<'a>> {
+    let (symbols, data) = (&lookup.symbols, &lookup.data);
+    let Some(bytes) = data.bytes_at(address) else {
+        return vec![];
+    };
+    let bytes = &bytes[..size.min(bytes.len())];
+    let value = Style::new().yellow();
+    let line = |label: &str, text: String| {
+        Line::from(vec![
+            Span::raw(format!("    {:<10}", label)),
+            Span::styled(text, value),
+        ])
+    };
+
+    if bytes.contains(&0) {
+        if let Some(text) = c_string(bytes) {
+            return vec![line("string", text), Line::from("")];
+        }
+    }
+    let word = data.word_size;
+    if bytes.len() >= word && bytes.len() % word == 0 && bytes.len() / word <= MAX_POINTERS {
+        let targets: Vec<Option<String>> = (0..bytes.len() / word)
+            .map(|i| data.pointer(symbols, address + (i * word) as u64))
+            .collect();
+        if targets.iter().any(Option::is_some) {
+            let mut lines: Vec<Line> = targets
+                .into_iter()
+                .enumerate()
+                .map(|(i, target)| {
+                    let label = if i == 0 { "pointers" } else { "" };
+                    let target = target.map_or(String::from("?"), |target| format!("-> {}", target));
+                    line(label, format!("[{}] {}", i, target))
+                })
+                .collect();
+            lines.push(Line::from(""));
+            return lines;
+        }
+    }
+    let endian = data.endian;
+    let (unsigned, signed, float) = match bytes.len() {
+        1 => (bytes[0] as u64, bytes[0] as i8 as i64, None),
+        2 => match endian.parse_u16_at(&mut 0, bytes) {
+            Ok(v) => (v as u64, v as i16 as i64, None),
+            Err(_) => return vec![],
+        },
+        4 => match endian.parse_u32_at(&mut 0, bytes) {
+            Ok(v) => (v as u64, v as i32 as i64, Some(f32::from_bits(v) as f64)),
+            Err(_) => return vec![],
+        },
+        8 => match endian.parse_u64_at(&mut 0, bytes) {
+            Ok(v) => (v, v as i64, Some(f64::from_bits(v))),
+            Err(_) => return vec![],
+        },
+        _ => return vec![],
+    };
+    let mut text = format!("{} (0x{:x})", unsigned, unsigned);
+    if signed < 0 {
+        text.push_str(&format!(", signed {}", signed));
+    }
+    let mut lines = vec![line("value", text)];
+    // 只有看起来像正常数值时才按浮点数解释
+    if let Some(float) = float.filter(|f| f.is_normal() && (1e-6..1e12).contains(&f.abs())) {
+        lines.push(line("as float", format!("{:?}", float)));
+    }
+    lines.push(Line::from(""));
+    lines
+}
+
+/// Hex and ASCII dump of a data symbol.
+pub fn dump_symbol<'a>(
+    elf: &ElfBytes<'a, AnyEndian>,
+    symbol_address: u64,
+    symbol_size: usize,
+    shdr: &SectionHeader,
+    section_name: &str,
+    diagnostics: &Diagnostics,
+) -> Vec<Line<'a>> {
+    let data = match symbol_bytes(
+        elf,
+        shdr,
+        section_name,
+        symbol_address,
+        symbol_size,
+        diagnostics,
+    ) {
+        Ok(data) => data,
+        Err(lines) => return lines,
+    };
+    let width = code_bitness(elf) as usize / 4;
+
+    data.chunks(BYTES_PER_LINE)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let address = symbol_address + (i * BYTES_PER_LINE) as u64;
+            let columns = format!("    {:0width$X}    ", address, width = width);
+            hex_line(columns, chunk, |_| Style::new().cyan())
+        })
+        .collect()
+}
+
+/// Names for every address the disassembler may print: `.symtab` and
+/// `.dynsym` symbols, PLT stubs (`name@plt`) and the GOT slots they jump
+/// through (`name@got`).
+pub fn collect_symbols(elf: &ElfBytes<'_, AnyEndian>, diagnostics: &Diagnostics) -> SymbolMap {
+    let mut addr_to_symbol = SymbolMap::new();
+    // 符号大小之外的地址按同一节里前面最近的符号加偏移显示
+    if let Some(shdrs) = elf.section_headers() {
+        for shdr in shdrs
+            .iter()
+            .filter(|shdr| shdr.sh_flags & abi::SHF_ALLOC as u64 != 0 && shdr.sh_size != 0)
+        {
+            addr_to_symbol.insert_section(shdr.sh_addr, shdr.sh_size);
+        }
+    }
+    // 解析符号表
+    match elf.symbol_table() {
+        Ok(Some((symbols, strtab))) => {
+            let location = table_location(elf, abi::SHT_SYMTAB);
+            for (i, symbol) in symbols.iter().enumerate() {
+                match strtab.get(symbol.st_name as usize) {
+                    Ok(name) if is_label(&symbol, name) => {
+                        let size = symbol_extent(elf, &symbol);
+                        addr_to_symbol.insert(symbol.st_value, name.to_string(), size);
                     }
+                    Ok(_) => {}
+                    Err(_) => diagnostics.report(ElfError::BadStringIndex {
+                        table: ".strtab",
+                        index: symbol.st_name as u64,
+                        offset: location.at(i).unwrap_or(location.offset),
+                    }),
                 }
             }
-            None => {}
-        };
+        }
+        Ok(None) => {}
+        Err(e) => diagnostics.report(ElfError::BadTable {
+            table: ".symtab",
+            offset: table_location(elf, abi::SHT_SYMTAB).offset,
+            reason: e.to_string(),
+        }),
+    }
 
-        // 解析PLT
-        if let Ok(Some(rela_plt)) = elf.section_header_by_name(".rela.plt") {
-            if let Ok((rela_data, _)) = elf.section_data(&rela_plt) {
-                if let Ok(Some(plt)) = elf.section_header_by_name(".plt") {
-                    let rela = elf.section_data_as_relas(&rela_plt).unwrap();
-                    let (dynsym, dynstr) = elf
-                        .dynamic_symbol_table()
-                        .expect("dynsym should parse")
-                        .unwrap();
-                    let _ = rela.enumerate().for_each(|(i, s)| {
-                        let sym = dynsym.get(s.r_sym as usize).unwrap();
-                        let name = dynstr.get(sym.st_name as usize).unwrap();
-
-                        addr_to_symbol.insert(
-                            plt.sh_addr + (i as u64 + 1) * plt.sh_entsize,
-                            format!("{}@plt", name),
-                        );
-                    });
+    // 导出的动态符号, 剥离了 .symtab 的文件只剩下这些名字
+    if let Ok(Some((dynsym, dynstr))) = elf.dynamic_symbol_table() {
+        for symbol in dynsym.iter() {
+            if let Ok(name) = dynstr.get(symbol.st_name as usize) {
+                if is_label(&symbol, name) {
+                    let size = symbol_extent(elf, &symbol);
+                    addr_to_symbol.insert_missing(symbol.st_value, name.to_string(), size);
                 }
             }
         }
+    }
+
+    let word_size = match elf.ehdr.class {
+        elf::file::Class::ELF32 => 4,
+        elf::file::Class::ELF64 => 8,
+    };
 
-        MySymbolResolver { addr_to_symbol }
+    // 解析PLT
+    if let (Some(relocs), Ok(Some(plt)), Ok(Some((dynsym, dynstr)))) = (
+        plt_relocations(elf, diagnostics),
+        elf.section_header_by_name(".plt"),
+        elf.dynamic_symbol_table(),
+    ) {
+        let sym_location = table_location(elf, abi::SHT_DYNSYM);
+        let (_, entry_size) = plt_layout(elf, &plt);
+        for (i, reloc) in relocs.entries.iter().enumerate() {
+            let name = plt_symbol_name(&dynsym, &dynstr, reloc, sym_location, diagnostics);
+            if let Some(address) = plt_entry_address(elf, &plt, i) {
+                addr_to_symbol.insert(address, format!("{}@plt", name), entry_size);
+            }
+            // GOT 槽位, AArch64 的 PLT 桩通过 adrp + ldr 读取
+            addr_to_symbol.insert(reloc.r_offset, format!("{}@got", name), word_size);
+        }
     }
 
-    pub fn create_box(elf: &ElfBytes<'_, AnyEndian>) -> Box<dyn SymbolResolver> {
-        Box::new(Self::new(elf))
+    // .got 中的其余槽位 (GLOB_DAT 等), 不经过 PLT 的函数和变量通过它们访问
+    if let (Ok(Some(got)), Ok(Some((dynsym, dynstr)))) = (
+        elf.section_header_by_name(".got"),
+        elf.dynamic_symbol_table(),
+    ) {
+        for (r_offset, r_sym) in dynamic_relocations(elf) {
+            if r_sym == 0 || r_offset < got.sh_addr || r_offset - got.sh_addr >= got.sh_size {
+                continue;
+            }
+            let Some(name) = dynsym
+                .get(r_sym as usize)
+                .ok()
+                .and_then(|sym| dynstr.get(sym.st_name as usize).ok())
+            else {
+                continue;
+            };
+            addr_to_symbol.insert_missing(r_offset, format!("{}@got", name), word_size);
+        }
     }
+
+    addr_to_symbol
 }
 
-impl SymbolResolver for MySymbolResolver {
-    fn symbol(
-        &mut self,
-        _instruction: &Instruction,
-        _operand: u32,
-        _instruction_operand: Option<u32>,
-        address: u64,
-        _address_size: u32,
-    ) -> Option<SymbolResult> {
-        if !(_instruction.is_call_far() || _instruction.is_call_near()) {
+/// How many bytes from a symbol resolve to `name+offset`: its size, or up to
+/// the end of its section for the labels assembly code defines without one.
+fn symbol_extent(elf: &ElfBytes<'_, AnyEndian>, symbol: &Symbol) -> u64 {
+    if symbol.st_size != 0 || symbol.st_shndx == abi::SHN_UNDEF || symbol.st_shndx >= SHN_LORESERVE
+    {
+        return symbol.st_size;
+    }
+    elf.section_headers()
+        .and_then(|shdrs| shdrs.get(symbol.st_shndx as usize).ok())
+        .map_or(0, |shdr| {
+            shdr.sh_addr
+                .saturating_add(shdr.sh_size)
+                .saturating_sub(symbol.st_value)
+        })
+}
+
+/// Addends of the `R_*_RELATIVE` relocations in `SHT_RELA` sections, by the
+/// address they patch.
+pub fn relative_relocations(elf: &ElfBytes<'_, AnyEndian>) -> HashMap<u64, u64> {
+    let relative = match elf.ehdr.e_machine {
+        abi::EM_X86_64 => abi::R_X86_64_RELATIVE,
+        abi::EM_AARCH64 => abi::R_AARCH64_RELATIVE,
+        abi::EM_RISCV => abi::R_RISCV_RELATIVE,
+        _ => return HashMap::new(),
+    };
+    let Some(shdrs) = elf.section_headers() else {
+        return HashMap::new();
+    };
+    shdrs
+        .iter()
+        .filter(|shdr| shdr.sh_type == abi::SHT_RELA)
+        .filter_map(|shdr| elf.section_data_as_relas(&shdr).ok())
+        .flatten()
+        .filter(|rela| rela.r_type == relative)
+        .map(|rela| (rela.r_offset, rela.r_addend as u64))
+        .collect()
+}
+
+/// Target address and symbol index of every relocation in the dynamic
+/// relocation tables; tables that can't be parsed are skipped.
+fn dynamic_relocations(elf: &ElfBytes<'_, AnyEndian>) -> Vec<(u64, u32)> {
+    let Some(shdrs) = elf.section_headers() else {
+        return vec![];
+    };
+    let mut relocations = vec![];
+    for shdr in shdrs
+        .iter()
+        .filter(|shdr| shdr.sh_flags & abi::SHF_ALLOC as u64 != 0)
+    {
+        match shdr.sh_type {
+            abi::SHT_RELA => {
+                if let Ok(relas) = elf.section_data_as_relas(&shdr) {
+                    relocations.extend(relas.map(|r| (r.r_offset, r.r_sym)));
+                }
+            }
+            abi::SHT_REL => {
+                if let Ok(rels) = elf.section_data_as_rels(&shdr) {
+                    relocations.extend(rels.map(|r| (r.r_offset, r.r_sym)));
+                }
+            }
+            _ => {}
+        }
+    }
+    relocations
+}
+
+/// Whether a symbol names a code or data address worth printing. Section,
+/// file and undefined symbols don't, nor do thread-local ones (their value is
+/// an offset into the TLS block) or the `$x`/`$d` mapping symbols of AArch64
+/// and RISC-V.
+fn is_label(symbol: &Symbol, name: &str) -> bool {
+    !symbol.is_undefined()
+        && !matches!(
+            symbol.st_symtype(),
+            abi::STT_SECTION | abi::STT_FILE | abi::STT_TLS
+        )
+        && !name.is_empty()
+        && !name.starts_with('$')
+}
+
+/// One entry of the PLT relocation table.
+pub struct PltReloc {
+    pub r_offset: u64,
+    pub r_sym: u32,
+    pub r_type: u32,
+    pub r_addend: i64,
+    /// File offset of the relocation entry itself.
+    pub offset: u64,
+}
+
+/// The PLT relocation table: `.rela.plt` on x86-64, `.rel.plt` on i386.
+pub struct PltRelocations {
+    pub section: &'static str,
+    pub entries: Vec<PltReloc>,
+}
+
+/// Read the PLT relocations, whichever of `.rela.plt` / `.rel.plt` the file uses.
+pub fn plt_relocations(
+    elf: &ElfBytes<'_, AnyEndian>,
+    diagnostics: &Diagnostics,
+) -> Option<PltRelocations> {
+    // 先核对表项大小, 否则按错误的步长读出来的都是垃圾
+    if let Ok(Some(shdr)) = elf.section_header_by_name(".rela.plt") {
+        let relas = Rela::validate_entsize(elf.ehdr.class, shdr.sh_entsize as usize)
+            .and_then(|_| elf.section_data_as_relas(&shdr))
+            .map(|relas| {
+                relas
+                    .map(|r| PltReloc {
+                        r_offset: r.r_offset,
+                        r_sym: r.r_sym,
+                        r_type: r.r_type,
+                        r_addend: r.r_addend,
+                        offset: 0,
+                    })
+                    .collect()
+            });
+        return locate_plt_relocations(".rela.plt", &shdr, relas, diagnostics);
+    }
+
+    let shdr = elf.section_header_by_name(".rel.plt").ok().flatten()?;
+    let rels = Rel::validate_entsize(elf.ehdr.class, shdr.sh_entsize as usize)
+        .and_then(|_| elf.section_data_as_rels(&shdr))
+        .map(|rels| {
+            rels.map(|r| PltReloc {
+                r_offset: r.r_offset,
+                r_sym: r.r_sym,
+                r_type: r.r_type,
+                r_addend: 0,
+                offset: 0,
+            })
+            .collect()
+        });
+    locate_plt_relocations(".rel.plt", &shdr, rels, diagnostics)
+}
+
+/// Fill in the file offset of each relocation, reporting the table when it
+/// can't be read or its offsets don't fit in 64 bits.
+fn locate_plt_relocations(
+    section: &'static str,
+    shdr: &SectionHeader,
+    entries: Result<Vec<PltReloc>, ParseError>,
+    diagnostics: &Diagnostics,
+) -> Option<PltRelocations> {
+    let report = |reason: String| {
+        diagnostics.report(ElfError::BadTable {
+            table: section,
+            offset: shdr.sh_offset,
+            reason,
+        })
+    };
+    let mut entries = match entries {
+        Ok(entries) => entries,
+        Err(e) => {
+            report(e.to_string());
             return None;
         }
+    };
+    let location = TableLocation::of(shdr);
+    for (i, entry) in entries.iter_mut().enumerate() {
+        let Some(offset) = location.at(i) else {
+            report(format!("entry {} is past the end of the address space", i));
+            return None;
+        };
+        entry.offset = offset;
+    }
+    Some(PltRelocations { section, entries })
+}
 
-        self.addr_to_symbol
-            .get(&address)
-            .map(|name| SymbolResult::with_str(address, name.as_str()))
+/// Size of the PLT header (PLT0) and of every following entry.
+///
+/// `sh_entsize` can't be trusted here: GNU ld sets it to 4 for the i386 PLT.
+pub fn plt_layout(elf: &ElfBytes<'_, AnyEndian>, plt: &SectionHeader) -> (u64, u64) {
+    match elf.ehdr.e_machine {
+        abi::EM_386 | abi::EM_X86_64 => (16, 16),
+        abi::EM_AARCH64 | abi::EM_RISCV => (32, 16),
+        _ => (plt.sh_entsize, plt.sh_entsize),
     }
 }
 
-// Custom formatter output that stores the output in a vector.
-struct MyFormatterOutput {
-    vec: Vec<(String, FormatterTextKind)>,
+/// Address of the PLT stub for the `idx`-th PLT relocation, `None` when the
+/// section header puts it past the end of the address space.
+pub fn plt_entry_address(
+    elf: &ElfBytes<'_, AnyEndian>,
+    plt: &SectionHeader,
+    idx: usize,
+) -> Option<u64> {
+    let (header, entry) = plt_layout(elf, plt);
+    plt.sh_addr
+        .checked_add(header)?
+        .checked_add((idx as u64).checked_mul(entry)?)
+}
+
+/// Name of the relocation type, as printed by readelf.
+pub fn reloc_type_name(e_machine: u16, r_type: u32) -> String {
+    let name = match (e_machine, r_type) {
+        (abi::EM_X86_64, abi::R_X86_64_JUMP_SLOT) => "R_X86_64_JUMP_SLOT",
+        (abi::EM_X86_64, abi::R_X86_64_IRELATIVE) => "R_X86_64_IRELATIVE",
+        (abi::EM_X86_64, abi::R_X86_64_GLOB_DAT) => "R_X86_64_GLOB_DAT",
+        (abi::EM_386, R_386_JUMP_SLOT) => "R_386_JUMP_SLOT",
+        (abi::EM_386, R_386_IRELATIVE) => "R_386_IRELATIVE",
+        (abi::EM_386, R_386_GLOB_DAT) => "R_386_GLOB_DAT",
+        (abi::EM_AARCH64, abi::R_AARCH64_JUMP_SLOT) => "R_AARCH64_JUMP_SLOT",
+        (abi::EM_AARCH64, abi::R_AARCH64_IRELATIVE) => "R_AARCH64_IRELATIVE",
+        (abi::EM_AARCH64, abi::R_AARCH64_GLOB_DAT) => "R_AARCH64_GLOB_DAT",
+        (abi::EM_RISCV, R_RISCV_JUMP_SLOT) => "R_RISCV_JUMP_SLOT",
+        (abi::EM_RISCV, R_RISCV_IRELATIVE) => "R_RISCV_IRELATIVE",
+        (abi::EM_RISCV, R_RISCV_64) => "R_RISCV_64",
+        _ => return format!("type {}", r_type),
+    };
+    name.to_string()
 }
 
-impl MyFormatterOutput {
-    pub fn new() -> Self {
-        Self { vec: Vec::new() }
+/// Symbol type, binding and visibility names, as printed by readelf.
+pub fn symbol_type_name(kind: u8) -> &'static str {
+    match kind {
+        abi::STT_NOTYPE => "NOTYPE",
+        abi::STT_OBJECT => "OBJECT",
+        abi::STT_FUNC => "FUNC",
+        abi::STT_SECTION => "SECTION",
+        abi::STT_FILE => "FILE",
+        abi::STT_COMMON => "COMMON",
+        abi::STT_TLS => "TLS",
+        abi::STT_GNU_IFUNC => "IFUNC",
+        _ => "OTHER",
     }
 }
 
-impl FormatterOutput for MyFormatterOutput {
-    fn write(&mut self, text: &str, kind: FormatterTextKind) {
-        // This allocates a string. If that's a problem, just call print!() here
-        // instead of storing the result in a vector.
-        self.vec.push((String::from(text), kind));
+pub fn symbol_bind_name(bind: u8) -> &'static str {
+    match bind {
+        abi::STB_LOCAL => "LOCAL",
+        abi::STB_GLOBAL => "GLOBAL",
+        abi::STB_WEAK => "WEAK",
+        abi::STB_GNU_UNIQUE => "UNIQUE",
+        _ => "OTHER",
     }
 }
 
-fn get_color<'a>(s: String, kind: FormatterTextKind) -> Span<'a> {
-    match kind {
-        FormatterTextKind::Directive | FormatterTextKind::Keyword => {
-            Span::styled(s, Style::new().yellow().italic())
-        }
-        FormatterTextKind::Prefix | FormatterTextKind::Mnemonic => {
-            Span::styled(s, Style::default().bold())
+pub fn symbol_visibility_name(visibility: u8) -> &'static str {
+    match visibility {
+        abi::STV_DEFAULT => "DEFAULT",
+        abi::STV_INTERNAL => "INTERNAL",
+        abi::STV_HIDDEN => "HIDDEN",
+        abi::STV_PROTECTED => "PROTECTED",
+        _ => "OTHER",
+    }
+}
+
+/// Name of the dynamic symbol a PLT relocation refers to, reporting bad indices.
+pub fn plt_symbol_name(
+    dynsym: &SymbolTable<'_, AnyEndian>,
+    dynstr: &StringTable<'_>,
+    reloc: &PltReloc,
+    sym_location: TableLocation,
+    diagnostics: &Diagnostics,
+) -> String {
+    // IRELATIVE 重定位没有符号, 只有解析函数的地址
+    if reloc.r_sym == 0 {
+        return format!("*ABS*+0x{:x}", reloc.r_addend);
+    }
+    let Ok(sym) = dynsym.get(reloc.r_sym as usize) else {
+        diagnostics.report(ElfError::SymbolOutOfRange {
+            what: format!("relocation refers to dynamic symbol {}", reloc.r_sym),
+            offset: reloc.offset,
+        });
+        return INVALID_NAME.to_string();
+    };
+    match dynstr.get(sym.st_name as usize) {
+        Ok(name) => name.to_string(),
+        Err(_) => {
+            diagnostics.report(ElfError::BadStringIndex {
+                table: ".dynstr",
+                index: sym.st_name as u64,
+                offset: sym_location
+                    .at(reloc.r_sym as usize)
+                    .unwrap_or(sym_location.offset),
+            });
+            INVALID_NAME.to_string()
         }
-        FormatterTextKind::Register => Span::styled(s, Style::new().green()),
-        FormatterTextKind::Number => Span::styled(s, Style::new().cyan()),
-        _ => Span::styled(s, Style::default()),
+    }
+}
+
+/// Decoder bitness for the file's code.
+///
+/// x32 objects are ELFCLASS32 but contain 64-bit code, so `e_machine` decides
+/// before the ELF class does.
+pub fn code_bitness(elf: &ElfBytes<'_, AnyEndian>) -> u32 {
+    match elf.ehdr.e_machine {
+        abi::EM_X86_64 => 64,
+        abi::EM_386 | abi::EM_IAMCU => 32,
+        _ => match elf.ehdr.class {
+            elf::file::Class::ELF32 => 32,
+            elf::file::Class::ELF64 => 64,
+        },
     }
 }
 
 pub fn get_interpreter(elf: &ElfBytes<AnyEndian>) -> Option<String> {
-    // 遍历程序头表查找 PT_INTERP 段
-    for ph in elf.segments().unwrap() {
+    // 遍历程序头表查找 PT_INTERP 段 (relocatable objects have no program headers)
+    for ph in elf.segments()? {
         if ph.p_type == elf::abi::PT_INTERP {
             // 读取 INTERP 段的数据
             if let Ok(data) = elf.segment_data(&ph) {
                 // 去掉结尾的 null 字节并转换为字符串
-                return String::from_utf8(data[..data.len() - 1].to_vec()).ok();
+                let data = data.strip_suffix(&[0]).unwrap_or(data);
+                return String::from_utf8(data.to_vec()).ok();
             }
         }
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::disasm::x86::X86Disassembler;
+
+    /// x86-64 code at 0x1000, decoded as a whole section with labels.
+    fn listing(code: &'static [u8], symbols: SymbolMap) -> Listing<'static> {
+        let symbols = Rc::new(symbols);
+        let options = FormatOptions::default();
+        let lookup = Lookup {
+            symbols: symbols.clone(),
+            data: Rc::new(DataSections {
+                sections: vec![],
+                relative: HashMap::new(),
+                endian: AnyEndian::Little,
+                word_size: 8,
+            }),
+        };
+        Listing {
+            text: vec![],
+            code: Some(Code {
+                bytes: code,
+                address: 0x1000,
+                offset: 0,
+                labels: true,
+                options,
+                lookup,
+                disassembler: Box::new(X86Disassembler::new(64, symbols, options)),
+                has_syntaxes: true,
+                address_width: 16,
+                bytes_width: MAX_BYTES_COLUMN * 3,
+                rows: vec![],
+                decoded: 0,
+                window: None,
+            }),
+        }
+    }
+
+    fn text(line: &Line) -> String {
+        let text: String = line
+            .spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect();
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// `movabs rax, imm64` and `nop`: the 11-byte groups straddle the chunks.
+    fn code() -> &'static [u8] {
+        let mut code = vec![];
+        for _ in 0..DECODE_CHUNK {
+            code.extend([0x48, 0xb8, 1, 0, 0, 0, 0, 0, 0, 0, 0x90]);
+        }
+        code.push(0xc3);
+        code.leak()
+    }
+
+    #[test]
+    fn decodes_as_far_as_needed() {
+        let mut listing = listing(code(), SymbolMap::new());
+        assert_eq!(listing.len(), 0);
+        listing.decode_to(10);
+        let first = listing.len();
+        assert!((11..=DECODE_CHUNK).contains(&first));
+        assert!(listing.estimated_len() > first);
+
+        // 第 n 组从 0x1000 + 11n 开始
+        let address = 0x1000 + 11 * 3000;
+        let line = listing.line_of(address + 5).unwrap();
+        assert_eq!(line, 6000);
+        assert!(listing.len() < 2 * DECODE_CHUNK);
+        let lines = listing.lines(line..line + 2);
+        assert_eq!(text(&lines[0]), "00000000000090E8 mov rax,1");
+        assert_eq!(text(&lines[1]), "00000000000090F2 nop");
+        assert_eq!(listing.step(line + 1).map(|step| step.len), Some(1));
+
+        listing.decode_to(usize::MAX);
+        assert_eq!(listing.len(), 2 * DECODE_CHUNK + 1);
+        assert_eq!(listing.estimated_len(), listing.len());
+        let steps = listing.steps();
+        assert!(steps.iter().all(Option::is_some));
+        assert_eq!(
+            steps.last().unwrap().unwrap().address,
+            0x1000 + 11 * DECODE_CHUNK as u64
+        );
+    }
+
+    #[test]
+    fn labels_known_symbols() {
+        let mut symbols = SymbolMap::new();
+        symbols.insert(0x1000, "start".to_string(), 11);
+        symbols.insert(0x100b, "next".to_string(), 11);
+        let mut listing = listing(code(), symbols);
+        let lines: Vec<String> = listing.lines(0..6).iter().map(text).collect();
+        assert_eq!(
+            lines,
+            [
+                "0000000000001000 <start>:",
+                "0000000000001000 mov rax,1",
+                "000000000000100A nop",
+                "",
+                "000000000000100B <next>:",
+                "000000000000100B mov rax,1",
+            ]
+        );
+        assert!(listing.step(0).is_none());
+        assert_eq!(listing.line_of(0x100b), Some(5));
+        assert_eq!(listing.line_of(0xfff), None);
+    }
+}
@@ -5,6 +5,7 @@ use ratatui::{
 };
 use elf::ElfBytes;
 use elf::endian::AnyEndian;
+use ratatui::crossterm::event::KeyCode;
 use ratatui::prelude::*;
 
 pub trait Page<'a> {
@@ -13,24 +14,31 @@ pub trait Page<'a> {
     fn select_left(&mut self);
     fn select_right(&mut self);
     fn page_render(&mut self, area: Rect, buf: &mut Buffer);
+
+    /// Keys specific to this page, seen before the global ones.
+    /// Returns `true` when the key was used.
+    fn handle_key(&mut self, _key: KeyCode, _elf: &ElfBytes<'a, AnyEndian>) -> bool {
+        false
+    }
 }
 
+/// Placeholder shown in place of a tab whose data is missing from the file.
 pub struct EmptyPage {
     message: String,
 }
 
 impl EmptyPage {
-    pub fn new() -> EmptyPage {
+    pub fn new(message: impl Into<String>) -> EmptyPage {
         EmptyPage {
-            message: String::from("This ELF file does not contain a symbol table"),
+            message: message.into(),
         }
     }
 }
 
-impl<'a> Widget for &EmptyPage {
+impl Widget for &EmptyPage {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let paragraph = Paragraph::new(self.message.clone())
-            .block(Block::bordered().title("Error"));
+            .block(Block::bordered().title("Not Available"));
         paragraph.render(area, buf);
     }
 }
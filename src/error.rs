@@ -0,0 +1,161 @@
+use std::cell::{Ref, RefCell};
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+
+use elf::section::SectionHeader;
+
+/// A problem found while reading data out of the ELF file.
+///
+/// Every variant carries the file offset of the structure that was being
+/// read, so the Diagnostics tab can point at the bytes involved.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ElfError {
+    /// The ELF header itself could not be parsed; nothing else can be shown.
+    BadHeader { reason: String },
+    /// A name refers to an offset outside of its string table.
+    BadStringIndex {
+        table: &'static str,
+        index: u64,
+        offset: u64,
+    },
+    /// A section's contents could not be read from the file.
+    TruncatedSection {
+        name: String,
+        offset: u64,
+        size: u64,
+    },
+    /// A table (symbols, relocations, ...) could not be parsed.
+    BadTable {
+        table: &'static str,
+        offset: u64,
+        reason: String,
+    },
+    /// A symbol index or address points outside of the table or section it refers to.
+    SymbolOutOfRange { what: String, offset: u64 },
+}
+
+impl ElfError {
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            ElfError::BadHeader { .. } => None,
+            ElfError::BadStringIndex { offset, .. }
+            | ElfError::TruncatedSection { offset, .. }
+            | ElfError::BadTable { offset, .. }
+            | ElfError::SymbolOutOfRange { offset, .. } => Some(*offset),
+        }
+    }
+}
+
+impl fmt::Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElfError::BadHeader { reason } => write!(f, "Invalid ELF header: {}", reason),
+            ElfError::BadStringIndex { table, index, .. } => {
+                write!(f, "String index {} is outside of {}", index, table)
+            }
+            ElfError::TruncatedSection { name, size, .. } => {
+                write!(f, "Section {} ({} bytes) is truncated", name, size)
+            }
+            ElfError::BadTable { table, reason, .. } => {
+                write!(f, "Table {} could not be parsed: {}", table, reason)
+            }
+            ElfError::SymbolOutOfRange { what, .. } => write!(f, "Out of range: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for ElfError {}
+
+/// File position of a table of fixed-size entries, used to locate bad entries.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TableLocation {
+    pub offset: u64,
+    pub entsize: u64,
+}
+
+impl TableLocation {
+    pub fn of(shdr: &SectionHeader) -> TableLocation {
+        TableLocation {
+            offset: shdr.sh_offset,
+            entsize: shdr.sh_entsize,
+        }
+    }
+
+    /// File offset of entry `idx`, `None` when it doesn't fit in 64 bits.
+    pub fn at(&self, idx: usize) -> Option<u64> {
+        (idx as u64)
+            .checked_mul(self.entsize)?
+            .checked_add(self.offset)
+    }
+}
+
+/// Shared collector of the problems found so far.
+///
+/// Pages keep a handle to it so problems hit while lazily decoding are
+/// recorded as well; identical problems are only recorded once.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    entries: Rc<RefCell<Vec<ElfError>>>,
+    /// The same problems, to find duplicates without scanning `entries`.
+    seen: Rc<RefCell<HashSet<ElfError>>>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics::default()
+    }
+
+    pub fn report(&self, error: ElfError) {
+        if self.seen.borrow_mut().insert(error.clone()) {
+            log::warn!("{}", error);
+            self.entries.borrow_mut().push(error);
+        }
+    }
+
+    pub fn entries(&self) -> Ref<'_, Vec<ElfError>> {
+        self.entries.borrow()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_offsets_overflow() {
+        let location = TableLocation {
+            offset: 0x40,
+            entsize: 24,
+        };
+        assert_eq!(location.at(2), Some(0x70));
+        let location = TableLocation {
+            offset: u64::MAX - 8,
+            entsize: u64::MAX / 2,
+        };
+        assert_eq!(location.at(0), Some(u64::MAX - 8));
+        assert_eq!(location.at(1), None);
+        assert_eq!(location.at(3), None);
+    }
+
+    #[test]
+    fn reports_each_problem_once() {
+        let diagnostics = Diagnostics::new();
+        for index in [1, 2, 1, 1, 2] {
+            diagnostics.report(ElfError::BadStringIndex {
+                table: ".strtab",
+                index,
+                offset: 0x100,
+            });
+        }
+        let indices: Vec<u64> = diagnostics
+            .entries()
+            .iter()
+            .map(|error| match error {
+                ElfError::BadStringIndex { index, .. } => *index,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(indices, [1, 2]);
+    }
+}
@@ -0,0 +1,523 @@
+//! The list panes, narrowed with `/` to the names matching a substring, a
+//! fuzzy pattern or a regular expression.
+
+use std::mem;
+use std::ops::Range;
+
+use ratatui::{
+    buffer::Buffer,
+    crossterm::event::KeyCode,
+    layout::Rect,
+    style::{Color, Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, ListDirection, ListState, StatefulWidget},
+};
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Case-insensitive.
+    Substring,
+    /// The characters of the query in order, with anything between them.
+    Fuzzy,
+    Regex,
+}
+
+impl FilterMode {
+    fn name(self) -> &'static str {
+        match self {
+            FilterMode::Substring => "substring",
+            FilterMode::Fuzzy => "fuzzy",
+            FilterMode::Regex => "regex",
+        }
+    }
+
+    fn next(self) -> FilterMode {
+        match self {
+            FilterMode::Substring => FilterMode::Fuzzy,
+            FilterMode::Fuzzy => FilterMode::Regex,
+            FilterMode::Regex => FilterMode::Substring,
+        }
+    }
+}
+
+/// A list of names that `/` narrows. The selection is kept on the same entry
+/// while the filter changes; entries are numbered as in `names`, whichever
+/// rows are shown and in whatever order. Only the visible rows are turned
+/// into list items, when rendering.
+pub struct FilteredList {
+    title: String,
+    highlight_symbol: &'static str,
+    names: Vec<String>,
+    /// The entries in the order they are listed.
+    order: Vec<usize>,
+    /// Shown before each name when set, with a header for the title.
+    columns: Option<(String, Vec<String>)>,
+    /// Shown dimmed after the name of each entry when set.
+    tags: Option<Vec<&'static str>>,
+    /// The entry shown on each row.
+    rows: Vec<usize>,
+    /// Selected row and scroll position.
+    pub state: ListState,
+    query: String,
+    mode: FilterMode,
+    /// `None` when the query isn't a valid regular expression.
+    regex: Option<Regex>,
+    /// Keys go to the query while it is being typed.
+    editing: bool,
+}
+
+impl FilteredList {
+    pub fn new(
+        title: &'static str,
+        highlight_symbol: &'static str,
+        names: Vec<String>,
+    ) -> FilteredList {
+        let mut list = FilteredList {
+            title: title.to_string(),
+            highlight_symbol,
+            order: (0..names.len()).collect(),
+            names,
+            columns: None,
+            tags: None,
+            rows: vec![],
+            state: ListState::default(),
+            query: String::new(),
+            mode: FilterMode::Substring,
+            regex: None,
+            editing: false,
+        };
+        list.refilter();
+        list
+    }
+
+    /// Show other names for the same entries, e.g. demangled ones.
+    pub fn set_names(&mut self, names: Vec<String>) {
+        self.names = names;
+        self.refilter();
+    }
+
+    pub fn set_title(&mut self, title: String) {
+        self.title = title;
+    }
+
+    /// List the entries in `order`; the ones left out are hidden, whatever
+    /// the query.
+    pub fn set_order(&mut self, order: Vec<usize>) {
+        self.order = order;
+        self.refilter();
+    }
+
+    /// Show `columns` before the name of each entry, as a table whose column
+    /// names `header` puts in the title. `None` lists the names alone.
+    pub fn set_columns(&mut self, columns: Option<(String, Vec<String>)>) {
+        self.columns = columns;
+    }
+
+    /// Show `tags` dimmed after the name of each entry; `None` shows none.
+    pub fn set_tags(&mut self, tags: Option<Vec<&'static str>>) {
+        self.tags = tags;
+    }
+
+    /// The entries shown, in order.
+    pub fn rows(&self) -> &[usize] {
+        &self.rows
+    }
+
+    /// The selected entry.
+    pub fn selected(&self) -> Option<usize> {
+        self.rows.get(self.state.selected()?).copied()
+    }
+
+    /// Select `entry`, clearing the filter if it hides it.
+    pub fn select(&mut self, entry: usize) {
+        if !self.rows.contains(&entry) {
+            self.query.clear();
+            self.editing = false;
+            self.refilter();
+        }
+        self.state
+            .select(self.rows.iter().position(|&row| row == entry));
+    }
+
+    pub fn select_next(&mut self) {
+        let row = self.state.selected().map_or(0, |row| row + 1);
+        if row < self.rows.len() {
+            self.state.select(Some(row));
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        let last = self.rows.len().saturating_sub(1);
+        let row = self
+            .state
+            .selected()
+            .map_or(last, |row| row.saturating_sub(1));
+        if row < self.rows.len() {
+            self.state.select(Some(row));
+        }
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.editing
+    }
+
+    /// `/` starts typing a query, Tab switches the matching mode, Enter
+    /// keeps the filter and selects the first match if nothing is selected,
+    /// Esc drops it. The arrow keys still move the selection while typing.
+    /// Returns whether the key was used.
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        if !self.editing {
+            match key {
+                KeyCode::Char('/') => self.editing = true,
+                KeyCode::Esc if !self.query.is_empty() => self.query.clear(),
+                _ => return false,
+            }
+        } else {
+            match key {
+                // 子串和模糊匹配时, 加长的查询只会在已匹配的行里缩小范围
+                KeyCode::Char(c) if self.mode != FilterMode::Regex => {
+                    self.query.push(c);
+                    self.narrow();
+                    return true;
+                }
+                KeyCode::Char(c) => self.query.push(c),
+                KeyCode::Backspace => {
+                    self.query.pop();
+                }
+                KeyCode::Tab => self.mode = self.mode.next(),
+                KeyCode::Enter => {
+                    self.editing = false;
+                    if self.state.selected().is_none() && !self.rows.is_empty() {
+                        self.state.select(Some(0));
+                    }
+                    return true;
+                }
+                KeyCode::Esc => {
+                    self.query.clear();
+                    self.editing = false;
+                }
+                _ => return false,
+            }
+        }
+        self.refilter();
+        true
+    }
+
+    /// Render the rows in view, scrolled so that the selected one is visible.
+    pub fn render(&mut self, area: Rect, buf: &mut Buffer) {
+        let block = self.block();
+        let height = block.inner(area).height as usize;
+        let selected = self.state.selected().filter(|&row| row < self.rows.len());
+        let mut offset = self.state.offset().min(self.rows.len().saturating_sub(1));
+        if let Some(row) = selected {
+            offset = offset.min(row).max((row + 1).saturating_sub(height));
+        }
+        *self.state.offset_mut() = offset;
+
+        let end = (offset + height).min(self.rows.len());
+        let items: Vec<Line> = self.rows[offset..end]
+            .iter()
+            .map(|&entry| self.item(entry))
+            .collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
+            .highlight_symbol(self.highlight_symbol)
+            .repeat_highlight_symbol(true)
+            .direction(ListDirection::TopToBottom);
+        let mut window = ListState::default().with_selected(selected.map(|row| row - offset));
+        StatefulWidget::render(list, area, buf, &mut window);
+    }
+
+    /// The line of `entry`: its columns, its name with the matches
+    /// highlighted and its tag.
+    fn item(&self, entry: usize) -> Line<'static> {
+        let name = &self.names[entry];
+        let mut line = highlight(name, &self.find(name).unwrap_or_default());
+        if let Some((_, columns)) = &self.columns {
+            line.spans.insert(0, Span::raw(columns[entry].clone()));
+        }
+        if let Some(tag) = self.tags.as_ref().and_then(|tags| tags.get(entry)) {
+            line.push_span(Span::raw(format!(" {}", tag)).dark_gray());
+        }
+        line
+    }
+
+    /// The border with the title, or the table header, and the filter status.
+    fn block(&self) -> Block<'static> {
+        // 表头放在上边框里, 留出高亮符号的宽度与各列对齐
+        let title = match &self.columns {
+            Some((header, _)) => format!("{}{}", "─".repeat(self.highlight_symbol.len()), header),
+            None => self.title.clone(),
+        };
+        let block = Block::bordered().title(title);
+        if !self.editing && self.query.is_empty() {
+            return block.title_bottom("/: filter");
+        }
+        let mut status = format!("/{}", self.query);
+        if self.editing {
+            status.push('▏');
+        }
+        status.push_str(&format!(
+            " [{}] {}/{}",
+            self.mode.name(),
+            self.rows.len(),
+            self.order.len()
+        ));
+        if self.mode == FilterMode::Regex && self.regex.is_none() {
+            block.title_bottom(Line::from(status + " invalid regex").red())
+        } else {
+            block.title_bottom(status)
+        }
+    }
+
+    /// Recompute the rows after the query, the order or the names changed,
+    /// keeping the selected entry when it still matches.
+    fn refilter(&mut self) {
+        let selected = self.selected();
+        self.regex = match self.mode {
+            FilterMode::Regex => Regex::new(&self.query).ok(),
+            _ => None,
+        };
+        let mut rows = mem::take(&mut self.rows);
+        rows.clear();
+        rows.extend(
+            self.order
+                .iter()
+                .copied()
+                .filter(|&entry| self.find(&self.names[entry]).is_some()),
+        );
+        self.rows = rows;
+        self.reselect(selected);
+    }
+
+    /// Recompute the rows after the query got longer: only the rows that
+    /// matched before can still match.
+    fn narrow(&mut self) {
+        let selected = self.selected();
+        let mut rows = mem::take(&mut self.rows);
+        rows.retain(|&entry| self.find(&self.names[entry]).is_some());
+        self.rows = rows;
+        self.reselect(selected);
+    }
+
+    fn reselect(&mut self, selected: Option<usize>) {
+        // 选中项被过滤掉时改选第一个匹配项
+        let row = match selected {
+            Some(entry) => self.rows.iter().position(|&row| row == entry).or(Some(0)),
+            None => None,
+        };
+        self.state.select(row.filter(|_| !self.rows.is_empty()));
+    }
+
+    /// The byte ranges of `name` matching the query, or `None` when it
+    /// doesn't match. Everything matches an empty or invalid query.
+    fn find(&self, name: &str) -> Option<Vec<Range<usize>>> {
+        if self.query.is_empty() {
+            return Some(vec![]);
+        }
+        match self.mode {
+            FilterMode::Substring => {
+                // 只做 ASCII 小写转换, 字节位置不变
+                let haystack = name.to_ascii_lowercase();
+                let needle = self.query.to_ascii_lowercase();
+                let found: Vec<Range<usize>> = haystack
+                    .match_indices(&needle)
+                    .map(|(start, text)| start..start + text.len())
+                    .collect();
+                (!found.is_empty()).then_some(found)
+            }
+            FilterMode::Fuzzy => {
+                let mut query = self
+                    .query
+                    .chars()
+                    .map(|c| c.to_ascii_lowercase())
+                    .peekable();
+                let mut found: Vec<Range<usize>> = vec![];
+                for (start, c) in name.char_indices() {
+                    if query.peek() != Some(&c.to_ascii_lowercase()) {
+                        continue;
+                    }
+                    query.next();
+                    let end = start + c.len_utf8();
+                    match found.last_mut() {
+                        Some(last) if last.end == start => last.end = end,
+                        _ => found.push(start..end),
+                    }
+                }
+                query.peek().is_none().then_some(found)
+            }
+            FilterMode::Regex => {
+                let Some(regex) = &self.regex else {
+                    return Some(vec![]);
+                };
+                regex.is_match(name).then(|| {
+                    regex
+                        .find_iter(name)
+                        .map(|found| found.range())
+                        .filter(|range| !range.is_empty())
+                        .collect()
+                })
+            }
+        }
+    }
+}
+
+/// `name` with the `found` ranges highlighted.
+fn highlight<'a>(name: &str, found: &[Range<usize>]) -> Line<'a> {
+    let style = Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let mut spans = vec![];
+    let mut at = 0;
+    for range in found {
+        if range.start > at {
+            spans.push(Span::raw(name[at..range.start].to_string()));
+        }
+        spans.push(Span::styled(name[range.clone()].to_string(), style));
+        at = range.end;
+    }
+    if at < name.len() {
+        spans.push(Span::raw(name[at..].to_string()));
+    }
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(names: &[&str]) -> FilteredList {
+        FilteredList::new(
+            "Symbols",
+            ">> ",
+            names.iter().map(|s| s.to_string()).collect(),
+        )
+    }
+
+    /// The parts of `name` the query highlights.
+    fn matched<'a>(list: &FilteredList, name: &'a str) -> Option<Vec<&'a str>> {
+        let found = list.find(name)?;
+        Some(found.into_iter().map(|range| &name[range]).collect())
+    }
+
+    /// Type `keys` into the query, starting it first if needed.
+    fn type_query(list: &mut FilteredList, keys: &str) {
+        if !list.is_editing() {
+            list.handle_key(KeyCode::Char('/'));
+        }
+        for c in keys.chars() {
+            list.handle_key(KeyCode::Char(c));
+        }
+    }
+
+    const NAMES: [&str; 6] = [
+        "main",
+        "_start",
+        "parse_header",
+        "PARSE_Args",
+        "print_help",
+        "deregister_tm_clones",
+    ];
+
+    #[test]
+    fn substring_ignores_case() {
+        let mut list = list(&NAMES);
+        type_query(&mut list, "parse");
+        assert_eq!(list.rows(), [2, 3]);
+        assert_eq!(matched(&list, "PARSE_Args"), Some(vec!["PARSE"]));
+    }
+
+    #[test]
+    fn fuzzy_matches_characters_in_order() {
+        let mut list = list(&NAMES);
+        list.handle_key(KeyCode::Char('/'));
+        list.handle_key(KeyCode::Tab);
+        for c in "prh".chars() {
+            list.handle_key(KeyCode::Char(c));
+        }
+        assert_eq!(list.rows(), [2, 4]);
+        // 相邻的匹配字符合并成一段
+        assert_eq!(matched(&list, "print_help"), Some(vec!["pr", "h"]));
+        assert_eq!(matched(&list, "main"), None);
+    }
+
+    #[test]
+    fn regex_matches() {
+        let mut list = list(&NAMES);
+        list.handle_key(KeyCode::Char('/'));
+        list.handle_key(KeyCode::Tab);
+        list.handle_key(KeyCode::Tab);
+        for c in "^p.*_h".chars() {
+            list.handle_key(KeyCode::Char(c));
+        }
+        assert_eq!(list.rows(), [2, 4]);
+        assert_eq!(matched(&list, "parse_header"), Some(vec!["parse_h"]));
+    }
+
+    #[test]
+    fn invalid_regex_matches_everything() {
+        let mut list = list(&NAMES);
+        list.handle_key(KeyCode::Char('/'));
+        list.handle_key(KeyCode::Tab);
+        list.handle_key(KeyCode::Tab);
+        type_query(&mut list, "(main");
+        assert!(list.regex.is_none());
+        assert_eq!(list.rows().len(), NAMES.len());
+        assert_eq!(matched(&list, "main"), Some(vec![]));
+    }
+
+    #[test]
+    fn empty_query_lists_everything_in_order() {
+        let mut list = list(&NAMES);
+        list.set_order(vec![5, 0, 3]);
+        assert_eq!(list.rows(), [5, 0, 3]);
+        type_query(&mut list, "");
+        assert_eq!(list.rows(), [5, 0, 3]);
+        type_query(&mut list, "x");
+        list.handle_key(KeyCode::Backspace);
+        assert_eq!(list.rows(), [5, 0, 3]);
+    }
+
+    #[test]
+    fn narrowing_equals_refiltering() {
+        for query in ["r", "re", "reg", "_", "p_h", "zz"] {
+            for mode in [FilterMode::Substring, FilterMode::Fuzzy] {
+                let mut narrowed = list(&NAMES);
+                narrowed.mode = mode;
+                type_query(&mut narrowed, query);
+                let mut refiltered = list(&NAMES);
+                refiltered.mode = mode;
+                refiltered.query = query.to_string();
+                refiltered.refilter();
+                assert_eq!(narrowed.rows(), refiltered.rows(), "{:?} {}", mode, query);
+            }
+        }
+    }
+
+    #[test]
+    fn selection_follows_the_entry() {
+        let mut list = list(&NAMES);
+        list.select(4);
+        type_query(&mut list, "p");
+        assert_eq!(list.selected(), Some(4));
+        // 选中项被过滤掉时改选第一个匹配项
+        type_query(&mut list, "ars");
+        assert_eq!(list.rows(), [2, 3]);
+        assert_eq!(list.selected(), Some(2));
+        // 没有匹配项时什么都不选
+        type_query(&mut list, "zz");
+        assert_eq!(list.selected(), None);
+        list.handle_key(KeyCode::Esc);
+        assert_eq!(list.rows().len(), NAMES.len());
+    }
+
+    #[test]
+    fn enter_selects_the_first_match() {
+        let mut list = list(&NAMES);
+        type_query(&mut list, "help");
+        assert_eq!(list.selected(), None);
+        list.handle_key(KeyCode::Enter);
+        assert!(!list.is_editing());
+        assert_eq!(list.selected(), Some(4));
+    }
+}
@@ -0,0 +1,49 @@
+//! Hex + ASCII dump lines, shared by the section contents pane and data symbols.
+
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+
+pub const BYTES_PER_LINE: usize = 16;
+
+/// One dump line: the `columns` text (offset, address, ...), the bytes in hex,
+/// then in ASCII. `style_of` picks the style of the byte at each index of `chunk`.
+pub fn hex_line<'a>(columns: String, chunk: &[u8], style_of: impl Fn(usize) -> Style) -> Line<'a> {
+    let mut hex = Spans::default();
+    let mut ascii = Spans::default();
+    for (i, &byte) in chunk.iter().enumerate() {
+        let style = style_of(i);
+        hex.push(&format!("{:02X} ", byte), style);
+        ascii.push(&printable(byte).to_string(), style);
+    }
+
+    let mut spans = vec![Span::raw(columns)];
+    spans.extend(hex.spans);
+    // 不足一行时补齐, 让 ASCII 列对齐
+    spans.push(Span::raw(
+        " ".repeat((BYTES_PER_LINE - chunk.len()) * 3 + 1),
+    ));
+    spans.extend(ascii.spans);
+    Line::from(spans)
+}
+
+fn printable(byte: u8) -> char {
+    match byte {
+        0x20..=0x7e => byte as char,
+        _ => '.',
+    }
+}
+
+/// Spans that merge consecutive text of the same style.
+#[derive(Default)]
+struct Spans {
+    spans: Vec<Span<'static>>,
+}
+
+impl Spans {
+    fn push(&mut self, text: &str, style: Style) {
+        match self.spans.last_mut() {
+            Some(last) if last.style == style => last.content.to_mut().push_str(text),
+            _ => self.spans.push(Span::styled(text.to_string(), style)),
+        }
+    }
+}
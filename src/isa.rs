@@ -0,0 +1,243 @@
+//! Which x86 instruction set extensions a binary uses, and in which functions.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+
+use elf::abi;
+use elf::endian::AnyEndian;
+use elf::ElfBytes;
+use iced_x86::{CpuidFeature, Decoder, DecoderOptions, Instruction};
+
+use crate::demangle::demangle;
+use crate::disasm::FormatOptions;
+use crate::discovery::Discovery;
+use crate::elf::{code_bitness, collect_symbols};
+use crate::error::Diagnostics;
+use crate::utils::map_file;
+
+/// Functions listed for each extension in the summary.
+const TOP_FUNCTIONS: usize = 3;
+
+pub struct Extension {
+    pub name: String,
+    /// The x86-64 microarchitecture level (1-4) that includes it; `None` in
+    /// 32-bit code, which has no levels.
+    pub level: Option<u8>,
+    pub instructions: usize,
+    /// Functions using the extension and how many of their instructions do,
+    /// most first. Code outside of any known function is left out.
+    pub functions: Vec<(String, usize)>,
+}
+
+impl Extension {
+    /// One line for the summary: level, counts and the functions using it most.
+    pub fn summary(&self) -> String {
+        let level = self
+            .level
+            .map_or(String::from("  "), |level| format!("v{}", level));
+        let mut text = format!(
+            "{:<14} {}  {:>7} instructions in {} functions",
+            self.name,
+            level,
+            self.instructions,
+            self.functions.len()
+        );
+        let top: Vec<String> = self
+            .functions
+            .iter()
+            .take(TOP_FUNCTIONS)
+            .map(|(name, count)| format!("{} ({})", name, count))
+            .collect();
+        if !top.is_empty() {
+            text.push_str(": ");
+            text.push_str(&top.join(", "));
+        }
+        text
+    }
+}
+
+pub struct IsaReport {
+    /// By level, then name; the extensions outside of the levels come last.
+    pub extensions: Vec<Extension>,
+    /// The levels are only defined for x86-64.
+    x86_64: bool,
+}
+
+impl IsaReport {
+    /// The highest x86-64 level among the extensions used, `None` for
+    /// 32-bit code.
+    pub fn level(&self) -> Option<u8> {
+        let highest = self
+            .extensions
+            .iter()
+            .filter_map(|extension| extension.level)
+            .max();
+        self.x86_64.then(|| highest.unwrap_or(1))
+    }
+}
+
+/// Scan the file in a background thread, like the hash; `None` when it is
+/// not x86 code or can't be read again. Function names are shown as `options`
+/// says; in stripped files they come from `discovery`, which is waited for.
+pub fn spawn_isa_scan(
+    path: &Path,
+    discovery: Arc<OnceLock<Discovery>>,
+    options: FormatOptions,
+) -> Arc<OnceLock<Option<IsaReport>>> {
+    let result = Arc::new(OnceLock::new());
+    let cell = result.clone();
+    let path = path.to_path_buf();
+    thread::spawn(move || {
+        let discovery = discovery.wait();
+        let report = map_file(&path).ok().and_then(|mapping| {
+            let elf = crate::elf::parse(&mapping).ok()?;
+            scan(&elf, discovery, options, &Diagnostics::new())
+        });
+        let _ = cell.set(report);
+    });
+    result
+}
+
+/// Decode every executable section and count the extensions each
+/// instruction needs, per function.
+pub fn scan(
+    elf: &ElfBytes<'_, AnyEndian>,
+    discovery: &Discovery,
+    options: FormatOptions,
+    diagnostics: &Diagnostics,
+) -> Option<IsaReport> {
+    if !matches!(
+        elf.ehdr.e_machine,
+        abi::EM_386 | abi::EM_IAMCU | abi::EM_X86_64
+    ) {
+        return None;
+    }
+    let mut functions = collect_symbols(elf, diagnostics);
+    // 剥离了符号表时用找到的函数代替
+    for function in &discovery.functions {
+        let name = format!("sub_{:X}", function.address);
+        functions.insert_missing(function.address, name, function.size);
+    }
+
+    // 扩展 -> 函数起始地址 -> 指令数
+    let mut counts: HashMap<CpuidFeature, BTreeMap<Option<u64>, usize>> = HashMap::new();
+    let bitness = code_bitness(elf);
+    for shdr in elf.section_headers()?.iter().filter(|shdr| {
+        shdr.sh_flags & abi::SHF_EXECINSTR as u64 != 0 && shdr.sh_type != abi::SHT_NOBITS
+    }) {
+        let Ok((code, None)) = elf.section_data(&shdr) else {
+            continue;
+        };
+        let mut decoder = Decoder::with_ip(bitness, code, shdr.sh_addr, DecoderOptions::NONE);
+        let mut instruction = Instruction::default();
+        while decoder.can_decode() {
+            decoder.decode_out(&mut instruction);
+            if instruction.is_invalid() {
+                continue;
+            }
+            for &feature in instruction.cpuid_features() {
+                if is_baseline(feature) {
+                    continue;
+                }
+                let function = functions.resolve(instruction.ip()).map(|(_, start)| start);
+                *counts
+                    .entry(feature)
+                    .or_default()
+                    .entry(function)
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    let x86_64 = elf.ehdr.e_machine == abi::EM_X86_64;
+    let mut extensions: Vec<Extension> = counts
+        .into_iter()
+        .map(|(feature, by_function)| {
+            let mut named: Vec<(String, usize)> = by_function
+                .iter()
+                .filter_map(|(&start, &count)| {
+                    let name = functions.get(start?)?;
+                    Some((demangle(name, options).into_owned(), count))
+                })
+                .collect();
+            named.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            Extension {
+                name: format!("{:?}", feature),
+                level: level(feature).filter(|_| x86_64),
+                instructions: by_function.values().sum(),
+                functions: named,
+            }
+        })
+        .collect();
+    extensions.sort_by(|a, b| {
+        let key = |e: &Extension| (e.level.unwrap_or(u8::MAX), e.name.clone());
+        key(a).cmp(&key(b))
+    });
+    Some(IsaReport { extensions, x86_64 })
+}
+
+/// Features every x86 or x86-64 processor has, which aren't worth listing.
+pub fn is_baseline(feature: CpuidFeature) -> bool {
+    matches!(
+        feature,
+        CpuidFeature::INTEL8086
+            | CpuidFeature::INTEL8086_ONLY
+            | CpuidFeature::INTEL186
+            | CpuidFeature::INTEL286
+            | CpuidFeature::INTEL286_ONLY
+            | CpuidFeature::INTEL386
+            | CpuidFeature::INTEL386_ONLY
+            | CpuidFeature::INTEL386_A0_ONLY
+            | CpuidFeature::INTEL486
+            | CpuidFeature::INTEL486_A_ONLY
+            | CpuidFeature::X64
+    )
+}
+
+/// The x86-64 psABI microarchitecture level that first requires `feature`.
+fn level(feature: CpuidFeature) -> Option<u8> {
+    use CpuidFeature::*;
+    match feature {
+        CMOV | CX8 | FPU | FXSR | MMX | SSE | SSE2 | SYSCALL => Some(1),
+        CMPXCHG16B | POPCNT | SSE3 | SSE4_1 | SSE4_2 | SSSE3 => Some(2),
+        AVX | AVX2 | BMI1 | BMI2 | F16C | FMA | LZCNT | MOVBE | XSAVE => Some(3),
+        AVX512F | AVX512BW | AVX512CD | AVX512DQ | AVX512VL => Some(4),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extension(name: &str, level: Option<u8>) -> Extension {
+        Extension {
+            name: name.to_string(),
+            level,
+            instructions: 1,
+            functions: vec![],
+        }
+    }
+
+    #[test]
+    fn levels_are_only_reported_for_x86_64() {
+        let report = IsaReport {
+            extensions: vec![extension("AVX2", Some(3)), extension("AES", None)],
+            x86_64: true,
+        };
+        assert_eq!(report.level(), Some(3));
+        let report = IsaReport {
+            extensions: vec![],
+            x86_64: true,
+        };
+        assert_eq!(report.level(), Some(1));
+        let report = IsaReport {
+            extensions: vec![extension("SSE2", None)],
+            x86_64: false,
+        };
+        assert_eq!(report.level(), None);
+        assert!(report.extensions[0].summary().starts_with("SSE2              "));
+    }
+}
@@ -3,17 +3,16 @@ use ::elf::ElfBytes;
 use clap::Parser;
 use ratatui::buffer::Buffer;
 use ratatui::style::palette::tailwind;
-use ratatui::symbols;
 use ratatui::text::Line;
-use sha2::{Digest, Sha256};
 use std::env;
 use std::io::{self, stdout};
 use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
 
 use ratatui::layout::{Constraint, Layout, Rect};
 use ratatui::prelude::Backend;
 use ratatui::style::{Color, Stylize};
-use ratatui::widgets::{Padding, Tabs, Widget};
+use ratatui::widgets::{Tabs, Widget};
 use ratatui::{
     backend::CrosstermBackend,
     crossterm::{
@@ -21,24 +20,40 @@ use ratatui::{
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
         ExecutableCommand,
     },
-    widgets::Block,
     Terminal,
 };
 use strum::{Display, EnumIter, FromRepr, IntoEnumIterator};
 
+mod cfg;
+mod demangle;
 mod deps;
+mod diagnostics;
+mod disasm;
+mod discovery;
 mod elf;
 mod empty;
+mod error;
+mod filter;
+mod hex;
+mod isa;
 mod plt;
 mod section;
+mod segment;
 mod summary;
 mod symbol;
 mod utils;
+mod xref;
 
 use deps::DependenciesPage;
+use diagnostics::DiagnosticsPage;
+use disasm::{FormatOptions, HexStyle, MemorySize, Syntax};
+use discovery::Discovery;
 use empty::{EmptyPage, Page};
+use error::{Diagnostics, ElfError};
+use isa::IsaReport;
 use plt::PLTPage;
 use section::SectionPage;
+use segment::SegmentPage;
 use summary::SummaryPage;
 use symbol::SymbolPage;
 
@@ -48,37 +63,73 @@ use symbol::SymbolPage;
 struct Args {
     /// Path of the executable file
     file: PathBuf,
+
+    /// Assembly syntax for x86 code; press `s` in the Deassembly tab to switch
+    #[arg(long, value_enum, default_value_t)]
+    syntax: Syntax,
+
+    /// How hexadecimal numbers are written in x86 code
+    #[arg(long, value_enum, default_value_t)]
+    hex: HexStyle,
+
+    /// When x86 memory operands get a size keyword (`dword ptr`)
+    #[arg(long, value_enum, default_value_t)]
+    memory_size: MemorySize,
+
+    /// Show C++ and Rust symbol names mangled; press `d` to switch
+    #[arg(long)]
+    mangled: bool,
+
+    /// Leave the hash suffix out of demangled Rust names
+    #[arg(long)]
+    hide_rust_hash: bool,
 }
 
 struct App<'a> {
     should_quit: bool,
     elf: ElfBytes<'a, AnyEndian>,
     summary_page: SummaryPage,
-    section_page: SectionPage<'a>,
+    section_page: Box<dyn Page<'a> + 'a>,
+    segment_page: Box<dyn Page<'a> + 'a>,
     symbol_page: Box<dyn Page<'a> + 'a>,
-    plt_page: PLTPage<'a>,
-    deps_page: DependenciesPage<'a>,
+    plt_page: Box<dyn Page<'a> + 'a>,
+    deps_page: Box<dyn Page<'a> + 'a>,
+    diagnostics_page: DiagnosticsPage,
     selected_tab: AppTab,
 }
 
 #[derive(Default, Clone, Copy, Display, FromRepr, EnumIter)]
+#[allow(clippy::upper_case_acronyms)]
 enum AppTab {
     #[default]
     #[strum(to_string = "Summary")]
     Summary,
     #[strum(to_string = "Sections")]
     Sections,
+    #[strum(to_string = "Segments")]
+    Segments,
     #[strum(to_string = "Deassembly")]
     Deassembly,
     #[strum(to_string = "Dynamic Symbols & PLT")]
     PLT,
     #[strum(to_string = "Dependencies")]
     Dependencies,
+    #[strum(to_string = "Diagnostics")]
+    Diagnostics,
 }
 
 impl<'a> App<'a> {
-    fn new(path: &PathBuf, file_hash: String, elf: ElfBytes<'a, AnyEndian>) -> App<'a> {
+    fn new(
+        path: &PathBuf,
+        file_hash: Arc<OnceLock<String>>,
+        isa: Arc<OnceLock<Option<IsaReport>>>,
+        discovery: Arc<OnceLock<Discovery>>,
+        elf: ElfBytes<'a, AnyEndian>,
+        options: FormatOptions,
+    ) -> App<'a> {
         let metadata = std::fs::metadata(path).expect("Failed to get file metadata");
+        let diagnostics = Diagnostics::new();
+        elf::check_sections(&elf, &diagnostics);
 
         // Get compiler info from .comment section
         let compiler_info = elf
@@ -88,36 +139,120 @@ impl<'a> App<'a> {
             .and_then(|header| elf.section_data(&header).ok())
             .and_then(|(data, _)| String::from_utf8(data.to_vec()).ok());
 
-        let (sectab, secstr) = elf
-            .section_headers_with_strtab()
-            .expect("sections should parse");
+        let section_page: Box<dyn Page<'a> + 'a> = match elf.section_headers_with_strtab() {
+            Ok((Some(sectab), Some(secstr))) => Box::new(SectionPage::new(
+                &elf,
+                sectab,
+                secstr,
+                elf::section_table_location(&elf),
+                &diagnostics,
+            )),
+            Ok(_) => Box::new(EmptyPage::new(
+                "This ELF file does not contain a section header table",
+            )),
+            Err(e) => {
+                diagnostics.report(ElfError::BadTable {
+                    table: "section headers",
+                    offset: elf.ehdr.e_shoff,
+                    reason: e.to_string(),
+                });
+                Box::new(EmptyPage::new(format!(
+                    "The section header table could not be parsed: {e}"
+                )))
+            }
+        };
 
-        // Find lazy-parsing types for the common ELF sections (we want .dynsym, .dynstr, .hash)
-        let symtable = elf.symbol_table().expect("symtab should parse");
-        let symbol_page: Box<dyn Page + 'a> = if let Some((symtab, strtab)) = symtable {
-            Box::new(SymbolPage::new(symtab, strtab))
-        } else {
-            Box::new(EmptyPage::new())
+        let segment_page: Box<dyn Page<'a> + 'a> = match elf.segments() {
+            Some(segments) => Box::new(SegmentPage::new(&elf, segments)),
+            None => Box::new(EmptyPage::new(
+                "This ELF file does not contain a program header table (relocatable object)",
+            )),
         };
 
+        // Find lazy-parsing types for the common ELF sections (we want .dynsym, .dynstr, .hash)
+        let symtab_location = elf::table_location(&elf, ::elf::abi::SHT_SYMTAB);
+        let symtab = match elf.symbol_table() {
+            Ok(symtab) => symtab,
+            Err(e) => {
+                diagnostics.report(ElfError::BadTable {
+                    table: ".symtab",
+                    offset: symtab_location.offset,
+                    reason: e.to_string(),
+                });
+                None
+            }
+        };
         // Find the dynamic symbol table and string table
-        let dynsymtab = elf.dynamic_symbol_table().expect("dynsym should parse");
-        let (dysymtab, dystrtab) = dynsymtab.unwrap();
-
-        let rela_plt = elf.section_header_by_name(".rela.plt").expect("not found");
-        let rela = elf
-            .section_data_as_relas(&rela_plt.unwrap())
-            .expect("rela should parse");
+        let dynsym_location = elf::table_location(&elf, ::elf::abi::SHT_DYNSYM);
+        let dynsymtab = match elf.dynamic_symbol_table() {
+            Ok(dynsymtab) => dynsymtab,
+            Err(e) => {
+                diagnostics.report(ElfError::BadTable {
+                    table: ".dynsym",
+                    offset: dynsym_location.offset,
+                    reason: e.to_string(),
+                });
+                None
+            }
+        };
+        let dystrtab = dynsymtab.as_ref().map(|(_, dystrtab)| *dystrtab);
+
+        // .symtab 和 .dynsym 合并列出; 两者都没有时仍然可以整段反汇编可执行段
+        let symbol_page = SymbolPage::new(
+            &elf,
+            symtab,
+            symtab_location,
+            dynsymtab.clone(),
+            dynsym_location,
+            path,
+            discovery,
+            options,
+            &diagnostics,
+        );
+        let symbol_page: Box<dyn Page<'a> + 'a> = if symbol_page.content.is_empty() {
+            Box::new(EmptyPage::new(
+                "This ELF file contains neither a symbol table nor executable sections",
+            ))
+        } else {
+            Box::new(symbol_page)
+        };
 
-        let plt = elf
-            .section_header_by_name(".plt")
-            .expect("not found")
-            .unwrap();
+        let plt_relocs = elf::plt_relocations(&elf, &diagnostics);
+        let plt = elf.section_header_by_name(".plt").ok().flatten();
+        let plt_page: Box<dyn Page<'a> + 'a> = match (plt_relocs, plt, dynsymtab) {
+            (Some(relocs), Some(plt), Some((dysymtab, dystrtab))) => Box::new(PLTPage::new(
+                &elf,
+                relocs,
+                dysymtab,
+                dystrtab,
+                plt,
+                options,
+                &diagnostics,
+            )),
+            (_, _, None) => Box::new(EmptyPage::new(
+                "This ELF file does not contain a dynamic symbol table (statically linked or relocatable object)",
+            )),
+            _ => Box::new(EmptyPage::new(
+                "This ELF file does not contain a .plt with .rela.plt/.rel.plt (linked with -z now or -fno-plt)",
+            )),
+        };
 
         let dynamic = elf.dynamic().ok().flatten();
-        let elf_header = elf.ehdr.clone();
+        let elf_header = elf.ehdr;
         let interpreter = elf::get_interpreter(&elf);
 
+        let deps_page: Box<dyn Page<'a> + 'a> = match dynamic {
+            Some(dynamic) => Box::new(DependenciesPage::new(
+                Some(dynamic),
+                dystrtab,
+                interpreter.as_deref(),
+                path.to_str().unwrap_or(""),
+            )),
+            None => Box::new(EmptyPage::new(
+                "This ELF file does not contain a dynamic section (statically linked or relocatable object)",
+            )),
+        };
+
         App {
             should_quit: false,
             elf,
@@ -128,16 +263,14 @@ impl<'a> App<'a> {
                 elf_header,
                 compiler_info,
                 interpreter.clone(),
+                isa,
             ),
-            section_page: SectionPage::new(sectab.expect("not found"), secstr.expect("not found")),
+            section_page,
+            segment_page,
             symbol_page,
-            plt_page: PLTPage::new(rela, dysymtab, dystrtab, plt),
-            deps_page: DependenciesPage::new(
-                dynamic,
-                Some(dystrtab),
-                interpreter.as_deref(),
-                path.to_str().unwrap_or(""),
-            ),
+            plt_page,
+            deps_page,
+            diagnostics_page: DiagnosticsPage::new(diagnostics),
             selected_tab: AppTab::Summary,
         }
     }
@@ -153,7 +286,7 @@ impl<'a> App<'a> {
     fn handle_events(&mut self) -> Result<bool, io::Error> {
         if event::poll(std::time::Duration::from_millis(20))? {
             if let Event::Key(key) = event::read()? {
-                if key.kind == event::KeyEventKind::Press {
+                if key.kind == event::KeyEventKind::Press && !self.handle_page_key(key.code) {
                     match key.code {
                         KeyCode::Char('q') => return Ok(true),
                         KeyCode::Down => {
@@ -175,14 +308,20 @@ impl<'a> App<'a> {
                             self.selected_tab = AppTab::Sections;
                         }
                         KeyCode::Char('3') => {
-                            self.selected_tab = AppTab::Deassembly;
+                            self.selected_tab = AppTab::Segments;
                         }
                         KeyCode::Char('4') => {
-                            self.selected_tab = AppTab::PLT;
+                            self.selected_tab = AppTab::Deassembly;
                         }
                         KeyCode::Char('5') => {
+                            self.selected_tab = AppTab::PLT;
+                        }
+                        KeyCode::Char('6') => {
                             self.selected_tab = AppTab::Dependencies;
                         }
+                        KeyCode::Char('7') => {
+                            self.selected_tab = AppTab::Diagnostics;
+                        }
                         _ => {}
                     }
                 }
@@ -191,43 +330,63 @@ impl<'a> App<'a> {
         Ok(false)
     }
 
+    fn handle_page_key(&mut self, key: KeyCode) -> bool {
+        match self.selected_tab {
+            AppTab::Summary => false,
+            AppTab::Sections => self.section_page.handle_key(key, &self.elf),
+            AppTab::Segments => self.segment_page.handle_key(key, &self.elf),
+            AppTab::Deassembly => self.symbol_page.handle_key(key, &self.elf),
+            AppTab::PLT => self.plt_page.handle_key(key, &self.elf),
+            AppTab::Dependencies => self.deps_page.handle_key(key, &self.elf),
+            AppTab::Diagnostics => Page::handle_key(&mut self.diagnostics_page, key, &self.elf),
+        }
+    }
+
     fn select_next(&mut self) {
         match self.selected_tab {
             AppTab::Summary => {}
-            AppTab::Sections => self.section_page.state.select_next(),
+            AppTab::Sections => self.section_page.select_next(&self.elf),
+            AppTab::Segments => self.segment_page.select_next(&self.elf),
             AppTab::Deassembly => self.symbol_page.select_next(&self.elf),
             AppTab::PLT => self.plt_page.select_next(&self.elf),
-            AppTab::Dependencies => self.deps_page.state.select_next(),
+            AppTab::Dependencies => self.deps_page.select_next(&self.elf),
+            AppTab::Diagnostics => Page::select_next(&mut self.diagnostics_page, &self.elf),
         }
     }
 
     fn select_previous(&mut self) {
         match self.selected_tab {
             AppTab::Summary => {}
-            AppTab::Sections => self.section_page.state.select_previous(),
+            AppTab::Sections => self.section_page.select_previous(&self.elf),
+            AppTab::Segments => self.segment_page.select_previous(&self.elf),
             AppTab::Deassembly => self.symbol_page.select_previous(&self.elf),
             AppTab::PLT => self.plt_page.select_previous(&self.elf),
-            AppTab::Dependencies => self.deps_page.state.select_previous(),
+            AppTab::Dependencies => self.deps_page.select_previous(&self.elf),
+            AppTab::Diagnostics => Page::select_previous(&mut self.diagnostics_page, &self.elf),
         }
     }
 
     fn select_left(&mut self) {
         match self.selected_tab {
             AppTab::Summary => {}
-            AppTab::Sections => {}
+            AppTab::Sections => self.section_page.select_left(),
+            AppTab::Segments => self.segment_page.select_left(),
             AppTab::Deassembly => self.symbol_page.select_left(),
             AppTab::PLT => self.plt_page.select_left(),
-            AppTab::Dependencies => {}
+            AppTab::Dependencies => self.deps_page.select_left(),
+            AppTab::Diagnostics => Page::select_left(&mut self.diagnostics_page),
         }
     }
 
     fn select_right(&mut self) {
         match self.selected_tab {
             AppTab::Summary => {}
-            AppTab::Sections => {}
+            AppTab::Sections => self.section_page.select_right(),
+            AppTab::Segments => self.segment_page.select_right(),
             AppTab::Deassembly => self.symbol_page.select_right(),
             AppTab::PLT => self.plt_page.select_right(),
-            AppTab::Dependencies => {}
+            AppTab::Dependencies => self.deps_page.select_right(),
+            AppTab::Diagnostics => Page::select_right(&mut self.diagnostics_page),
         }
     }
 
@@ -246,10 +405,12 @@ impl<'a> App<'a> {
     fn render_pages(&mut self, area: Rect, buf: &mut Buffer) {
         match self.selected_tab {
             AppTab::Summary => (&self.summary_page).render(area, buf),
-            AppTab::Sections => (&mut self.section_page).render(area, buf),
-            AppTab::Deassembly => (&mut self.symbol_page).page_render(area, buf),
-            AppTab::PLT => (&mut self.plt_page).render(area, buf),
-            AppTab::Dependencies => (&mut self.deps_page).render(area, buf),
+            AppTab::Sections => self.section_page.page_render(area, buf),
+            AppTab::Segments => self.segment_page.page_render(area, buf),
+            AppTab::Deassembly => self.symbol_page.page_render(area, buf),
+            AppTab::PLT => self.plt_page.page_render(area, buf),
+            AppTab::Dependencies => self.deps_page.page_render(area, buf),
+            AppTab::Diagnostics => self.diagnostics_page.page_render(area, buf),
         }
     }
 }
@@ -268,7 +429,7 @@ impl Widget for &mut App<'_> {
         }
 
         fn render_footer(area: Rect, buf: &mut Buffer) {
-            Line::raw("1, 2, 3, 4 select tabs |  ◄ ► to move between components | Press q to quit")
+            Line::raw("1-7 select tabs |  ◄ ► to move between components | Press q to quit")
                 .centered()
                 .render(area, buf);
         }
@@ -289,40 +450,41 @@ impl AppTab {
             .into()
     }
 
-    /// A block surrounding the tab's content
-    fn block(self) -> Block<'static> {
-        Block::bordered()
-            .border_set(symbols::border::PROPORTIONAL_TALL)
-            .padding(Padding::horizontal(1))
-            .border_style(self.palette().c700)
-    }
-
     const fn palette(self) -> tailwind::Palette {
         match self {
             Self::Summary => tailwind::BLUE,
             Self::Sections => tailwind::EMERALD,
+            Self::Segments => tailwind::TEAL,
             Self::Deassembly => tailwind::INDIGO,
             Self::PLT => tailwind::AMBER,
             Self::Dependencies => tailwind::PURPLE,
+            Self::Diagnostics => tailwind::RED,
         }
     }
 }
 
 fn main() -> io::Result<()> {
     if env::var("RUST_LOG").is_ok() {
-    let _ = simple_logging::log_to_file("exeviewer.log", log::LevelFilter::Info);
+        let _ = simple_logging::log_to_file("exeviewer.log", log::LevelFilter::Info);
     }
 
     let args = Args::parse();
-    let (file_path, buffer) = utils::find_executable(&args.file)?;
-
-    let file_hash = {
-        let mut hasher = Sha256::new();
-        hasher.update(&buffer);
-        format!("{:X}", hasher.finalize())
+    let (file_path, mapping) = utils::find_executable(&args.file)?;
+    let file_hash = utils::spawn_file_hash(&file_path);
+
+    let elf = elf::parse(&mapping)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    let options = FormatOptions {
+        syntax: args.syntax,
+        hex_style: args.hex,
+        memory_size: args.memory_size,
+        mangled: args.mangled,
+        hide_hash: args.hide_rust_hash,
+        ..FormatOptions::default()
     };
-
-    let app = App::new(&file_path, file_hash, elf::parse(&buffer));
+    let discovery = discovery::spawn_discovery(&file_path);
+    let isa = isa::spawn_isa_scan(&file_path, discovery.clone(), options);
+    let app = App::new(&file_path, file_hash, isa, discovery, elf, options);
 
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
@@ -1,66 +1,90 @@
 use elf::{
-    endian::AnyEndian, parse::{ParsingIterator, ParsingTable}, relocation::Rela, section::SectionHeader, string_table::StringTable, symbol::SymbolTable, ElfBytes
+    abi, endian::AnyEndian, section::SectionHeader, string_table::StringTable, symbol::SymbolTable, ElfBytes
 };
+use ratatui::crossterm::event::KeyCode;
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Modifier, Style},
     text::Line,
-    widgets::{Block, List, ListDirection, ListState, Paragraph, StatefulWidget, Widget},
+    widgets::{Block, Paragraph, Widget},
 };
 
-use crate::elf::decompile_symbol;
+use crate::demangle::demangle_all;
+use crate::disasm::{has_syntaxes, FormatOptions};
+use crate::elf::{
+    decompile_symbol, listing_key, plt_entry_address, plt_layout, plt_symbol_name, reloc_type_name,
+    table_location, Lookup, PltRelocations,
+};
+use crate::empty::Page;
+use crate::error::Diagnostics;
+use crate::filter::FilteredList;
 
 pub struct PLTPage<'a> {
     pub content: Vec<PLTItem<'a>>,
-    pub list: List<'a>,
-    pub state: ListState,
+    pub list: FilteredList,
+    /// Mangled, as they are in `.dynstr`.
+    names: Vec<String>,
     active_on_content: bool,
+    plt: SectionHeader,
+    options: FormatOptions,
+    /// Only the x86 backend has more than one syntax.
+    has_syntaxes: bool,
+    /// Built the first time an entry is disassembled.
+    lookup: Option<Lookup<'a>>,
+    diagnostics: Diagnostics,
 }
 
 pub struct PLTItem<'a> {
     address: u64, // 该项真实在内存中的地址
     size: u64, // 大小
     decompiled: bool, // 是否已反编译
+    relocation: String, // 重定位类型
     data: Vec<Line<'a>>, // 反编译数据
 }
 
 impl<'a> PLTPage<'a> {
     pub fn new(
-        rela: ParsingIterator<'a, AnyEndian, Rela>,
+        elf: &ElfBytes<'a, AnyEndian>,
+        relocs: PltRelocations,
         sym_tab: SymbolTable<'a, AnyEndian>,
         str_tab: StringTable<'a>,
         plt: SectionHeader,
+        options: FormatOptions,
+        diagnostics: &Diagnostics,
     ) -> PLTPage<'a> {
-        let name_list: Vec<&str> = rela
-            .map(|s| {
-                let sym = sym_tab.get(s.r_sym as usize).unwrap();
-                str_tab.get(sym.st_name as usize).unwrap()
-            })
-            .collect();
-        
+        let sym_location = table_location(elf, abi::SHT_DYNSYM);
+        let (_, entry_size) = plt_layout(elf, &plt);
+        let mut name_list: Vec<String> = vec![];
         let mut content: Vec<PLTItem<'_>> = vec![];
-        for i in 0..name_list.len() {
+        for (i, reloc) in relocs.entries.iter().enumerate() {
+            // 地址溢出的项跳过
+            let Some(address) = plt_entry_address(elf, &plt, i) else {
+                continue;
+            };
+            name_list.push(plt_symbol_name(&sym_tab, &str_tab, reloc, sym_location, diagnostics));
             content.push(PLTItem {
-                address: plt.sh_addr + (i as u64 + 1) * plt.sh_entsize,
-                size: plt.sh_entsize,
+                address,
+                size: entry_size,
                 decompiled: false,
+                relocation: format!(
+                    "{} ({})",
+                    reloc_type_name(elf.ehdr.e_machine, reloc.r_type),
+                    relocs.section
+                ),
                 data: vec![],
             });
         }
 
-        let list = List::new(name_list)
-            .block(Block::bordered().title("Dynamic Symbols"))
-            .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
-            .highlight_symbol(">> ")
-            .repeat_highlight_symbol(true)
-            .direction(ListDirection::TopToBottom);
-        
         PLTPage {
             content,
-            list,
-            state: ListState::default(),
+            list: FilteredList::new("Dynamic Symbols", ">> ", demangle_all(&name_list, options)),
+            names: name_list,
             active_on_content: false,
+            plt,
+            options,
+            has_syntaxes: has_syntaxes(elf.ehdr.e_machine),
+            lookup: None,
+            diagnostics: diagnostics.clone(),
         }
     }
 
@@ -68,34 +92,82 @@ impl<'a> PLTPage<'a> {
         if idx >= self.content.len() {
             return;
         }
-        let symbol = &self.content[idx];
-        if !symbol.decompiled {
-            let decompiled: Vec<Line<'a>> =
-                decompile_symbol(elf, symbol.address, symbol.size as usize, ".plt");
+        if !self.content[idx].decompiled {
+            let lookup = self
+                .lookup
+                .get_or_insert_with(|| Lookup::new(elf, self.options, &self.diagnostics));
+            let symbol = &self.content[idx];
+            let decompiled: Vec<Line<'a>> = decompile_symbol(
+                elf,
+                symbol.address,
+                symbol.size as usize,
+                &self.plt,
+                ".plt",
+                false,
+                self.options,
+                lookup,
+                &self.diagnostics,
+            )
+            .into_lines();
             self.content[idx].data = decompiled;
             self.content[idx].decompiled = true;
         }
     }
+}
 
-    pub fn select_next(&mut self, elf_file: &ElfBytes<'a, AnyEndian>) {
-        self.state.select_next();
-        let idx: usize = self.state.selected().unwrap();
-        self.load_symbol(elf_file, idx);
+impl<'a> Page<'a> for PLTPage<'a> {
+    fn page_render(&mut self, area: Rect, buf: &mut Buffer) {
+        self.render(area, buf);
     }
 
-    pub fn select_previous(&mut self, elf_file: &ElfBytes<'a, AnyEndian>) {
-        self.state.select_previous();
-        let idx: usize = self.state.selected().unwrap();
-        self.load_symbol(elf_file, idx);
+    fn select_next(&mut self, elf_file: &ElfBytes<'a, AnyEndian>) {
+        self.list.select_next();
+        if let Some(idx) = self.list.selected() {
+            self.load_symbol(elf_file, idx);
+        }
     }
 
-    pub fn select_left(&mut self) {
+    fn select_previous(&mut self, elf_file: &ElfBytes<'a, AnyEndian>) {
+        self.list.select_previous();
+        if let Some(idx) = self.list.selected() {
+            self.load_symbol(elf_file, idx);
+        }
+    }
+
+    fn select_left(&mut self) {
         self.active_on_content = false;
     }
 
-    pub fn select_right(&mut self) {
+    fn select_right(&mut self) {
         self.active_on_content = true;
     }
+
+    fn handle_key(&mut self, key: KeyCode, elf: &ElfBytes<'a, AnyEndian>) -> bool {
+        if self.list.handle_key(key) {
+            if let Some(idx) = self.list.selected() {
+                self.load_symbol(elf, idx);
+            }
+            return true;
+        }
+        let mangled = self.options.mangled;
+        if !listing_key(&mut self.options, key, self.has_syntaxes) {
+            return false;
+        }
+        if self.options.mangled != mangled {
+            self.list.set_names(demangle_all(&self.names, self.options));
+            if let Some(lookup) = &mut self.lookup {
+                lookup.rename(elf, self.options, &self.diagnostics);
+            }
+        }
+        // 格式变了, 已反汇编的项重新生成
+        for item in self.content.iter_mut() {
+            item.decompiled = false;
+        }
+        if let Some(idx) = self.list.selected() {
+            self.load_symbol(elf, idx);
+        }
+        true
+    }
 }
 
 impl Widget for &mut PLTPage<'_> {
@@ -105,15 +177,22 @@ impl Widget for &mut PLTPage<'_> {
             .constraints(vec![Constraint::Min(40), Constraint::Percentage(100)])
             .split(area);
 
-        StatefulWidget::render(&self.list, layout[0], buf, &mut self.state);
+        self.list.render(layout[0], buf);
 
-        let selected = self.state.selected();
-        if selected.is_none() {
-            Paragraph::new("Select a symbol to decompile")
-        } else {
-            Paragraph::new(self.content[selected.unwrap()].data.clone())
+        match self.list.selected().and_then(|idx| self.content.get(idx)) {
+            None => Paragraph::new("Select a symbol to decompile")
+                .block(Block::bordered().title("PLT Table")),
+            Some(item) => {
+                let mut block =
+                    Block::bordered().title(format!("PLT Table - {}", item.relocation));
+                let mut keys = String::from("b: bytes  o: offsets  d: demangle");
+                if self.has_syntaxes {
+                    keys.insert_str(0, &format!("s: {}  ", self.options.syntax.name()));
+                }
+                block = block.title_bottom(keys);
+                Paragraph::new(item.data.clone()).block(block)
+            }
         }
-        .block(Block::bordered().title("PLT Table"))
         .render(layout[1], buf);
     }
 }
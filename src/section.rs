@@ -1,53 +1,132 @@
-use elf::{endian::AnyEndian, parse::ParsingTable, string_table::StringTable};
+use std::collections::HashMap;
+
+use elf::{
+    abi, endian::AnyEndian, parse::ParsingTable, section::SectionHeader, segment::ProgramHeader,
+    string_table::StringTable, ElfBytes,
+};
 use ratatui::{
     buffer::Buffer,
+    crossterm::event::KeyCode,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Modifier, Style},
-    widgets::{Block, List, ListState, Paragraph, StatefulWidget, Widget},
+    style::{Modifier, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, List, Paragraph, StatefulWidget, Widget},
 };
 
+use crate::elf::{tls_symbol_address, INVALID_NAME, SHN_LORESERVE};
+use crate::empty::Page;
+use crate::error::{Diagnostics, ElfError, TableLocation};
+use crate::filter::FilteredList;
+use crate::hex::{hex_line, BYTES_PER_LINE};
+use crate::segment::{section_placement, segment_type_name, Placement};
+
 pub struct SectionPage<'a> {
-    pub content: Vec<Section>,
-    pub list: List<'a>,
-    pub state: ListState,
+    pub content: Vec<Section<'a>>,
+    pub list: FilteredList,
+    segment_types: Vec<String>,
+    show_mapping: bool, // 右侧显示节到段的映射矩阵
+    active_on_content: bool,
+    hex_scroll: usize, // 十六进制视图的首行
+    hex_height: usize, // 上次渲染时可见的行数, 用于翻页
+    address_width: usize,
 }
 
-pub struct Section {
+pub struct Section<'a> {
     offset: u64,
     size: u64,
+    address: Option<u64>, // 只有加载到内存的节才有虚拟地址
     description: String,
-    data: String,
+    placements: Vec<Placement>, // 相对每个程序段的位置
+    problem: Option<&'static str>,
+    nobits: bool,
+    data: Option<&'a [u8]>, // 读取失败时为 None
+    symbols: SectionSymbols,
+}
+
+/// Symbols defined in a section, as offsets from the section start.
+#[derive(Default)]
+struct SectionSymbols {
+    /// Non-overlapping `[start, end)` byte ranges, sorted, used for highlighting.
+    ranges: Vec<(u64, u64)>,
+    /// Every symbol start with its name, sorted, shown at the end of its line.
+    labels: Vec<(u64, String)>,
 }
 
 impl SectionPage<'_> {
     pub fn new<'a>(
+        elf: &ElfBytes<'a, AnyEndian>,
         sec_tab: ParsingTable<'a, AnyEndian, elf::section::SectionHeader>,
         str_tab: StringTable<'a>,
+        location: TableLocation,
+        diagnostics: &Diagnostics,
     ) -> SectionPage<'a> {
         let name_list: Vec<&str> = sec_tab
             .iter()
-            .map(|s| str_tab.get(s.sh_name as usize).unwrap())
+            .enumerate()
+            .map(|(i, s)| {
+                str_tab.get(s.sh_name as usize).unwrap_or_else(|_| {
+                    diagnostics.report(ElfError::BadStringIndex {
+                        table: ".shstrtab",
+                        index: s.sh_name as u64,
+                        offset: location.at(i).unwrap_or(location.offset),
+                    });
+                    INVALID_NAME
+                })
+            })
+            .collect();
+        let segments: Vec<ProgramHeader> = elf
+            .segments()
+            .map(|segments| segments.iter().collect())
+            .unwrap_or_default();
+        let segment_types = segments
+            .iter()
+            .map(|ph| segment_type_name(elf.ehdr.e_machine, ph.p_type))
             .collect();
-        let list = List::new(name_list)
-            .block(Block::bordered().title("Sections"))
-            .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
-            .highlight_symbol(">>")
-            .repeat_highlight_symbol(true);
 
+        let mut symbols = collect_section_symbols(elf);
         let content = sec_tab
             .iter()
-            .map(|s| Section {
-                offset: s.sh_offset,
-                size: s.sh_size,
-                description: getDescription(str_tab.get(s.sh_name as usize).unwrap()),
-                data: String::new(),
+            .zip(name_list.iter())
+            .enumerate()
+            .map(|(i, (s, name))| {
+                let placements: Vec<Placement> = segments
+                    .iter()
+                    .map(|ph| section_placement(ph, &s))
+                    .collect();
+                let nobits = s.sh_type == abi::SHT_NOBITS;
+                // 截断的节已经由 check_sections 报告过了
+                let data = if nobits {
+                    Some(&[][..])
+                } else {
+                    elf.section_data(&s).ok().map(|(data, _)| data)
+                };
+                Section {
+                    offset: s.sh_offset,
+                    size: s.sh_size,
+                    address: (s.sh_flags & abi::SHF_ALLOC as u64 != 0).then_some(s.sh_addr),
+                    description: get_description(name),
+                    problem: segment_problem(&s, &placements, &segments),
+                    placements,
+                    nobits,
+                    data,
+                    symbols: section_symbols(symbols.remove(&i).unwrap_or_default(), &s),
+                }
             })
             .collect();
 
+        let names = name_list.iter().map(|name| name.to_string()).collect();
         SectionPage {
             content,
-            list,
-            state: ListState::default(),
+            list: FilteredList::new("Sections", ">>", names),
+            segment_types,
+            show_mapping: false,
+            active_on_content: false,
+            hex_scroll: 0,
+            hex_height: 0,
+            address_width: match elf.ehdr.class {
+                elf::file::Class::ELF32 => 8,
+                elf::file::Class::ELF64 => 16,
+            },
         }
     }
 }
@@ -59,71 +138,424 @@ impl Widget for &mut SectionPage<'_> {
             .constraints(vec![Constraint::Min(30), Constraint::Percentage(100)])
             .split(area);
 
-        StatefulWidget::render(&self.list, layout[0], buf, &mut self.state);
-        let selected = self.state.selected();
+        self.list.render(layout[0], buf);
+        if self.show_mapping {
+            self.render_mapping(layout[1], buf);
+            return;
+        }
+        let Some(section) = self.list.selected().and_then(|idx| self.content.get(idx)) else {
+            let message = match self.list.selected() {
+                None => "Select a section to show its details",
+                Some(_) => "Section not found",
+            };
+            Paragraph::new(message)
+                .block(Block::bordered().title("Section Summary"))
+                .render(layout[1], buf);
+            return;
+        };
+        let idx = self.list.selected().unwrap_or_default();
+
+        let ranges: Vec<(u64, u64)> = self
+            .content
+            .iter()
+            .map(|s| (s.offset, s.offset.saturating_add(s.size)))
+            .collect();
+        let visualization = generate_layout_visualization(&ranges, idx, 50, 3);
+        let mut segments: Vec<String> = section
+            .placements
+            .iter()
+            .enumerate()
+            .filter(|(_, &placement)| placement != Placement::Outside)
+            .map(|(i, &placement)| match placement {
+                Placement::Partial => {
+                    format!("{}[{:02}] (partly)", self.segment_types[i], i)
+                }
+                _ => format!("{}[{:02}]", self.segment_types[i], i),
+            })
+            .collect();
+        if segments.is_empty() {
+            segments.push(String::from("(none)"));
+        }
+        let warning = match section.problem {
+            Some(problem) => format!("\x20       Warning:  section is {}\n\n", problem),
+            None => String::new(),
+        };
+        let summary = format!(
+            "\n\
+            \x20       Description:  {}\n\n\
+            \x20       Size:  {}\n\n\
+            \x20       Range:  [ {:016X} - {:016X} ]\n\n\
+            \x20       Segments:  {}\n\n\
+            {}\
+            \x20       Layout:\n{}\n",
+            section.description,
+            section.size,
+            section.offset,
+            section.offset.saturating_add(section.size),
+            segments.join(" "),
+            warning,
+            visualization
+        );
+
+        let [summary_area, hex_area] = Layout::vertical([
+            Constraint::Length(summary.lines().count() as u16 + 2),
+            Constraint::Min(0),
+        ])
+        .areas(layout[1]);
+        Paragraph::new(summary)
+            .block(Block::bordered().title("Section Summary"))
+            .render(summary_area, buf);
+        self.render_hex(idx, hex_area, buf);
+    }
+}
 
-        let paragraph = Paragraph::new(if selected.is_none() {
-            String::from("Select a section to show its details")
+impl SectionPage<'_> {
+    /// Scrollable hex + ASCII dump of the section, with the symbols inside it highlighted.
+    fn render_hex(&mut self, idx: usize, area: Rect, buf: &mut Buffer) {
+        let section = &self.content[idx];
+        let block = Block::bordered().title(if self.active_on_content {
+            "Contents (↑↓ scroll, PgUp/PgDn page, m mapping)"
         } else {
-            if selected.unwrap() >= self.content.len() {
-                String::from("Section not found")
-            } else {
-                let section = &self.content[selected.unwrap()];
-                let visualization = generate_section_visualization(&self.content, selected.unwrap(), 50, 3);
-                format!(
-                    "\n\n\
-                    \x20       Description:  {}\n\n\
-                    \x20       Size:  {}\n\n\
-                    \x20       Range:  [ {:016X} - {:016X} ]\n\n\
-                    \x20       Layout:\n{}\n",
-                    section.description,
-                    section.size,
-                    section.offset,
-                    section.offset + section.size,
-                    visualization
-                )
+            "Contents (► to scroll, m mapping)"
+        });
+        let data = match section.data {
+            _ if section.nobits => {
+                return Paragraph::new("This section occupies no space in the file")
+                    .block(block)
+                    .render(area, buf);
             }
-        })
-        .block(Block::bordered().title("Section Summary"));
+            None => {
+                return Paragraph::new("The contents of this section could not be read")
+                    .block(block)
+                    .render(area, buf);
+            }
+            Some(data) => data,
+        };
+
+        // 只生成可见的行, 大的节也不会拖慢渲染
+        let visible = area.height.saturating_sub(3) as usize;
+        let total = data.len().div_ceil(BYTES_PER_LINE);
+        self.hex_height = visible;
+        self.hex_scroll = self.hex_scroll.min(total.saturating_sub(visible));
+
+        let width = self.address_width;
+        let mut lines = vec![Line::from(format!(
+            "{:<10}{:<w$}  {:<48} {}",
+            "Offset",
+            "Address",
+            "Hex",
+            "ASCII",
+            w = width
+        ))
+        .bold()];
+        let symbols = &section.symbols;
+        let mut rows = data
+            .chunks(BYTES_PER_LINE)
+            .enumerate()
+            .skip(self.hex_scroll);
+        for (row, chunk) in rows.by_ref().take(visible) {
+            let start = (row * BYTES_PER_LINE) as u64;
+            let address = match section.address {
+                Some(address) => format!("{:0w$X}", address.wrapping_add(start), w = width),
+                None => format!("{:<w$}", "-", w = width),
+            };
+            let columns = format!("{:08X}  {}  ", section.offset.wrapping_add(start), address);
+            let mut line = hex_line(columns, chunk, |i| symbols.style_at(start + i as u64));
+
+            // 在行尾列出从本行开始的符号
+            let names: Vec<&str> = symbols.labels_in(start, start + BYTES_PER_LINE as u64);
+            if !names.is_empty() {
+                line.push_span(Span::raw(format!("  {}", names.join(", "))).magenta());
+            }
+            lines.push(line);
+        }
+
+        Paragraph::new(lines)
+            .block(block.title_bottom(format!(
+                "{} bytes, line {}/{}",
+                data.len(),
+                (self.hex_scroll + 1).min(total),
+                total
+            )))
+            .render(area, buf);
+    }
+}
+
+impl SectionSymbols {
+    fn style_at(&self, offset: u64) -> Style {
+        let idx = self.ranges.partition_point(|&(start, _)| start <= offset);
+        match idx.checked_sub(1).map(|i| (i, self.ranges[i])) {
+            // 相邻的符号交替使用两种颜色
+            Some((i, (_, end))) if offset < end && i % 2 == 0 => Style::new().cyan(),
+            Some((_, (_, end))) if offset < end => Style::new().yellow(),
+            _ => Style::default(),
+        }
+    }
 
-        paragraph.render(layout[1], buf);
+    fn labels_in(&self, start: u64, end: u64) -> Vec<&str> {
+        let first = self.labels.partition_point(|&(offset, _)| offset < start);
+        self.labels[first..]
+            .iter()
+            .take_while(|&&(offset, _)| offset < end)
+            .map(|(_, name)| name.as_str())
+            .collect()
     }
 }
 
-fn generate_section_visualization(sections: &[Section], selected_idx: usize, width: usize, height: usize) -> String {
+impl SectionPage<'_> {
+    /// Matrix like the mapping printed by `readelf -l`: one row per section,
+    /// one column per segment, scrolled together with the section list.
+    fn render_mapping(&self, area: Rect, buf: &mut Buffer) {
+        if self.segment_types.is_empty() {
+            Paragraph::new("This ELF file does not contain a program header table")
+                .block(Block::bordered().title("Section to Segment Mapping"))
+                .render(area, buf);
+            return;
+        }
+
+        // 列标题放在上边框里, 与列表项对齐 (留出高亮符号的宽度)
+        let header: String = self
+            .segment_types
+            .iter()
+            .map(|name| format!("{:^5}", column_label(name)))
+            .collect();
+        let rows: Vec<Line> = self
+            .list
+            .rows()
+            .iter()
+            .map(|&i| {
+                let section = &self.content[i];
+                let mut spans: Vec<Span> = section
+                    .placements
+                    .iter()
+                    .map(|placement| match placement {
+                        Placement::Inside => Span::raw("  ■  ").green(),
+                        Placement::Partial => Span::raw("  ◧  ").red(),
+                        Placement::Outside => Span::raw("  ·  "),
+                    })
+                    .collect();
+                if let Some(problem) = section.problem {
+                    spans.push(Span::raw(format!(" {}", problem)).red());
+                }
+                Line::from(spans)
+            })
+            .collect();
+
+        let list = List::new(rows)
+            .block(
+                Block::bordered()
+                    .title(format!("──{}", header))
+                    .title_bottom(
+                        "Section to Segment Mapping:  ■ inside  ◧ partly inside  · outside",
+                    ),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
+            .highlight_symbol(">>")
+            .repeat_highlight_symbol(true);
+        // 与左侧列表共用选中项和滚动位置
+        let mut state = self.list.state.clone();
+        StatefulWidget::render(list, area, buf, &mut state);
+    }
+}
+
+impl<'a> Page<'a> for SectionPage<'a> {
+    fn page_render(&mut self, area: Rect, buf: &mut Buffer) {
+        self.render(area, buf);
+    }
+
+    fn select_next(&mut self, _elf: &ElfBytes<'a, AnyEndian>) {
+        if self.active_on_content && !self.show_mapping {
+            self.hex_scroll = self.hex_scroll.saturating_add(1);
+        } else {
+            self.list.select_next();
+            self.hex_scroll = 0;
+        }
+    }
+
+    fn select_previous(&mut self, _elf: &ElfBytes<'a, AnyEndian>) {
+        if self.active_on_content && !self.show_mapping {
+            self.hex_scroll = self.hex_scroll.saturating_sub(1);
+        } else {
+            self.list.select_previous();
+            self.hex_scroll = 0;
+        }
+    }
+
+    fn select_left(&mut self) {
+        self.active_on_content = false;
+    }
+
+    fn select_right(&mut self) {
+        self.active_on_content = true;
+    }
+
+    fn handle_key(&mut self, key: KeyCode, _elf: &ElfBytes<'a, AnyEndian>) -> bool {
+        let selected = self.list.selected();
+        if self.list.handle_key(key) {
+            if self.list.selected() != selected {
+                self.hex_scroll = 0;
+            }
+            return true;
+        }
+        match key {
+            KeyCode::Char('m') => self.show_mapping = !self.show_mapping,
+            KeyCode::PageDown => {
+                self.hex_scroll = self.hex_scroll.saturating_add(self.hex_height.max(1))
+            }
+            KeyCode::PageUp => {
+                self.hex_scroll = self.hex_scroll.saturating_sub(self.hex_height.max(1))
+            }
+            KeyCode::Home => self.hex_scroll = 0,
+            // 渲染时会被限制到最后一页
+            KeyCode::End => self.hex_scroll = usize::MAX,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Text bar showing where the selected `[start, end)` range lies among all of them.
+pub fn generate_layout_visualization(
+    ranges: &[(u64, u64)],
+    selected_idx: usize,
+    width: usize,
+    height: usize,
+) -> String {
     let total_len = width * height;
     let mut visualization = vec!['.'; total_len];
-    
-    if let Some(max_offset) = sections.iter().map(|s| s.offset + s.size).max() {
+
+    if let Some(max_offset) = ranges
+        .iter()
+        .map(|&(_, end)| end)
+        .max()
+        .filter(|&max| max > 0)
+    {
         // 计算选中段在总长度中的起止位置
-        let section = &sections[selected_idx];
-        let start_pos = ((section.offset as f64 / max_offset as f64) * total_len as f64) as usize;
-        let mut end_pos = (((section.offset + section.size) as f64 / max_offset as f64) * total_len as f64) as usize;
-        
+        let (start, end) = ranges[selected_idx];
+        let start_pos =
+            (((start as f64 / max_offset as f64) * total_len as f64) as usize).min(total_len - 1);
+        let mut end_pos = ((end as f64 / max_offset as f64) * total_len as f64) as usize;
+
         // 确保小段至少显示一个字符
         if end_pos <= start_pos {
             end_pos = start_pos + 1;
         }
         end_pos = end_pos.min(total_len);
-        
+
         // 标记区间
-        for i in start_pos..end_pos {
-            visualization[i] = '*';
+        for cell in &mut visualization[start_pos..end_pos] {
+            *cell = '*';
         }
     }
-    
+
     // 按照指定宽度分行输出
     (0..height)
         .map(|row| {
             let start = row * width;
             let end = start + width;
-            format!("\x20       {}", visualization[start..end].iter().collect::<String>())
+            format!(
+                "\x20       {}",
+                visualization[start..end].iter().collect::<String>()
+            )
         })
         .collect::<Vec<_>>()
         .join("\n")
 }
 
-fn getDescription(name: &str) -> String {
+/// Problem with where the section was placed, if any.
+///
+/// Non-allocated sections (`.symtab`, `.comment`, ...) are never loaded, so
+/// only allocated ones are expected to be inside a segment.
+fn segment_problem(
+    shdr: &SectionHeader,
+    placements: &[Placement],
+    segments: &[ProgramHeader],
+) -> Option<&'static str> {
+    let alloc = shdr.sh_flags & abi::SHF_ALLOC as u64 != 0;
+    // GNU ld 有意让 PT_GNU_RELRO 只覆盖 .got.plt 开头保留的 3 项
+    let partial = placements
+        .iter()
+        .zip(segments)
+        .any(|(&placement, ph)| placement == Placement::Partial && ph.p_type != abi::PT_GNU_RELRO);
+    if partial {
+        Some("partly outside a segment")
+    } else if alloc && !placements.is_empty() && !placements.contains(&Placement::Inside) {
+        Some("in no segment")
+    } else {
+        None
+    }
+}
+
+/// Defined symbols of `.symtab` and `.dynsym`, grouped by section index.
+fn collect_section_symbols(
+    elf: &ElfBytes<'_, AnyEndian>,
+) -> HashMap<usize, Vec<(u64, u64, String)>> {
+    let mut symbols: HashMap<usize, Vec<(u64, u64, String)>> = HashMap::new();
+    let tables = [elf.symbol_table(), elf.dynamic_symbol_table()];
+    // 坏掉的符号表已经在创建其他页面时报告过了
+    for (symtab, strtab) in tables.into_iter().flat_map(|table| table.ok().flatten()) {
+        for sym in symtab.iter() {
+            if sym.is_undefined()
+                || sym.st_shndx >= SHN_LORESERVE
+                || matches!(sym.st_symtype(), abi::STT_SECTION | abi::STT_FILE)
+            {
+                continue;
+            }
+            let Ok(name) = strtab.get(sym.st_name as usize) else {
+                continue;
+            };
+            if name.is_empty() {
+                continue;
+            }
+            let address = match sym.st_symtype() {
+                abi::STT_TLS => tls_symbol_address(elf, sym.st_value),
+                _ => sym.st_value,
+            };
+            symbols.entry(sym.st_shndx as usize).or_default().push((
+                address,
+                sym.st_size,
+                name.to_string(),
+            ));
+        }
+    }
+    symbols
+}
+
+/// Highlight ranges and labels for the symbols of one section.
+fn section_symbols(symbols: Vec<(u64, u64, String)>, shdr: &SectionHeader) -> SectionSymbols {
+    // 地址换算为节内偏移, 可重定位文件的 st_value 本身就是偏移
+    let mut symbols: Vec<(u64, u64, String)> = symbols
+        .into_iter()
+        .filter_map(|(address, size, name)| {
+            let offset = address.checked_sub(shdr.sh_addr)?;
+            (offset <= shdr.sh_size).then_some((offset, size, name))
+        })
+        .collect();
+    symbols.sort();
+    symbols.dedup();
+    let mut result = SectionSymbols::default();
+    for (start, size, name) in symbols {
+        if result
+            .labels
+            .last()
+            .is_none_or(|(offset, last)| (*offset, last) != (start, &name))
+        {
+            result.labels.push((start, name));
+        }
+        // 重叠的符号 (别名等) 只高亮第一个
+        if size > 0 && result.ranges.last().is_none_or(|&(_, end)| start >= end) {
+            result.ranges.push((start, start.saturating_add(size)));
+        }
+    }
+    result
+}
+
+/// Four-letter column label for a segment type, e.g. `RELR` for `GNU_RELRO`.
+fn column_label(name: &str) -> String {
+    name.trim_start_matches("GNU_").chars().take(4).collect()
+}
+
+fn get_description(name: &str) -> String {
     match name {
         ".text" => "Executable code".to_string(),
         ".rodata" => "Read-only data".to_string(),
@@ -0,0 +1,368 @@
+use elf::{
+    abi, endian::AnyEndian, section::SectionHeader, segment::ProgramHeader, segment::SegmentTable,
+    ElfBytes,
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    widgets::{Block, HighlightSpacing, List, ListState, Paragraph, StatefulWidget, Widget, Wrap},
+};
+
+use crate::elf::INVALID_NAME;
+use crate::empty::Page;
+use crate::section::generate_layout_visualization;
+
+pub struct SegmentPage<'a> {
+    pub content: Vec<Segment>,
+    pub list: List<'a>,
+    pub state: ListState,
+}
+
+pub struct Segment {
+    p_type: String,
+    flags: String,
+    offset: u64,
+    filesz: u64,
+    vaddr: u64,
+    memsz: u64,
+    sections: Vec<String>, // 该段包含的节
+}
+
+impl SegmentPage<'_> {
+    pub fn new<'a>(
+        elf: &ElfBytes<'a, AnyEndian>,
+        segments: SegmentTable<'a, AnyEndian>,
+    ) -> SegmentPage<'a> {
+        // 节名只用于展示, 无效的名字已经由 Sections 页报告过了
+        let sections: Vec<(SectionHeader, &str)> = match elf.section_headers_with_strtab() {
+            Ok((Some(shdrs), strtab)) => shdrs
+                .iter()
+                .map(|shdr| {
+                    let name = strtab
+                        .and_then(|strtab| strtab.get(shdr.sh_name as usize).ok())
+                        .unwrap_or(INVALID_NAME);
+                    (shdr, name)
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        let content: Vec<Segment> = segments
+            .iter()
+            .map(|ph| Segment {
+                p_type: segment_type_name(elf.ehdr.e_machine, ph.p_type),
+                flags: segment_flags(ph.p_flags),
+                offset: ph.p_offset,
+                filesz: ph.p_filesz,
+                vaddr: ph.p_vaddr,
+                memsz: ph.p_memsz,
+                sections: sections
+                    .iter()
+                    .filter(|(shdr, _)| section_in_segment(&ph, shdr))
+                    .map(|(_, name)| name.to_string())
+                    .collect(),
+            })
+            .collect();
+
+        let rows: Vec<String> = segments
+            .iter()
+            .zip(content.iter())
+            .map(|(ph, segment)| {
+                format!(
+                    "{:<15} {:<5}  0x{:016x}  0x{:016x}  0x{:016x}  0x{:010x}  0x{:010x}  0x{:x}",
+                    segment.p_type,
+                    segment.flags,
+                    ph.p_offset,
+                    ph.p_vaddr,
+                    ph.p_paddr,
+                    ph.p_filesz,
+                    ph.p_memsz,
+                    ph.p_align
+                )
+            })
+            .collect();
+
+        let list = List::new(rows)
+            .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
+            .highlight_symbol(">> ")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        SegmentPage {
+            content,
+            list,
+            state: ListState::default(),
+        }
+    }
+}
+
+impl Widget for &mut SegmentPage<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+
+        // 表头与列表项对齐, 前面留出高亮符号的宽度
+        let block = Block::bordered().title("Program Headers");
+        let inner = block.inner(layout[0]);
+        block.render(layout[0], buf);
+        let [header_area, list_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(inner);
+        let header = format!(
+            "   {:<15} {:<5}  {:<18}  {:<18}  {:<18}  {:<12}  {:<12}  {}",
+            "Type", "Flags", "Offset", "VirtAddr", "PhysAddr", "FileSiz", "MemSiz", "Align"
+        );
+        Paragraph::new(header)
+            .style(Style::default().add_modifier(Modifier::BOLD))
+            .render(header_area, buf);
+        StatefulWidget::render(&self.list, list_area, buf, &mut self.state);
+
+        let paragraph = Paragraph::new(match self.state.selected() {
+            None => String::from("Select a segment to show its details"),
+            Some(idx) if idx >= self.content.len() => String::from("Segment not found"),
+            Some(idx) => {
+                let segment = &self.content[idx];
+                let ranges: Vec<(u64, u64)> = self
+                    .content
+                    .iter()
+                    .map(|s| (s.offset, s.offset.saturating_add(s.filesz)))
+                    .collect();
+                let visualization = generate_layout_visualization(&ranges, idx, 50, 3);
+                let sections = if segment.sections.is_empty() {
+                    String::from("(none)")
+                } else {
+                    segment.sections.join(" ")
+                };
+                format!(
+                    "\n\
+                    \x20       Type:  {} ({})\n\n\
+                    \x20       File:  [ {:016X} - {:016X} ]\n\n\
+                    \x20       Memory:  [ {:016X} - {:016X} ]\n\n\
+                    \x20       Sections:  {}\n\n\
+                    \x20       Layout:\n{}\n",
+                    segment.p_type,
+                    segment.flags,
+                    segment.offset,
+                    segment.offset.saturating_add(segment.filesz),
+                    segment.vaddr,
+                    segment.vaddr.saturating_add(segment.memsz),
+                    sections,
+                    visualization
+                )
+            }
+        })
+        .wrap(Wrap { trim: false })
+        .block(Block::bordered().title("Segment Summary"));
+
+        paragraph.render(layout[1], buf);
+    }
+}
+
+impl<'a> Page<'a> for SegmentPage<'a> {
+    fn page_render(&mut self, area: Rect, buf: &mut Buffer) {
+        self.render(area, buf);
+    }
+
+    fn select_next(&mut self, _elf: &ElfBytes<'a, AnyEndian>) {
+        self.state.select_next();
+    }
+
+    fn select_previous(&mut self, _elf: &ElfBytes<'a, AnyEndian>) {
+        self.state.select_previous();
+    }
+
+    fn select_left(&mut self) {}
+
+    fn select_right(&mut self) {}
+}
+
+/// Name of the segment type, as printed by readelf.
+pub fn segment_type_name(e_machine: u16, p_type: u32) -> String {
+    let name = match (e_machine, p_type) {
+        (_, abi::PT_NULL) => "NULL",
+        (_, abi::PT_LOAD) => "LOAD",
+        (_, abi::PT_DYNAMIC) => "DYNAMIC",
+        (_, abi::PT_INTERP) => "INTERP",
+        (_, abi::PT_NOTE) => "NOTE",
+        (_, abi::PT_SHLIB) => "SHLIB",
+        (_, abi::PT_PHDR) => "PHDR",
+        (_, abi::PT_TLS) => "TLS",
+        (_, abi::PT_GNU_EH_FRAME) => "GNU_EH_FRAME",
+        (_, abi::PT_GNU_STACK) => "GNU_STACK",
+        (_, abi::PT_GNU_RELRO) => "GNU_RELRO",
+        (_, abi::PT_GNU_PROPERTY) => "GNU_PROPERTY",
+        (abi::EM_ARM, abi::PT_ARM_EXIDX) => "ARM_EXIDX",
+        (abi::EM_AARCH64, abi::PT_AARCH64_MEMTAG_MTE) => "AARCH64_MEMTAG_MTE",
+        (abi::EM_RISCV, abi::PT_RISCV_ATTRIBUTES) => "RISCV_ATTRIBUTES",
+        _ => return format!("0x{:x}", p_type),
+    };
+    name.to_string()
+}
+
+fn segment_flags(p_flags: u32) -> String {
+    [(abi::PF_R, 'R'), (abi::PF_W, 'W'), (abi::PF_X, 'X')]
+        .iter()
+        .map(|&(flag, c)| if p_flags & flag != 0 { c } else { '-' })
+        .collect()
+}
+
+/// How much of a section a segment covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Inside,
+    Partial,
+    Outside,
+}
+
+/// Where the section lies relative to the segment, following readelf's rules:
+/// `.tbss` only belongs to `PT_TLS`, and `PT_PHDR` holds no sections.
+pub fn section_placement(ph: &ProgramHeader, shdr: &SectionHeader) -> Placement {
+    let tls = shdr.sh_flags & abi::SHF_TLS as u64 != 0;
+    let nobits = shdr.sh_type == abi::SHT_NOBITS;
+    let alloc = shdr.sh_flags & abi::SHF_ALLOC as u64 != 0;
+    let type_matches = match ph.p_type {
+        abi::PT_TLS => tls,
+        abi::PT_LOAD | abi::PT_GNU_RELRO => !(tls && nobits),
+        abi::PT_PHDR => false,
+        _ => !tls,
+    };
+    if !type_matches || shdr.sh_type == abi::SHT_NULL {
+        return Placement::Outside;
+    }
+
+    let in_file = nobits || within(shdr.sh_offset, shdr.sh_size, ph.p_offset, ph.p_filesz);
+    let in_memory = !alloc || within(shdr.sh_addr, shdr.sh_size, ph.p_vaddr, ph.p_memsz);
+    if in_file && in_memory {
+        return Placement::Inside;
+    }
+
+    // 与段有交集却没有被完整包含, 通常是链接脚本的问题
+    let overlaps_file = !nobits && overlaps(shdr.sh_offset, shdr.sh_size, ph.p_offset, ph.p_filesz);
+    let overlaps_memory = alloc && overlaps(shdr.sh_addr, shdr.sh_size, ph.p_vaddr, ph.p_memsz);
+    if overlaps_file || overlaps_memory {
+        Placement::Partial
+    } else {
+        Placement::Outside
+    }
+}
+
+pub fn section_in_segment(ph: &ProgramHeader, shdr: &SectionHeader) -> bool {
+    section_placement(ph, shdr) == Placement::Inside
+}
+
+/// Whether `[start, start + size)` lies inside `[outer, outer + outer_size)`;
+/// an empty range counts when it starts inside, or at the start of an empty segment.
+fn within(start: u64, size: u64, outer: u64, outer_size: u64) -> bool {
+    let Some(delta) = start.checked_sub(outer) else {
+        return false;
+    };
+    match size {
+        0 => delta < outer_size || (delta == 0 && outer_size == 0),
+        _ => delta < outer_size && size <= outer_size - delta,
+    }
+}
+
+/// Whether two non-empty ranges share at least one byte.
+fn overlaps(start: u64, size: u64, outer: u64, outer_size: u64) -> bool {
+    size > 0
+        && outer_size > 0
+        && start < outer.saturating_add(outer_size)
+        && outer < start.saturating_add(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Placement::*;
+
+    fn segment(p_type: u32, offset: u64, vaddr: u64, filesz: u64, memsz: u64) -> ProgramHeader {
+        ProgramHeader {
+            p_type,
+            p_offset: offset,
+            p_vaddr: vaddr,
+            p_paddr: vaddr,
+            p_filesz: filesz,
+            p_memsz: memsz,
+            p_flags: abi::PF_R | abi::PF_W,
+            p_align: 0x1000,
+        }
+    }
+
+    fn section(sh_type: u32, flags: u32, offset: u64, addr: u64, size: u64) -> SectionHeader {
+        SectionHeader {
+            sh_name: 0,
+            sh_type,
+            sh_flags: (abi::SHF_ALLOC | abi::SHF_WRITE | flags) as u64,
+            sh_addr: addr,
+            sh_offset: offset,
+            sh_size: size,
+            sh_link: 0,
+            sh_info: 0,
+            sh_addralign: 8,
+            sh_entsize: 0,
+        }
+    }
+
+    /// 文件中 0x200 字节, 内存中 0x400 字节
+    fn load() -> ProgramHeader {
+        segment(abi::PT_LOAD, 0x1000, 0x40_1000, 0x200, 0x400)
+    }
+
+    #[test]
+    fn nobits_past_the_file_size() {
+        let data = section(abi::SHT_PROGBITS, 0, 0x1000, 0x40_1000, 0x200);
+        assert_eq!(section_placement(&load(), &data), Inside);
+        // .bss 的文件偏移在 p_filesz 之后, 只看内存
+        let bss = section(abi::SHT_NOBITS, 0, 0x1200, 0x40_1200, 0x200);
+        assert_eq!(section_placement(&load(), &bss), Inside);
+        let too_big = section(abi::SHT_NOBITS, 0, 0x1200, 0x40_1200, 0x300);
+        assert_eq!(section_placement(&load(), &too_big), Partial);
+        let past = section(abi::SHT_NOBITS, 0, 0x1200, 0x40_1400, 0x100);
+        assert_eq!(section_placement(&load(), &past), Outside);
+        // 有内容的节不能超出 p_filesz
+        let progbits = section(abi::SHT_PROGBITS, 0, 0x1100, 0x40_1100, 0x200);
+        assert_eq!(section_placement(&load(), &progbits), Partial);
+    }
+
+    #[test]
+    fn empty_sections_at_the_boundaries() {
+        let at_start = section(abi::SHT_PROGBITS, 0, 0x1000, 0x40_1000, 0);
+        assert_eq!(section_placement(&load(), &at_start), Inside);
+        // 结束处的空节属于下一个段
+        let at_end = section(abi::SHT_NOBITS, 0, 0x1200, 0x40_1400, 0);
+        assert_eq!(section_placement(&load(), &at_end), Outside);
+        let at_file_end = section(abi::SHT_PROGBITS, 0, 0x1200, 0x40_1200, 0);
+        assert_eq!(section_placement(&load(), &at_file_end), Outside);
+        let before = section(abi::SHT_PROGBITS, 0, 0xfff, 0x40_0fff, 0);
+        assert_eq!(section_placement(&load(), &before), Outside);
+        // 空段只包含起点处的空节
+        let empty = segment(abi::PT_LOAD, 0x1200, 0x40_1200, 0, 0);
+        assert_eq!(section_placement(&empty, &at_file_end), Inside);
+        assert_eq!(section_placement(&empty, &at_start), Outside);
+    }
+
+    #[test]
+    fn tls_sections() {
+        let tdata = section(abi::SHT_PROGBITS, abi::SHF_TLS, 0x1100, 0x40_1100, 0x100);
+        let tbss = section(abi::SHT_NOBITS, abi::SHF_TLS, 0x1200, 0x40_1200, 0x80);
+        let tls = segment(abi::PT_TLS, 0x1100, 0x40_1100, 0x100, 0x180);
+        assert_eq!(section_placement(&load(), &tdata), Inside);
+        assert_eq!(section_placement(&tls, &tdata), Inside);
+        // .tbss 不占用 PT_LOAD 的内存, 只属于 PT_TLS
+        assert_eq!(section_placement(&load(), &tbss), Outside);
+        assert_eq!(section_placement(&tls, &tbss), Inside);
+        let data = section(abi::SHT_PROGBITS, 0, 0x1100, 0x40_1100, 0x100);
+        assert_eq!(section_placement(&tls, &data), Outside);
+    }
+
+    #[test]
+    fn phdr_and_null_sections() {
+        let phdr = segment(abi::PT_PHDR, 0x1000, 0x40_1000, 0x200, 0x200);
+        let data = section(abi::SHT_PROGBITS, 0, 0x1000, 0x40_1000, 0x100);
+        assert_eq!(section_placement(&phdr, &data), Outside);
+        let null = section(abi::SHT_NULL, 0, 0x1000, 0x40_1000, 0);
+        assert_eq!(section_placement(&load(), &null), Outside);
+    }
+}
@@ -1,4 +1,9 @@
-use std::{fs::Metadata, path::PathBuf, time::SystemTime};
+use std::{
+    fs::Metadata,
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+    time::SystemTime,
+};
 
 use elf::{endian::AnyEndian, file::FileHeader};
 use ratatui::{
@@ -9,24 +14,28 @@ use ratatui::{
     widgets::{Block, Paragraph, Widget},
 };
 
+use crate::isa::IsaReport;
+
 pub struct SummaryPage {
     file_name: String,
     file_size: u64,
     file_modified: SystemTime,
-    file_hash: String,
+    file_hash: Arc<OnceLock<String>>,
     elf_header: FileHeader<AnyEndian>,
     compiler_info: Option<String>,
     interpreter: Option<String>,
+    isa: Arc<OnceLock<Option<IsaReport>>>,
 }
 
 impl SummaryPage {
     pub fn new(
         path: PathBuf,
         metadata: Metadata,
-        file_hash: String,
+        file_hash: Arc<OnceLock<String>>,
         elf_header: FileHeader<AnyEndian>,
         compiler_info: Option<String>,
         interpreter: Option<String>,
+        isa: Arc<OnceLock<Option<IsaReport>>>,
     ) -> SummaryPage {
         SummaryPage {
             file_name: path.file_name().unwrap().to_string_lossy().into_owned(),
@@ -36,6 +45,7 @@ impl SummaryPage {
             elf_header,
             compiler_info,
             interpreter,
+            isa,
         }
     }
 
@@ -50,7 +60,7 @@ impl SummaryPage {
             0x14 => "PowerPC",
             0x15 => "PowerPC64",
             0x32 => "IA-64",
-            0x3E => "AMD64",
+            0xF3 => "RISC-V",
             _ => "Unknown",
         }
     }
@@ -107,7 +117,11 @@ impl Widget for &SummaryPage {
             Line::from(""),
             Line::from(vec![
                 Span::raw("SHA-256: "),
-                Span::styled(&self.file_hash, Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(
+                    // 哈希在后台线程中计算
+                    self.file_hash.get().map_or("Computing...", String::as_str),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
             ]),
             Line::from(""),
             Line::from(vec![
@@ -151,6 +165,28 @@ impl Widget for &SummaryPage {
             ]));
         }
 
+        // 指令集扩展也在后台线程中统计, 只有 x86 有
+        let isa = match self.isa.get() {
+            None => Some("Scanning...".to_string()),
+            Some(None) => None,
+            Some(Some(report)) => Some(match report.level() {
+                Some(level) => format!("x86-64-v{} (highest level used)", level),
+                None => format!("{} used", report.extensions.len()),
+            }),
+        };
+        if let Some(isa) = isa {
+            lines.push(Line::from(""));
+            lines.push(Line::from(vec![
+                Span::raw("ISA Extensions: "),
+                Span::styled(isa, Style::default().add_modifier(Modifier::BOLD)),
+            ]));
+        }
+        if let Some(Some(report)) = self.isa.get() {
+            for extension in &report.extensions {
+                lines.push(Line::from(format!("  {}", extension.summary())));
+            }
+        }
+
         Paragraph::new(lines)
             .block(Block::bordered().title("File Summary"))
             .render(area, buf);
@@ -1,115 +1,1136 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
 use std::vec;
 
+use elf::abi;
 use elf::ElfBytes;
-use elf::{endian::AnyEndian, parse::ParsingTable, string_table::StringTable};
+use elf::{endian::AnyEndian, string_table::StringTable, symbol::SymbolTable};
+use ratatui::crossterm::event::KeyCode;
 use ratatui::widgets::{Scrollbar, ScrollbarOrientation, ScrollbarState};
 
-use crate::elf::decompile_symbol;
+use crate::cfg::{Cfg, Graph};
+use crate::demangle::{demangle, demangle_all};
+use crate::disasm::{self, has_syntaxes, Disassembler, FormatOptions, SymbolMap};
+use crate::discovery::Discovery;
+use crate::elf::{
+    code_bitness, collect_symbols, decompile_symbol, dump_symbol, listing_key, symbol_bind_name,
+    symbol_section, symbol_type_name, symbol_visibility_name, tls_symbol_address, typed_data,
+    Listing, Lookup, INVALID_NAME, SHN_LORESERVE,
+};
 use crate::empty::Page;
+use crate::error::{Diagnostics, ElfError, TableLocation};
+use crate::filter::FilteredList;
+use crate::hex::BYTES_PER_LINE;
+use crate::xref::XrefIndex;
+use ratatui::style::Stylize;
 use ratatui::text::Line;
 use ratatui::{
     buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
     style::{Modifier, Style},
-    widgets::{Block, List, ListDirection, ListState, Paragraph, StatefulWidget, Widget},
+    widgets::{Block, List, ListState, Paragraph, StatefulWidget, Widget},
 };
 
+/// Shown for `.symtab` entries with an empty name.
+const UNNAMED: &str = "<unnamed>";
+
 pub struct SymbolPage<'a> {
     pub content: Vec<Symbol<'a>>,
-    pub list: List<'a>,
-    pub state: ListState,
-    pub ScrollState: ScrollbarState,
-    pub active_on_content: bool,
+    /// The names as they are shown, demangled unless `options` says otherwise.
+    names: Vec<String>,
+    /// The names as they are in the file.
+    mangled_names: Vec<String>,
+    pub list: FilteredList,
+    pub scroll_state: ScrollbarState,
+    focus: Focus,
+    page_height: usize, // 上次渲染时可见的行数, 用于翻页
+    address_width: usize,
+    show_xrefs: bool,
+    /// Show functions as a control-flow graph instead of a flat listing.
+    show_graph: bool,
+    /// List the symbols as a table of their attributes.
+    show_table: bool,
+    kinds: KindFilter,
+    /// The table column the list is sorted by; `None` keeps the file order.
+    sort: Option<SortColumn>,
+    descending: bool,
+    /// By section index, for the table.
+    section_names: Vec<String>,
+    options: FormatOptions,
+    /// Only the x86 backend has more than one syntax.
+    has_syntaxes: bool,
+    /// Built in the background the first time the references pane is
+    /// opened, it decodes the whole file; with the names of the callers.
+    xrefs: Option<(Arc<OnceLock<XrefIndex>>, SymbolMap)>,
+    /// The references pane of the selected symbol, kept while it stays selected.
+    references: Option<References<'a>>,
+    xref_state: ListState,
+    /// Mapped again by the background threads.
+    path: PathBuf,
+    /// Symbol and cursor line to return to with Backspace.
+    history: Vec<(usize, usize)>,
+    /// Where Backspace left from, to go to again with `]`.
+    forward: Vec<(usize, usize)>,
+    /// Built the first time a symbol is shown.
+    lookup: Option<Lookup<'a>>,
+    /// Describes the instruction under the cursor; `None` for other architectures.
+    describer: Option<Box<dyn Disassembler>>,
+    /// The functions of a stripped file, until the background search is
+    /// done, and the addresses already listed under a name.
+    discovery: Option<(Arc<OnceLock<Discovery>>, HashSet<u64>)>,
+    diagnostics: Diagnostics,
+}
+
+/// Columns of the symbol table, in the order `c` cycles through them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortColumn {
+    Address,
+    Size,
+    Type,
+    Binding,
+    Visibility,
+    Section,
+    Table,
+    Name,
+}
+
+impl SortColumn {
+    /// The column after `column`, then back to the file order.
+    fn next(column: Option<SortColumn>) -> Option<SortColumn> {
+        match column {
+            None => Some(SortColumn::Address),
+            Some(SortColumn::Address) => Some(SortColumn::Size),
+            Some(SortColumn::Size) => Some(SortColumn::Type),
+            Some(SortColumn::Type) => Some(SortColumn::Binding),
+            Some(SortColumn::Binding) => Some(SortColumn::Visibility),
+            Some(SortColumn::Visibility) => Some(SortColumn::Section),
+            Some(SortColumn::Section) => Some(SortColumn::Table),
+            Some(SortColumn::Table) => Some(SortColumn::Name),
+            Some(SortColumn::Name) => None,
+        }
+    }
+}
+
+/// The kinds of entries the list shows; `f`, `v`, `u`, `l` and `n` toggle them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KindFilter {
+    functions: bool,
+    data: bool,
+    undefined: bool,
+    local: bool,
+    /// `NOTYPE`, `FILE` and section symbols.
+    other: bool,
+}
+
+impl KindFilter {
+    const ALL: KindFilter = KindFilter {
+        functions: true,
+        data: true,
+        undefined: true,
+        local: true,
+        other: true,
+    };
+
+    fn shows(&self, symbol: &Symbol) -> bool {
+        if symbol.bind == Some(abi::STB_LOCAL) && !self.local {
+            return false;
+        }
+        if symbol.is_undefined() {
+            return self.undefined;
+        }
+        match symbol.kind {
+            _ if symbol.is_sweep() => self.functions,
+            abi::STT_FUNC | abi::STT_GNU_IFUNC => self.functions,
+            abi::STT_OBJECT | abi::STT_TLS | abi::STT_COMMON => self.data,
+            _ => self.other,
+        }
+    }
+
+    /// Toggle the kind `key` stands for; returns whether it stands for one.
+    fn toggle(&mut self, key: KeyCode) -> bool {
+        let shown = match key {
+            KeyCode::Char('f') => &mut self.functions,
+            KeyCode::Char('v') => &mut self.data,
+            KeyCode::Char('u') => &mut self.undefined,
+            KeyCode::Char('l') => &mut self.local,
+            KeyCode::Char('n') => &mut self.other,
+            _ => return false,
+        };
+        *shown = !*shown;
+        true
+    }
+
+    /// The kinds hidden, for the list title.
+    fn hidden(&self) -> Vec<&'static str> {
+        [
+            (self.functions, "functions"),
+            (self.data, "data"),
+            (self.undefined, "undefined"),
+            (self.local, "local"),
+            (self.other, "other"),
+        ]
+        .iter()
+        .filter(|(shown, _)| !shown)
+        .map(|&(_, name)| name)
+        .collect()
+    }
+}
+
+/// Where an entry of the list comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    /// A whole executable section, swept.
+    Section,
+    Symtab,
+    Dynsym,
+    /// In both `.symtab` and `.dynsym`.
+    Both,
+    /// A function found by following the code of a stripped file.
+    Discovered,
+}
+
+impl Source {
+    fn name(self) -> &'static str {
+        match self {
+            Source::Section => "section",
+            Source::Symtab => "symtab",
+            Source::Dynsym => "dynsym",
+            Source::Both => "both",
+            Source::Discovered => "found",
+        }
+    }
+
+    fn string_table(self) -> &'static str {
+        match self {
+            Source::Dynsym => ".dynstr",
+            _ => ".strtab",
+        }
+    }
+}
+
+/// The lines of the references pane for one symbol.
+struct References<'a> {
+    symbol: usize,
+    /// The referencing instruction of each line.
+    from: Vec<u64>,
+    lines: Vec<Line<'a>>,
+    title: String,
+}
+
+/// The pane that the arrow keys act on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    List,
+    Assembly,
+    References,
 }
 
 pub struct Symbol<'a> {
-    address: u64,
+    address: u64, // 整段扫描时为段的起始地址
     size: u64,
+    shndx: u16,
+    kind: u8,
+    source: Source,
+    /// Binding and visibility of `.symtab` and `.dynsym` entries.
+    bind: Option<u8>,
+    visibility: Option<u8>,
+    /// Shown as a hex dump instead of being disassembled.
+    is_data: bool,
+    /// Lines above the hex dump of a data symbol.
+    dump_start: usize,
     decompiled: bool,
     vertical_scroll: usize,
-    data: Vec<Line<'a>>,
+    cursor: usize, // 光标所在的行
+    data: Listing<'a>,
+    /// Laid out the first time the function is shown as a graph.
+    graph: Option<Graph>,
 }
 
 impl<'a> SymbolPage<'a> {
+    /// Every executable section, swept as a whole, followed by the symbols of
+    /// `.symtab` and those of `.dynsym` it doesn't have. Without `.symtab`,
+    /// the functions found by following the code come last as `sub_XXXX`,
+    /// once `discovery` has them.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        sym_tab: ParsingTable<'a, AnyEndian, elf::symbol::Symbol>,
-        str_tab: StringTable<'a>,
+        elf: &ElfBytes<'a, AnyEndian>,
+        symbols: Option<(SymbolTable<'a, AnyEndian>, StringTable<'a>)>,
+        location: TableLocation,
+        dynamic: Option<(SymbolTable<'a, AnyEndian>, StringTable<'a>)>,
+        dynamic_location: TableLocation,
+        path: &Path,
+        discovery: Arc<OnceLock<Discovery>>,
+        options: FormatOptions,
+        diagnostics: &Diagnostics,
     ) -> SymbolPage<'a> {
-        let mut name_list: Vec<&str> = Vec::new();
+        let mut name_list: Vec<String> = Vec::new();
         let mut content: Vec<Symbol> = Vec::new();
-        sym_tab.iter().for_each(|sym| {
-            let name = str_tab.get(sym.st_name as usize).unwrap();
-            if sym.is_undefined() {
-                return;
+        let mut section_names = vec![];
+
+        // 整段线性扫描, 剥离了符号表的文件也能看到代码
+        if let Ok((Some(shdrs), strtab)) = elf.section_headers_with_strtab() {
+            for (i, shdr) in shdrs.iter().enumerate() {
+                let name = strtab
+                    .and_then(|strtab| strtab.get(shdr.sh_name as usize).ok())
+                    .unwrap_or(INVALID_NAME);
+                section_names.push(name.to_string());
+                if shdr.sh_flags & abi::SHF_EXECINSTR as u64 == 0
+                    || shdr.sh_type == abi::SHT_NOBITS
+                    || shdr.sh_size == 0
+                {
+                    continue;
+                }
+                // 超出 u16 的节号与特殊节号冲突, 符号也无法引用, 不扫描
+                let Some(shndx) = u16::try_from(i).ok().filter(|&i| i < SHN_LORESERVE) else {
+                    continue;
+                };
+                name_list.push(format!("[{}]", name));
+                content.push(Symbol::new(
+                    shdr.sh_addr,
+                    shdr.sh_size,
+                    shndx,
+                    abi::STT_SECTION,
+                    Source::Section,
+                ));
             }
-            name_list.push(name);
-            content.push(Symbol {
-                address: sym.st_value,
-                size: sym.st_size,
-                decompiled: false,
-                vertical_scroll: 0,
-                data: vec![],
-            });
-        });
+        }
+
+        let has_symtab = symbols.is_some();
+        let tables = [
+            symbols.map(|table| (table, location, Source::Symtab)),
+            dynamic.map(|table| (table, dynamic_location, Source::Dynsym)),
+        ];
+        let listed = merge_symbols(
+            tables.into_iter().flatten(),
+            &section_names,
+            &mut name_list,
+            &mut content,
+            diagnostics,
+        );
+        extend_unsized(elf, &mut content);
+        // 剥离了 .symtab 时, 后台找到的函数之后再补上
+        let discovery = (!has_symtab).then_some((discovery, listed));
 
-        let list = List::new(name_list)
-            .block(Block::bordered().title("Symbols"))
-            .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
-            .highlight_symbol(">> ")
-            .repeat_highlight_symbol(true)
-            .direction(ListDirection::TopToBottom);
+        let names = demangle_all(&name_list, options);
+        let mut list = FilteredList::new("Symbols", ">> ", names.clone());
+        list.set_tags(Some(content.iter().map(|symbol| symbol.source.name()).collect()));
 
-        SymbolPage {
+        let mut page = SymbolPage {
             content,
             list,
-            state: ListState::default(),
-            ScrollState: ScrollbarState::default(),
-            active_on_content: false,
+            names,
+            mangled_names: name_list,
+            scroll_state: ScrollbarState::default(),
+            focus: Focus::List,
+            page_height: 0,
+            address_width: code_bitness(elf) as usize / 4,
+            show_xrefs: false,
+            show_graph: false,
+            show_table: false,
+            kinds: KindFilter::ALL,
+            sort: None,
+            descending: false,
+            section_names,
+            options,
+            has_syntaxes: has_syntaxes(elf.ehdr.e_machine),
+            xrefs: None,
+            references: None,
+            xref_state: ListState::default(),
+            path: path.to_path_buf(),
+            history: vec![],
+            forward: vec![],
+            lookup: None,
+            describer: disasm::for_elf(elf, Rc::new(SymbolMap::new()), options).ok(),
+            discovery,
+            diagnostics: diagnostics.clone(),
+        };
+        page.list.set_title(page.list_title());
+        page
+    }
+
+    /// Append the functions found in a stripped file once the background
+    /// search is done.
+    fn add_discovered(&mut self) {
+        let Some((cell, listed)) = self
+            .discovery
+            .take_if(|(cell, _)| cell.get().is_some())
+        else {
+            return;
+        };
+        let Some(discovery) = cell.get() else {
+            return;
+        };
+        for problem in &discovery.problems {
+            self.diagnostics.report(problem.clone());
+        }
+        for function in discovery
+            .functions
+            .iter()
+            .filter(|function| !listed.contains(&function.address))
+        {
+            let name = format!("sub_{:X}", function.address);
+            self.names.push(name.clone());
+            self.mangled_names.push(name);
+            self.content.push(Symbol::new(
+                function.address,
+                function.size,
+                function.shndx,
+                abi::STT_FUNC,
+                Source::Discovered,
+            ));
+        }
+        self.list.set_names(self.names.clone());
+        self.update_list();
+    }
+
+    /// The title of the list: which kinds are hidden, and whether functions
+    /// are still being looked for.
+    fn list_title(&self) -> String {
+        let mut title = String::from("Symbols");
+        if self.discovery.is_some() {
+            title.push_str(" ─ finding functions...");
+        }
+        let hidden = self.kinds.hidden();
+        if !hidden.is_empty() {
+            title.push_str(&format!(" ─ hiding {}", hidden.join(", ")));
+        }
+        title
+    }
+
+    /// Apply the kind filter, the sort order and the table layout to the list
+    /// after one of them changed.
+    fn update_list(&mut self) {
+        let mut order: Vec<usize> = (0..self.content.len())
+            .filter(|&i| self.kinds.shows(&self.content[i]))
+            .collect();
+        if let Some(column) = self.sort {
+            order.sort_by(|&a, &b| self.compare(column, a, b));
+        }
+        if self.descending {
+            order.reverse();
+        }
+        self.list.set_order(order);
+        self.list
+            .set_columns(self.show_table.then(|| self.table_columns()));
+        // 表格里有单独的一列, 列表里把来源标在名字后面
+        self.list.set_tags((!self.show_table).then(|| {
+            self.content
+                .iter()
+                .map(|symbol| symbol.source.name())
+                .collect()
+        }));
+        self.list.set_title(self.list_title());
+    }
+
+    fn compare(&self, column: SortColumn, a: usize, b: usize) -> Ordering {
+        let (x, y) = (&self.content[a], &self.content[b]);
+        match column {
+            SortColumn::Address => x.address.cmp(&y.address),
+            SortColumn::Size => x.size.cmp(&y.size),
+            SortColumn::Type => symbol_type_name(x.kind).cmp(symbol_type_name(y.kind)),
+            SortColumn::Binding => x
+                .bind
+                .map(symbol_bind_name)
+                .cmp(&y.bind.map(symbol_bind_name)),
+            SortColumn::Visibility => x
+                .visibility
+                .map(symbol_visibility_name)
+                .cmp(&y.visibility.map(symbol_visibility_name)),
+            SortColumn::Section => self.section_label(x.shndx).cmp(self.section_label(y.shndx)),
+            SortColumn::Table => x.source.name().cmp(y.source.name()),
+            SortColumn::Name => self.names[a].cmp(&self.names[b]),
+        }
+    }
+
+    /// The header and the columns before the name of each entry, like
+    /// `readelf -s`. The sorted column is marked with its direction.
+    fn table_columns(&self) -> (String, Vec<String>) {
+        let width = self.address_width;
+        // 过长的节名截断, 不让它把名字挤出去
+        let section_width = self
+            .section_names
+            .iter()
+            .map(|name| name.len())
+            .max()
+            .unwrap_or(0)
+            .clamp(8, 20);
+        let label = |column: SortColumn, name: &str| {
+            if self.sort != Some(column) {
+                name.to_string()
+            } else if self.descending {
+                format!("{}▼", name)
+            } else {
+                format!("{}▲", name)
+            }
+        };
+        let header = format!(
+            "{:<w$} {:>8} {:<7} {:<6} {:<9} {:<sw$} {:<7} {}",
+            label(SortColumn::Address, "Address"),
+            label(SortColumn::Size, "Size"),
+            label(SortColumn::Type, "Type"),
+            label(SortColumn::Binding, "Bind"),
+            label(SortColumn::Visibility, "Vis"),
+            label(SortColumn::Section, "Section"),
+            label(SortColumn::Table, "Table"),
+            label(SortColumn::Name, "Name"),
+            w = width,
+            sw = section_width
+        );
+        let columns = self
+            .content
+            .iter()
+            .map(|symbol| {
+                format!(
+                    "{:0w$X} {:>8} {:<7} {:<6} {:<9} {:<sw$.sw$} {:<7} ",
+                    symbol.address,
+                    symbol.size,
+                    symbol_type_name(symbol.kind),
+                    symbol.bind.map_or("-", symbol_bind_name),
+                    symbol.visibility.map_or("-", symbol_visibility_name),
+                    self.section_label(symbol.shndx),
+                    symbol.source.name(),
+                    w = width,
+                    sw = section_width
+                )
+            })
+            .collect();
+        (header, columns)
+    }
+
+    fn section_label(&self, shndx: u16) -> &str {
+        match shndx {
+            abi::SHN_UNDEF => "UND",
+            abi::SHN_ABS => "ABS",
+            abi::SHN_COMMON => "COM",
+            _ => self
+                .section_names
+                .get(shndx as usize)
+                .map_or("?", |name| name.as_str()),
+        }
+    }
+
+    /// The entry to show for `address`: the smallest symbol containing it,
+    /// else the whole section.
+    fn symbol_at(&self, address: u64) -> Option<usize> {
+        self.content
+            .iter()
+            .enumerate()
+            .filter(|(_, symbol)| {
+                !matches!(symbol.kind, abi::STT_TLS | abi::STT_FILE)
+                    && (symbol.kind != abi::STT_SECTION || symbol.is_sweep())
+                    && symbol.shndx != abi::SHN_UNDEF
+                    && symbol.shndx < SHN_LORESERVE
+                    && address
+                        .checked_sub(symbol.address)
+                        .is_some_and(|offset| offset == 0 || offset < symbol.size)
+            })
+            .min_by_key(|(_, symbol)| (symbol.is_sweep(), symbol.size))
+            .map(|(idx, _)| idx)
+    }
+
+    /// Select the entry for `address` and put the cursor on it, remembering
+    /// where we came from.
+    fn go_to(&mut self, elf: &ElfBytes<'a, AnyEndian>, address: u64) -> bool {
+        let Some(idx) = self.symbol_at(address) else {
+            return false;
+        };
+        // 新的跳转丢弃前进的记录
+        self.history.extend(self.location());
+        self.forward.clear();
+        self.reveal(idx);
+        self.list.select(idx);
+        self.xref_state = ListState::default();
+        self.load_symbol(elf, idx);
+        let symbol = &mut self.content[idx];
+        // 指令行取包含该地址的那条指令, 数据按每行的字节数换算
+        symbol.cursor = match symbol.data.line_of(address) {
+            Some(line) => line,
+            None if symbol.is_data => {
+                symbol.dump_start + (address - symbol.address) as usize / BYTES_PER_LINE
+            }
+            None => 0,
+        };
+        // 目标放在可见区域的上部
+        symbol.vertical_scroll = symbol.cursor.saturating_sub(self.page_height / 4);
+        self.focus = Focus::Assembly;
+        true
+    }
+
+    /// Show every kind of entry again when the kind filter hides entry `idx`.
+    fn reveal(&mut self, idx: usize) {
+        if !self.kinds.shows(&self.content[idx]) {
+            self.kinds = KindFilter::ALL;
+            self.update_list();
+        }
+    }
+
+    /// Follow the branch target or data address of the instruction under the cursor.
+    fn follow(&mut self, elf: &ElfBytes<'a, AnyEndian>) -> bool {
+        let Some(symbol) = self
+            .list
+            .selected()
+            .and_then(|idx| self.content.get_mut(idx))
+        else {
+            return false;
+        };
+        let Some(step) = symbol.data.step(symbol.cursor) else {
+            return false;
+        };
+        match step.flow.target().or(step.data) {
+            Some(target) => self.go_to(elf, target),
+            None => false,
+        }
+    }
+
+    /// The selected symbol and its cursor line.
+    fn location(&self) -> Option<(usize, usize)> {
+        let idx = self.list.selected().filter(|&i| i < self.content.len())?;
+        Some((idx, self.content[idx].cursor))
+    }
+
+    fn go_back(&mut self, elf: &ElfBytes<'a, AnyEndian>) -> bool {
+        let Some(to) = self.history.pop() else {
+            return false;
+        };
+        self.forward.extend(self.location());
+        self.return_to(elf, to);
+        true
+    }
+
+    fn go_forward(&mut self, elf: &ElfBytes<'a, AnyEndian>) -> bool {
+        let Some(to) = self.forward.pop() else {
+            return false;
+        };
+        self.history.extend(self.location());
+        self.return_to(elf, to);
+        true
+    }
+
+    /// Select symbol `idx` again with the cursor where it was.
+    fn return_to(&mut self, elf: &ElfBytes<'a, AnyEndian>, (idx, cursor): (usize, usize)) {
+        self.reveal(idx);
+        self.list.select(idx);
+        self.xref_state = ListState::default();
+        self.load_symbol(elf, idx);
+        self.content[idx].cursor = cursor;
+        self.focus = Focus::Assembly;
+    }
+
+    /// The instruction selected in the references pane.
+    fn selected_reference(&self) -> Option<u64> {
+        let references = self
+            .references
+            .as_ref()
+            .filter(|references| Some(references.symbol) == self.list.selected())?;
+        references.from.get(self.xref_state.selected()?).copied()
+    }
+
+    /// What the instruction under the cursor reads, writes and needs, while
+    /// the Assembly pane has the focus.
+    fn instruction_detail(&mut self) -> Vec<String> {
+        if self.focus != Focus::Assembly {
+            return vec![];
+        }
+        let Some(symbol) = self
+            .list
+            .selected()
+            .and_then(|idx| self.content.get_mut(idx))
+        else {
+            return vec![];
+        };
+        let (Some(describer), Some(step)) =
+            (self.describer.as_mut(), symbol.data.step(symbol.cursor))
+        else {
+            return vec![];
+        };
+        match symbol.data.bytes_at(step.address) {
+            Some(code) => describer.describe(code, step.address),
+            None => vec![],
+        }
+    }
+
+    /// Start building the cross-reference index, naming the referencing
+    /// instructions after the symbols and the functions of this page.
+    fn load_xrefs(&mut self, elf: &ElfBytes<'a, AnyEndian>) {
+        if self.xrefs.is_some() {
+            return;
+        }
+        let mut names = collect_symbols(elf, &self.diagnostics);
+        for (symbol, name) in self.content.iter().zip(self.mangled_names.iter()) {
+            if symbol.kind == abi::STT_FUNC {
+                names.insert_missing(symbol.address, name.clone(), symbol.size);
+            }
+        }
+        self.xrefs = Some((XrefIndex::spawn_build(&self.path), names));
+    }
+
+    /// Lines of the references pane for the selected symbol, and its title.
+    /// They are worked out again only when another symbol is selected.
+    fn reference_lines(&mut self) -> (&[Line<'a>], &str) {
+        let Some(idx) = self.list.selected() else {
+            return (&[], "References");
+        };
+        let Some((index, names)) = self.xrefs.as_ref() else {
+            return (&[], "References");
+        };
+        let Some(index) = index.get() else {
+            return (&[], "References ─ decoding the file...");
+        };
+        if self.references.as_ref().map(|references| references.symbol) != Some(idx) {
+            let (Some(symbol), Some(name)) = (self.content.get(idx), self.names.get(idx)) else {
+                return (&[], "References");
+            };
+            let width = self.address_width;
+            let references = index.references_to(symbol.address, symbol.size);
+            let lines = references
+                .iter()
+                .map(|reference| {
+                    let mut line = Line::from(format!(
+                        "{:0w$X}  {:<4}  ",
+                        reference.from,
+                        reference.kind.name(),
+                        w = width
+                    ));
+                    if let Some((caller, start)) = names.resolve(reference.from) {
+                        let caller = demangle(caller, self.options);
+                        line.push_span(
+                            format!("{}+0x{:x}", caller, reference.from - start).magenta(),
+                        );
+                    }
+                    // 引用落在符号中间时标出具体的目标
+                    if reference.to != symbol.address {
+                        line.push_span(format!("  -> +0x{:x}", reference.to - symbol.address));
+                    }
+                    line
+                })
+                .collect();
+            self.references = Some(References {
+                symbol: idx,
+                from: references.iter().map(|reference| reference.from).collect(),
+                lines,
+                title: format!("References to {} ({})", name, references.len()),
+            });
+        }
+        match &self.references {
+            Some(references) => (&references.lines, &references.title),
+            None => (&[], "References"),
+        }
+    }
+
+    /// Lay out the control-flow graph of a function. Whole sections and data
+    /// get none.
+    fn load_graph(&mut self, idx: usize) {
+        let Some(symbol) = self.content.get_mut(idx) else {
+            return;
+        };
+        if symbol.graph.is_some() || symbol.is_sweep() || symbol.is_data {
+            return;
         }
+        let cfg = Cfg::build(&symbol.data.steps());
+        if cfg.blocks.is_empty() {
+            return;
+        }
+        let lines = symbol.data.lines(0..symbol.data.len());
+        // 跳出函数的目标按符号名加偏移显示
+        let name_of = |target: u64| match self.symbol_at(target) {
+            Some(i) if self.content[i].address == target => self.names[i].clone(),
+            Some(i) => format!("{}+0x{:x}", self.names[i], target - self.content[i].address),
+            None => format!("{:X}", target),
+        };
+        let graph = Graph::layout(cfg, &lines, &self.names[idx], name_of);
+        self.content[idx].graph = Some(graph);
+    }
+
+    /// What the entries without contents of their own are: undefined,
+    /// absolute and common symbols, source files and section symbols.
+    fn describe(&self, idx: usize) -> Option<String> {
+        let symbol = &self.content[idx];
+        let name = &self.names[idx];
+        let text = match symbol.shndx {
+            _ if symbol.is_undefined() && symbol.bind == Some(abi::STB_WEAK) => format!(
+                "{} is an undefined weak symbol: another object file or shared library may define it, otherwise its address is 0",
+                name
+            ),
+            _ if symbol.is_undefined() => format!(
+                "{} is undefined: it is defined in another object file or shared library",
+                name
+            ),
+            // 链接器用无名的 FILE 符号结束上一个文件的局部符号
+            _ if symbol.kind == abi::STT_FILE && name == UNNAMED => String::from(
+                "An unnamed FILE symbol: the local symbols after it belong to no source file",
+            ),
+            _ if symbol.kind == abi::STT_FILE => {
+                format!("{} is the source file of the local symbols after it", name)
+            }
+            _ if symbol.kind == abi::STT_SECTION && !symbol.is_sweep() => format!(
+                "Section symbol of {}, for relocations against the section",
+                self.section_label(symbol.shndx)
+            ),
+            abi::SHN_ABS => format!(
+                "{} is an absolute value: {:#X} ({})",
+                name, symbol.address, symbol.address
+            ),
+            abi::SHN_COMMON => format!(
+                "{} is a common block of {} bytes aligned to {}, allocated by the linker",
+                name, symbol.size, symbol.address
+            ),
+            _ => return None,
+        };
+        Some(text)
     }
 
     pub fn load_symbol(&mut self, elf: &ElfBytes<'a, AnyEndian>, idx: usize) {
         if idx >= self.content.len() {
             return;
         }
-        let symbol = &self.content[idx];
-        if !symbol.decompiled {
-            let decompiled: Vec<Line<'a>> =
-                decompile_symbol(elf, symbol.address, symbol.size as usize, ".text");
+        if !self.content[idx].decompiled {
+            let lookup = self
+                .lookup
+                .get_or_insert_with(|| Lookup::new(elf, self.options, &self.diagnostics))
+                .clone();
+            let symbol = &self.content[idx];
+            let address = match symbol.kind {
+                abi::STT_TLS => tls_symbol_address(elf, symbol.address),
+                _ => symbol.address,
+            };
+            let size = symbol.size as usize;
+            let mut is_data = false;
+            let mut dump_start = 0;
+            let decompiled: Listing<'a> = if let Some(text) = self.describe(idx) {
+                is_data = true;
+                vec![Line::from(text)].into()
+            } else {
+                match symbol_section(elf, symbol.shndx, address) {
+                    None => vec![Line::from(format!(
+                        "Symbol {:08X} is not inside any section",
+                        address
+                    ))]
+                    .into(),
+                    Some((shdr, name)) if shdr.sh_type == abi::SHT_NOBITS => {
+                        is_data = true;
+                        vec![Line::from(format!(
+                            "Symbol {:08X} lives in {}, which has no contents in the file",
+                            address, name
+                        ))]
+                        .into()
+                    }
+                    // 函数按所在段反汇编, 数据符号显示内容的解读和十六进制
+                    Some((shdr, name))
+                        if shdr.sh_flags & abi::SHF_EXECINSTR as u64 != 0 && symbol.is_code() =>
+                    {
+                        // 整段扫描时在各个已知符号处插入标签
+                        let labels = symbol.is_sweep();
+                        decompile_symbol(
+                            elf,
+                            address,
+                            size,
+                            &shdr,
+                            &name,
+                            labels,
+                            self.options,
+                            &lookup,
+                            &self.diagnostics,
+                        )
+                    }
+                    Some((shdr, name)) => {
+                        is_data = true;
+                        // 没有大小的符号已经延伸到下一个符号, 仍为 0 时后面紧接着别的符号或者段尾
+                        if size == 0 {
+                            vec![Line::from(format!(
+                                "{} has a size of 0: another symbol or the end of {} follows right away",
+                                self.names[idx], name
+                            ))]
+                            .into()
+                        } else {
+                            let mut lines = typed_data(&lookup, address, size);
+                            dump_start = lines.len();
+                            lines.extend(dump_symbol(
+                                elf,
+                                address,
+                                size,
+                                &shdr,
+                                &name,
+                                &self.diagnostics,
+                            ));
+                            lines.into()
+                        }
+                    }
+                }
+            };
             self.content[idx].data = decompiled;
+            self.content[idx].is_data = is_data;
+            self.content[idx].dump_start = dump_start;
             self.content[idx].decompiled = true;
         }
     }
+}
+
+/// A symbol table with its string table.
+type Table<'a> = (SymbolTable<'a, AnyEndian>, StringTable<'a>);
 
-    
+/// Append the symbols of each table, listing a symbol found in both
+/// `.symtab` and `.dynsym` once. Returns the addresses of the defined ones.
+fn merge_symbols<'a>(
+    tables: impl Iterator<Item = (Table<'a>, TableLocation, Source)>,
+    section_names: &[String],
+    name_list: &mut Vec<String>,
+    content: &mut Vec<Symbol<'a>>,
+    diagnostics: &Diagnostics,
+) -> HashSet<u64> {
+    // 两张表里的同一个符号只列一次: (地址, 去掉版本的名字) -> 条目, 其次按地址
+    let mut by_name: HashMap<(u64, String), usize> = HashMap::new();
+    let mut by_address: HashMap<u64, usize> = HashMap::new();
+    for ((sym_tab, str_tab), location, source) in tables {
+        // 第 0 项是空符号
+        for (i, sym) in sym_tab.iter().enumerate().skip(1) {
+            let name = str_tab.get(sym.st_name as usize).unwrap_or_else(|_| {
+                diagnostics.report(ElfError::BadStringIndex {
+                    table: source.string_table(),
+                    index: sym.st_name as u64,
+                    offset: location.at(i).unwrap_or(location.offset),
+                });
+                INVALID_NAME
+            });
+            // 节符号没有名字, 用节名代替
+            let name = match section_names.get(sym.st_shndx as usize) {
+                Some(section) if name.is_empty() && sym.st_symtype() == abi::STT_SECTION => {
+                    section.as_str()
+                }
+                _ if name.is_empty() => UNNAMED,
+                _ => name,
+            };
+            let base = name.split('@').next().unwrap_or(name).to_string();
+            let defined = sym.st_shndx != abi::SHN_UNDEF
+                && !matches!(sym.st_symtype(), abi::STT_SECTION | abi::STT_FILE);
+            if source == Source::Dynsym {
+                let same = by_name
+                    .get(&(sym.st_value, base.clone()))
+                    .or_else(|| by_address.get(&sym.st_value).filter(|_| defined));
+                if let Some(&idx) = same {
+                    content[idx].source = Source::Both;
+                    continue;
+                }
+            }
+            by_name.entry((sym.st_value, base)).or_insert(content.len());
+            if defined {
+                by_address.entry(sym.st_value).or_insert(content.len());
+            }
+            name_list.push(name.to_string());
+            content.push(Symbol {
+                bind: Some(sym.st_bind()),
+                visibility: Some(sym.st_vis()),
+                ..Symbol::new(
+                    sym.st_value,
+                    sym.st_size,
+                    sym.st_shndx,
+                    sym.st_symtype(),
+                    source,
+                )
+            });
+        }
+    }
+    by_address.into_keys().collect()
+}
+
+/// Give the symbols without a size, like the `_init`/`_fini` written in
+/// assembly or a `msg:` label in `.rodata`, the bytes up to the next symbol
+/// or the end of their section.
+fn extend_unsized(elf: &ElfBytes<'_, AnyEndian>, content: &mut [Symbol]) {
+    let Some(shdrs) = elf.section_headers() else {
+        return;
+    };
+    let mut starts: Vec<(u16, u64)> = content.iter().map(|s| (s.shndx, s.address)).collect();
+    starts.sort_unstable();
+    for symbol in content.iter_mut().filter(|s| {
+        s.size == 0
+            && !s.is_undefined()
+            && !matches!(s.kind, abi::STT_FILE | abi::STT_SECTION | abi::STT_TLS)
+    }) {
+        let Ok(shdr) = shdrs.get(symbol.shndx as usize) else {
+            continue;
+        };
+        if shdr.sh_type == abi::SHT_NOBITS {
+            continue;
+        }
+        let next = starts.partition_point(|&start| start <= (symbol.shndx, symbol.address));
+        let end = match starts.get(next) {
+            Some(&(shndx, address)) if shndx == symbol.shndx => address,
+            _ => shdr.sh_addr.saturating_add(shdr.sh_size),
+        };
+        symbol.size = end.saturating_sub(symbol.address);
+    }
+}
+
+impl Symbol<'_> {
+    fn new(address: u64, size: u64, shndx: u16, kind: u8, source: Source) -> Self {
+        Symbol {
+            address,
+            size,
+            shndx,
+            kind,
+            source,
+            bind: None,
+            visibility: None,
+            is_data: false,
+            dump_start: 0,
+            decompiled: false,
+            vertical_scroll: 0,
+            cursor: 0,
+            data: Listing::from(vec![]),
+            graph: None,
+        }
+    }
+
+    /// A whole executable section, swept when the file has no symbols for it.
+    fn is_sweep(&self) -> bool {
+        self.source == Source::Section
+    }
+
+    fn is_undefined(&self) -> bool {
+        self.shndx == abi::SHN_UNDEF && self.bind.is_some()
+    }
+
+    /// Disassembled when it lies in an executable section; `NOTYPE` covers
+    /// the labels of hand-written assembly.
+    fn is_code(&self) -> bool {
+        self.is_sweep()
+            || matches!(
+                self.kind,
+                abi::STT_FUNC | abi::STT_GNU_IFUNC | abi::STT_NOTYPE
+            )
+    }
 }
 
 impl<'a> Widget for &mut SymbolPage<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        self.add_discovered();
         let layout = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints(vec![Constraint::Min(40), Constraint::Percentage(100)])
+            .constraints(if self.show_table {
+                vec![Constraint::Percentage(60), Constraint::Percentage(40)]
+            } else {
+                vec![Constraint::Min(40), Constraint::Percentage(100)]
+            })
             .split(area);
 
-        StatefulWidget::render(&self.list, layout[0], buf, &mut self.state);
-        let selected = self.state.selected();
+        self.list.render(layout[0], buf);
+        let selected = self.list.selected();
 
-        let paragraph = if selected.is_none() {
-            Paragraph::new("Select a symbol to decompile")
+        let (code_area, xref_area) = if self.show_xrefs {
+            let [code_area, xref_area] =
+                Layout::vertical([Constraint::Percentage(65), Constraint::Percentage(35)])
+                    .areas(layout[1]);
+            (code_area, Some(xref_area))
+        } else {
+            (layout[1], None)
+        };
+        let detail = self.instruction_detail();
+        let (code_area, detail_area) = if detail.is_empty() {
+            (code_area, None)
         } else {
-            let data = self.content[selected.unwrap()].data.clone();
-            self.ScrollState = self.ScrollState.content_length(data.len());
-            Paragraph::new(data)
-            .scroll((self.content[selected.unwrap()].vertical_scroll as u16, 0))
+            let [code_area, detail_area] = Layout::vertical([
+                Constraint::Min(3),
+                Constraint::Length(detail.len() as u16 + 2),
+            ])
+            .areas(code_area);
+            (code_area, Some(detail_area))
         };
+
+        // 只渲染可见的行, 整段反汇编可能有几十万行
+        let height = code_area.height.saturating_sub(2) as usize;
+        self.page_height = height;
+        if let Some(idx) = selected.filter(|_| self.show_graph) {
+            self.load_graph(idx);
+        }
+        let show_cursor = self.focus == Focus::Assembly;
+        let (paragraph, title) = match selected.and_then(|idx| self.content.get_mut(idx)) {
+            None => (Paragraph::new("Select a symbol to decompile"), "Assembly"),
+            Some(Symbol {
+                graph: Some(graph),
+                data,
+                cursor,
+                ..
+            }) if self.show_graph => {
+                let len = graph.height();
+                *cursor = (*cursor).min(data.len().saturating_sub(1));
+                // 光标所在的指令行, 以及它所在方框的标题行都尽量可见
+                let row = graph.row_of_line(*cursor).unwrap_or(0);
+                graph.scroll = graph
+                    .scroll
+                    .min(row.saturating_sub(1))
+                    .max((row + 2).saturating_sub(height));
+                let start = graph.scroll;
+                let visible = graph.lines(
+                    &data.lines(0..data.len()),
+                    start..(start + height).min(len),
+                    *cursor,
+                    show_cursor,
+                );
+                self.scroll_state = self.scroll_state.content_length(len).position(start);
+                (Paragraph::new(visible), "Control flow")
+            }
+            Some(symbol) => {
+                // 光标和可见区域移到还没解码的地方时接着解码
+                let bottom = symbol.vertical_scroll.saturating_add(height);
+                symbol.data.decode_to(symbol.cursor.max(bottom));
+                let len = symbol.data.len();
+                symbol.cursor = symbol.cursor.min(len.saturating_sub(1));
+                // 滚动时让光标保持在可见区域内
+                symbol.vertical_scroll = symbol
+                    .vertical_scroll
+                    .min(len.saturating_sub(1))
+                    .min(symbol.cursor)
+                    .max((symbol.cursor + 1).saturating_sub(height));
+                let start = symbol.vertical_scroll;
+                let mut visible = symbol.data.lines(start..(start + height).min(len));
+                if show_cursor {
+                    if let Some(line) = visible.get_mut(symbol.cursor - start) {
+                        line.style = Style::default().add_modifier(Modifier::REVERSED);
+                    }
+                }
+                self.scroll_state = self
+                    .scroll_state
+                    .content_length(symbol.data.estimated_len())
+                    .position(start);
+                let title = if symbol.is_data { "Data" } else { "Assembly" };
+                (Paragraph::new(visible), title)
+            }
+        };
+        let mut keys =
+            String::from("Enter: follow  Backspace: back  ]: forward  x: references  g: graph");
+        if self.has_syntaxes {
+            keys.push_str(&format!("  s: {}", self.options.syntax.name()));
+        }
+        keys.push_str("  b: bytes  o: offsets  d: demangle  f v u l n: kinds  t: table");
+        if self.show_table {
+            keys.push_str("  c: sort  r: reverse");
+        }
         paragraph
-            .block(Block::bordered().title("Assembly"))
-            .render(layout[1], buf);
+            .block(Block::bordered().title(title).title_bottom(keys))
+            .render(code_area, buf);
 
         Scrollbar::new(ScrollbarOrientation::VerticalRight)
             .begin_symbol(Some("▲"))
             .end_symbol(Some("▼"))
-            .render(layout[1], buf, &mut self.ScrollState);
+            .render(code_area, buf, &mut self.scroll_state);
+
+        if let Some(area) = detail_area {
+            Paragraph::new(detail.into_iter().map(Line::from).collect::<Vec<_>>())
+                .block(Block::bordered().title("Instruction"))
+                .render(area, buf);
+        }
+
+        if let Some(area) = xref_area {
+            let (lines, title) = self.reference_lines();
+            let list = List::new(lines.to_vec())
+                .block(Block::bordered().title(title.to_string()))
+                .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
+                .highlight_symbol(">> ");
+            StatefulWidget::render(list, area, buf, &mut self.xref_state);
+        }
     }
 }
 
@@ -119,36 +1140,264 @@ impl<'a> Page<'a> for SymbolPage<'a> {
     }
 
     fn select_next(&mut self, elf_file: &ElfBytes<'a, AnyEndian>) {
-        if self.active_on_content {
-            let idx: usize = self.state.selected().unwrap();
-            self.content[idx].vertical_scroll = self.content[idx].vertical_scroll.saturating_add(1);
-            self.ScrollState = self.ScrollState.position(self.content[idx].vertical_scroll);
+        match self.focus {
+            Focus::List => {
+                self.list.select_next();
+                self.xref_state = ListState::default();
+            }
+            Focus::References => {
+                self.xref_state.select_next();
+                return;
+            }
+            Focus::Assembly => {}
+        }
+        let Some(idx) = self.list.selected() else {
+            return;
+        };
+        if self.focus == Focus::Assembly {
+            self.content[idx].cursor = self.content[idx].cursor.saturating_add(1);
         } else {
-            self.state.select_next();
-            let idx: usize = self.state.selected().unwrap();
             self.load_symbol(elf_file, idx);
-            self.ScrollState = self.ScrollState.position(self.content[idx].vertical_scroll);
         }
+        self.scroll_state = self
+            .scroll_state
+            .position(self.content[idx].vertical_scroll);
     }
 
     fn select_previous(&mut self, elf_file: &ElfBytes<'a, AnyEndian>) {
-        if self.active_on_content {
-            let idx: usize = self.state.selected().unwrap();
-            self.content[idx].vertical_scroll = self.content[idx].vertical_scroll.saturating_sub(1);
-            self.ScrollState = self.ScrollState.position(self.content[idx].vertical_scroll);
+        match self.focus {
+            Focus::List => {
+                self.list.select_previous();
+                self.xref_state = ListState::default();
+            }
+            Focus::References => {
+                self.xref_state.select_previous();
+                return;
+            }
+            Focus::Assembly => {}
+        }
+        let Some(idx) = self.list.selected() else {
+            return;
+        };
+        if self.focus == Focus::Assembly {
+            self.content[idx].cursor = self.content[idx].cursor.saturating_sub(1);
         } else {
-            self.state.select_previous();
-            let idx: usize = self.state.selected().unwrap();
             self.load_symbol(elf_file, idx);
-            self.ScrollState = self.ScrollState.position(self.content[idx].vertical_scroll);
         }
+        self.scroll_state = self
+            .scroll_state
+            .position(self.content[idx].vertical_scroll);
     }
 
     fn select_left(&mut self) {
-        self.active_on_content = false;
+        self.focus = match self.focus {
+            Focus::References => Focus::Assembly,
+            _ => Focus::List,
+        };
     }
 
     fn select_right(&mut self) {
-        self.active_on_content = true;
+        self.focus = match self.focus {
+            Focus::List => Focus::Assembly,
+            _ if self.show_xrefs => Focus::References,
+            focus => focus,
+        };
+    }
+
+    fn handle_key(&mut self, key: KeyCode, elf: &ElfBytes<'a, AnyEndian>) -> bool {
+        let selected = self.list.selected();
+        if self.list.handle_key(key) {
+            self.focus = Focus::List;
+            if let Some(idx) = self.list.selected().filter(|&idx| Some(idx) != selected) {
+                self.xref_state = ListState::default();
+                self.load_symbol(elf, idx);
+            }
+            return true;
+        }
+        let table_key = match key {
+            _ if self.kinds.toggle(key) => true,
+            KeyCode::Char('t') => {
+                self.show_table = !self.show_table;
+                true
+            }
+            KeyCode::Char('c') if self.show_table => {
+                self.sort = SortColumn::next(self.sort);
+                true
+            }
+            KeyCode::Char('r') if self.show_table => {
+                self.descending = !self.descending;
+                true
+            }
+            _ => false,
+        };
+        if table_key {
+            // 选中的符号被隐藏时会改选第一行
+            self.update_list();
+            if let Some(idx) = self.list.selected() {
+                self.load_symbol(elf, idx);
+            }
+            return true;
+        }
+        let mangled = self.options.mangled;
+        if listing_key(&mut self.options, key, self.has_syntaxes) {
+            if self.options.mangled != mangled {
+                self.names = demangle_all(&self.mangled_names, self.options);
+                self.list.set_names(self.names.clone());
+                if let Some(lookup) = &mut self.lookup {
+                    lookup.rename(elf, self.options, &self.diagnostics);
+                }
+                if self.sort == Some(SortColumn::Name) {
+                    self.update_list();
+                }
+            }
+            // 格式变了, 已反汇编的符号重新生成; 行数不变, 光标留在原处.
+            // 数据里的指针目标也带着名字, 切换 d 时一起重新生成
+            let renamed = self.options.mangled != mangled;
+            if renamed {
+                self.references = None;
+            }
+            for symbol in self.content.iter_mut().filter(|s| !s.is_data || renamed) {
+                symbol.decompiled = false;
+                symbol.graph = None;
+            }
+            if let Some(idx) = self.list.selected() {
+                self.load_symbol(elf, idx);
+            }
+            return true;
+        }
+        if key == KeyCode::Char('g') {
+            self.show_graph = !self.show_graph;
+            return true;
+        }
+        if key == KeyCode::Char('x') {
+            self.show_xrefs = !self.show_xrefs;
+            if self.show_xrefs {
+                self.load_xrefs(elf);
+            } else if self.focus == Focus::References {
+                self.focus = Focus::Assembly;
+            }
+            return true;
+        }
+        match key {
+            KeyCode::Enter if self.focus == Focus::References => {
+                return match self.selected_reference() {
+                    Some(from) => self.go_to(elf, from),
+                    None => false,
+                };
+            }
+            KeyCode::Enter => return self.follow(elf),
+            KeyCode::Backspace => return self.go_back(elf),
+            KeyCode::Char(']') => return self.go_forward(elf),
+            _ => {}
+        }
+        let page = self.page_height.max(1);
+        let Some(symbol) = self
+            .list
+            .selected()
+            .and_then(|idx| self.content.get_mut(idx))
+        else {
+            return false;
+        };
+        match key {
+            KeyCode::PageDown => {
+                symbol.vertical_scroll = symbol.vertical_scroll.saturating_add(page);
+                symbol.cursor = symbol.cursor.saturating_add(page);
+            }
+            KeyCode::PageUp => {
+                symbol.vertical_scroll = symbol.vertical_scroll.saturating_sub(page);
+                symbol.cursor = symbol.cursor.saturating_sub(page);
+            }
+            KeyCode::Home => symbol.cursor = 0,
+            // 渲染时会被限制到最后一行
+            KeyCode::End => symbol.cursor = usize::MAX,
+            _ => return false,
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use elf::file::Class;
+
+    /// `.strtab` 与 `.dynstr` 共用一张字符串表
+    const STRINGS: &[u8] = b"\0foo\0bar\0baz\0bar_alias\0foo@@V1\0";
+
+    /// (名字偏移, 类型, 节号, 地址)
+    fn table(symbols: &[(u32, u8, u16, u64)]) -> Vec<u8> {
+        let mut data = vec![0; 24];
+        for &(name, kind, shndx, value) in symbols {
+            data.extend(name.to_le_bytes());
+            data.push(abi::STB_GLOBAL << 4 | kind);
+            data.push(0);
+            data.extend(shndx.to_le_bytes());
+            data.extend(value.to_le_bytes());
+            data.extend(16u64.to_le_bytes());
+        }
+        data
+    }
+
+    fn parse(data: &[u8]) -> Table<'_> {
+        (
+            SymbolTable::new(AnyEndian::Little, Class::ELF64, data),
+            StringTable::new(STRINGS),
+        )
+    }
+
+    #[test]
+    fn symbols_in_both_tables_are_merged() {
+        let symtab = table(&[(1, abi::STT_FUNC, 1, 0x1000), (5, abi::STT_FUNC, 1, 0x2000)]);
+        // 同名同址, 别名同址, 只在 .dynsym 里的未定义符号
+        let dynsym = table(&[
+            (23, abi::STT_FUNC, 1, 0x1000),
+            (13, abi::STT_FUNC, 1, 0x2000),
+            (9, abi::STT_FUNC, abi::SHN_UNDEF, 0),
+        ]);
+        let tables = [
+            (parse(&symtab), TableLocation::default(), Source::Symtab),
+            (parse(&dynsym), TableLocation::default(), Source::Dynsym),
+        ];
+        let mut names = vec![];
+        let mut content = vec![];
+        let diagnostics = Diagnostics::new();
+        let listed = merge_symbols(
+            tables.into_iter(),
+            &[],
+            &mut names,
+            &mut content,
+            &diagnostics,
+        );
+
+        assert_eq!(names, ["foo", "bar", "baz"]);
+        let sources: Vec<Source> = content.iter().map(|symbol| symbol.source).collect();
+        assert_eq!(sources, [Source::Both, Source::Both, Source::Dynsym]);
+        assert!(content[2].is_undefined());
+        assert_eq!(listed, HashSet::from([0x1000, 0x2000]));
+        assert!(diagnostics.entries().is_empty());
+    }
+
+    #[test]
+    fn symbols_in_one_table_keep_their_source() {
+        let symtab = table(&[(1, abi::STT_FUNC, 1, 0x1000)]);
+        let dynsym = table(&[(5, abi::STT_FUNC, 1, 0x2000)]);
+        let tables = [
+            (parse(&symtab), TableLocation::default(), Source::Symtab),
+            (parse(&dynsym), TableLocation::default(), Source::Dynsym),
+        ];
+        let mut names = vec![];
+        let mut content = vec![];
+        merge_symbols(
+            tables.into_iter(),
+            &[],
+            &mut names,
+            &mut content,
+            &Diagnostics::new(),
+        );
+
+        assert_eq!(names, ["foo", "bar"]);
+        let sources: Vec<Source> = content.iter().map(|symbol| symbol.source).collect();
+        assert_eq!(sources, [Source::Symtab, Source::Dynsym]);
     }
 }
@@ -1,30 +1,29 @@
 
 
 use std::env;
-use std::fs;
-use std::io;
-use std::path::PathBuf;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::thread;
 
+use memmap2::Mmap;
+use sha2::{Digest, Sha256};
 
-pub fn is_linux() -> bool {
-    env::consts::OS == "linux"
-}
 
-pub fn is_arch_match(arch: &str) -> bool {
-    let current_arch = env::consts::ARCH;
-    match arch {
-        "x86_64" => current_arch == "x86_64",
-        "x86" => current_arch == "x86",
-        "aarch64" => current_arch == "aarch64",
-        "arm" => current_arch == "arm",
-        _ => false
-    }
+/// Map the file read-only, so large binaries are paged in on demand
+/// instead of being copied into memory up front.
+pub fn map_file(path: &Path) -> io::Result<Mmap> {
+    let file = File::open(path)?;
+    // SAFETY: the mapping is read-only; like every viewer working on a live file
+    // we accept that another process truncating it underneath us is undefined.
+    unsafe { Mmap::map(&file) }
 }
 
-pub fn find_executable(name: &PathBuf) -> io::Result<(PathBuf, Vec<u8>)> {
+pub fn find_executable(name: &PathBuf) -> io::Result<(PathBuf, Mmap)> {
     // First try the file directly
     if name.exists() {
-        return Ok((name.clone(), fs::read(name)?));
+        return Ok((name.clone(), map_file(name)?));
     }
 
     // If the path is absolute or contains directory components, don't search PATH
@@ -40,7 +39,7 @@ pub fn find_executable(name: &PathBuf) -> io::Result<(PathBuf, Vec<u8>)> {
         for dir in env::split_paths(&paths) {
             let full_path = dir.join(name);
             if full_path.exists() {
-                let buffer = fs::read(&full_path)?;
+                let buffer = map_file(&full_path)?;
                 
                 // Check for shebang
                 if buffer.len() > 2 && buffer[0] == b'#' && buffer[1] == b'!' {
@@ -76,4 +75,32 @@ pub fn find_executable(name: &PathBuf) -> io::Result<(PathBuf, Vec<u8>)> {
     ))
 }
 
+/// Compute the SHA-256 of a file on a background thread.
+///
+/// The file is streamed in fixed-size chunks, so neither startup time nor
+/// memory use grow with the file size. The returned cell is filled once the
+/// hash is ready.
+pub fn spawn_file_hash(path: &Path) -> Arc<OnceLock<String>> {
+    let result = Arc::new(OnceLock::new());
+    let cell = result.clone();
+    let path = path.to_path_buf();
+    thread::spawn(move || {
+        let hash = hash_file(&path).unwrap_or_else(|e| format!("Failed to hash file: {}", e));
+        let _ = cell.set(hash);
+    });
+    result
+}
 
+fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut chunk = vec![0u8; 1 << 20];
+    loop {
+        let n = file.read(&mut chunk)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&chunk[..n]);
+    }
+    Ok(format!("{:X}", hasher.finalize()))
+}
@@ -0,0 +1,175 @@
+//! Cross references: which instructions call, jump to or access each address.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+
+use elf::abi;
+use elf::endian::AnyEndian;
+use elf::ElfBytes;
+
+use crate::disasm::{self, Flow, FormatOptions, Step, SymbolMap};
+use crate::utils::map_file;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RefKind {
+    Call,
+    Jump,
+    /// Loads, stores and address computations (`lea`, `adrp` + `add`, ...).
+    Data,
+}
+
+impl RefKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            RefKind::Call => "call",
+            RefKind::Jump => "jump",
+            RefKind::Data => "data",
+        }
+    }
+}
+
+/// An instruction referring to an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reference {
+    pub from: u64,
+    pub to: u64,
+    pub kind: RefKind,
+}
+
+/// Every code-to-code and code-to-data reference of the file, by target.
+pub struct XrefIndex {
+    targets: BTreeMap<u64, Vec<Reference>>,
+}
+
+impl XrefIndex {
+    /// Build the index in a background thread, like the instruction set
+    /// scan; it is empty when the file can't be read again.
+    pub fn spawn_build(path: &Path) -> Arc<OnceLock<XrefIndex>> {
+        let result = Arc::new(OnceLock::new());
+        let cell = result.clone();
+        let path = path.to_path_buf();
+        thread::spawn(move || {
+            let index = map_file(&path)
+                .ok()
+                .and_then(|mapping| Some(XrefIndex::build(&crate::elf::parse(&mapping).ok()?)))
+                .unwrap_or(XrefIndex {
+                    targets: BTreeMap::new(),
+                });
+            let _ = cell.set(index);
+        });
+        result
+    }
+
+    /// Decode every executable section once and record what each instruction refers to.
+    pub fn build(elf: &ElfBytes<'_, AnyEndian>) -> XrefIndex {
+        let mut index = XrefIndex {
+            targets: BTreeMap::new(),
+        };
+        let Ok(mut disassembler) =
+            disasm::for_elf(elf, Rc::new(SymbolMap::new()), FormatOptions::default())
+        else {
+            return index;
+        };
+        let Some(shdrs) = elf.section_headers() else {
+            return index;
+        };
+        for shdr in shdrs.iter().filter(|shdr| {
+            shdr.sh_flags & abi::SHF_EXECINSTR as u64 != 0 && shdr.sh_type != abi::SHT_NOBITS
+        }) {
+            let Ok((code, None)) = elf.section_data(&shdr) else {
+                continue;
+            };
+            for step in disassembler.steps(code, shdr.sh_addr, false) {
+                index.insert(&step);
+            }
+        }
+        index
+    }
+
+    /// Record what the instruction of `step` refers to.
+    fn insert(&mut self, step: &Step) {
+        let branch = match step.flow {
+            Flow::Call(Some(to)) => Some((to, RefKind::Call)),
+            Flow::Jump(Some(to)) | Flow::Branch(to) => Some((to, RefKind::Jump)),
+            _ => None,
+        };
+        let data = step.data.map(|to| (to, RefKind::Data));
+        for (to, kind) in branch.into_iter().chain(data) {
+            self.targets.entry(to).or_default().push(Reference {
+                from: step.address,
+                to,
+                kind,
+            });
+        }
+    }
+
+    /// References into `[address, address + size)` from outside of it, so a
+    /// function's own branches are left out. A size of 0 means the address alone.
+    /// Sorted by the referring address; sections decoded twice (overlapping
+    /// headers) don't list an instruction twice.
+    pub fn references_to(&self, address: u64, size: u64) -> Vec<Reference> {
+        let end = address.saturating_add(size.max(1));
+        let mut references: Vec<Reference> = self
+            .targets
+            .range(address..end)
+            .flat_map(|(_, references)| references.iter())
+            .filter(|reference| reference.from < address || reference.from >= end)
+            .copied()
+            .collect();
+        references.sort_by_key(|reference| (reference.from, reference.to, reference.kind));
+        references.dedup();
+        references
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(address: u64, flow: Flow, data: Option<u64>) -> Step {
+        Step {
+            address,
+            len: 4,
+            flow,
+            data,
+        }
+    }
+
+    #[test]
+    fn references_are_sorted_and_unique() {
+        let mut index = XrefIndex {
+            targets: BTreeMap::new(),
+        };
+        let steps = [
+            step(0x3000, Flow::Call(Some(0x1000)), None),
+            step(0x2000, Flow::Jump(Some(0x1008)), None),
+            step(0x2004, Flow::Next, Some(0x1000)),
+            // 函数内部的分支不算
+            step(0x1004, Flow::Branch(0x1000), None),
+            step(0x1010, Flow::Call(None), None),
+        ];
+        // 同一段代码解码两次
+        for step in steps.iter().chain(&steps) {
+            index.insert(step);
+        }
+
+        let references: Vec<(u64, u64, RefKind)> = index
+            .references_to(0x1000, 0x10)
+            .iter()
+            .map(|reference| (reference.from, reference.to, reference.kind))
+            .collect();
+        assert_eq!(
+            references,
+            [
+                (0x2000, 0x1008, RefKind::Jump),
+                (0x2004, 0x1000, RefKind::Data),
+                (0x3000, 0x1000, RefKind::Call),
+            ]
+        );
+        assert_eq!(index.references_to(0x1008, 0).len(), 1);
+        assert!(index.references_to(0x1010, 0).is_empty());
+    }
+}